@@ -88,6 +88,52 @@ pub struct CompilerConfig {
     
 }
 
+/// Sanitizers tests can be compiled & ran with.
+#[derive(
+    Serialize, Deserialize, 
+    clap::ValueEnum, 
+    strum_macros::Display,
+    Clone, Copy, Debug, PartialEq
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Sanitizer {
+
+    /// AddressSanitizer, out of bounds accesses & use after free.
+    Address,
+
+    /// UndefinedBehaviorSanitizer.
+    Undefined,
+
+    /// LeakSanitizer, memory leaks.
+    Leak,
+
+    /// ThreadSanitizer, data races, can't be combined
+    /// with [Sanitizer::Address] or [Sanitizer::Leak].
+    Thread
+
+}
+
+/// How the tests of a file are built into harnesses.
+#[derive(
+    Serialize, Deserialize, 
+    clap::ValueEnum, 
+    strum_macros::Display,
+    Clone, Copy, Debug, PartialEq
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum HarnessMode {
+
+    /// A single binary per file dispatching to its tests by name,
+    /// tests with different compiler settings get their own binary.
+    File,
+
+    /// A binary per test, recompiling the file for every test.
+    Test
+
+}
+
 /// A path to parse for the recipe.
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct ParsePath {
@@ -128,7 +174,36 @@ pub struct Recipe {
 
     #[serde(rename = "parse")] 
     /// Paths/files to parse.
-    pub parse_path: Vec<ParsePath>
+    pub parse_path: Vec<ParsePath>,
+
+    /// Parse the recipes files with their includes resolved.
+    pub full_parse: Option<bool>,
+
+    #[serde(default = "Vec::new")]
+    #[serde(deserialize_with = "serde_tokenize_strings_and_vec")]
+    /// Function prefixes that mark a test, replaces [Run::prefix]
+    /// for this recipe.
+    pub prefix: Vec<String>,
+
+    /// Default for [crate::test::Settings::standalone] inside of this recipe.
+    pub standalone: Option<bool>,
+
+    #[serde(default = "Vec::new")]
+    #[serde(deserialize_with = "serde_tokenize_strings_and_vec")]
+    /// Object files/static libraries produced by the build that tests 
+    /// which aren't standalone are linked against.
+    pub objects: Vec<String>,
+
+    #[serde(default = "Vec::new")]
+    /// Sanitizers the recipes tests are compiled & ran with,
+    /// replaces [Run::sanitize] for this recipe.
+    pub sanitize: Vec<Sanitizer>,
+
+    #[serde(default = "Vec::new")]
+    #[serde(deserialize_with = "serde_tokenize_strings_and_vec")]
+    /// Valgrind suppression files used when the recipes
+    /// tests run under valgrind.
+    pub suppressions: Vec<String>
 
 }
 
@@ -169,14 +244,267 @@ pub struct Run {
     /// by [`find_config`].
     pub config_path: Option<PathBuf>,
 
+    #[arg(long = "full-parse")]
+    #[serde(default)]
+    /// Resolve includes while parsing files for tests with the `-I` / `-D`
+    /// flags from the compiler config, instead of parsing every file by itself.
+    pub full_parse: bool,
+
+    #[arg(long = "prefix")]
+    #[clap(value_delimiter = ' ')]
+    #[serde(default = "Vec::new")]
+    #[serde(deserialize_with = "serde_tokenize_strings_and_vec")]
+    /// Function prefixes that mark a test, if empty 
+    /// [crate::defaults::DEFAULT_FUNCTION_PREFIX] is used.
+    pub prefix: Vec<String>,
+
+    #[arg(long = "sanitize", value_delimiter = ',')]
+    #[serde(default = "Vec::new")]
+    /// Compile & run tests with sanitizers, for example
+    /// `--sanitize address,undefined`.
+    pub sanitize: Vec<Sanitizer>,
+
+    #[arg(long = "valgrind")]
+    #[serde(default)]
+    /// Run every test under valgrind's memcheck, for when
+    /// sanitizers aren't available.
+    pub valgrind: bool,
+
+    #[arg(long = "coverage")]
+    #[serde(default)]
+    /// Build tests with coverage instrumentation & write a
+    /// coverage summary, lcov & HTML report after the run.
+    pub coverage: bool,
+
+    #[arg(long = "bench")]
+    #[serde(default)]
+    /// Also run the benchmarks, tests named `<prefix>bench_*`,
+    /// implied by `--baseline` & `--compare`.
+    pub bench: bool,
+
+    #[arg(long = "baseline")]
+    #[serde(skip)]
+    /// Save the benchmark results as the baseline `<name>`.
+    pub baseline: Option<String>,
+
+    #[arg(long = "compare")]
+    #[serde(skip)]
+    /// Compare the benchmark results against the baseline `<name>`,
+    /// regressions beyond the threshold fail the run.
+    pub compare: Option<String>,
+
+    #[arg(long = "bench-threshold")]
+    #[serde(default)]
+    /// Percent a benchmark may be slower than its baseline, if
+    /// not set [crate::defaults::DEFAULT_BENCH_THRESHOLD] is used.
+    pub bench_threshold: Option<f64>,
+
+    #[arg(long = "harness")]
+    #[serde(default)]
+    /// Build a harness binary per file or per test, overwritten by the
+    /// `harness` setting. Per file if not set.
+    pub harness: Option<HarnessMode>,
+
+    #[arg(long = "no-cache")]
+    #[serde(default)]
+    /// Always compile the tests instead of reusing the binaries
+    /// cached inside of `.cesty/cache` by previous runs.
+    pub no_cache: bool,
+
+    #[arg(long = "cache-size")]
+    #[serde(default)]
+    /// Size in MiB the compilation cache is kept under, if not
+    /// set [crate::defaults::DEFAULT_CACHE_SIZE] is used.
+    pub cache_size: Option<u64>,
+
+    #[arg(long = "keep")]
+    #[serde(default)]
+    /// Amount of the most recent batches kept, older ones are removed
+    /// at the start of every run & by `cesty clean`. If neither this nor
+    /// `--older-than` is set [crate::defaults::DEFAULT_KEPT_BATCHES] are kept.
+    pub keep: Option<usize>,
+
+    #[arg(long = "older-than")]
+    #[serde(default)]
+    /// Remove the batches older than this, for example `7d`, `12h` or `30m`.
+    pub older_than: Option<String>,
+
+    #[arg(long = "keep-artifacts")]
+    #[serde(skip)]
+    /// Don't remove any old batches at the start of this run.
+    pub keep_artifacts: bool,
+
+    #[arg(long = "build-dir")]
+    #[serde(default)]
+    /// Directory the batches are placed in instead of `.cesty`, relative
+    /// to the config. Caches, baselines & corpora stay inside of `.cesty`.
+    pub build_dir: Option<PathBuf>,
+
+    #[arg(long = "wait")]
+    #[serde(skip)]
+    /// Wait for other cesty runs using the same `.cesty` directory
+    /// to release it instead of failing.
+    pub wait: bool,
+
+    #[arg(long = "retries")]
+    #[serde(default)]
+    /// Times a failing test is retried, tests that pass on a retry are
+    /// reported as flaky. Overwritten by the `retries` test setting.
+    pub retries: Option<u32>,
+
+    #[arg(long = "fail-on-flaky")]
+    #[serde(default)]
+    /// Fail the run if any test was flaky.
+    pub fail_on_flaky: bool,
+
+    #[arg(long = "include-ignored")]
+    #[serde(skip)]
+    /// Also run the tests that are skipped through `skip` or `run = false`.
+    pub include_ignored: bool,
+
+    #[arg(long = "shuffle")]
+    #[serde(default)]
+    /// Run the files & the tests inside of every file in a random
+    /// order, to find tests that depend on each other, implied by `--seed`.
+    pub shuffle: bool,
+
+    #[arg(long = "seed")]
+    #[serde(skip)]
+    /// Seed of `--shuffle`, random if not set. Exported to the
+    /// tests as `CESTY_SEED` & property tests without their own
+    /// `seed` derive theirs from it.
+    pub seed: Option<u64>,
+
+    #[arg(long = "failed", conflicts_with = "failed_first")]
+    #[serde(skip)]
+    /// Only run the tests that did not pass in the latest run
+    /// recorded inside of `.cesty/history`.
+    pub failed: bool,
+
+    #[arg(long = "failed-first")]
+    #[serde(skip)]
+    /// Run the tests that did not pass in the latest recorded
+    /// run before all the other tests.
+    pub failed_first: bool,
+
     #[command(flatten)]
     pub compiler: Option<CompilerConfig>,
 
+    #[clap(skip)]
+    #[serde(default)]
+    /// Tests (`file::name`) reported separately that never fail the
+    /// run, written by `cesty flaky --quarantine`.
+    pub quarantine: Vec<String>,
+
     #[clap(skip)]
     pub recipes: Vec<Recipe>
 
 }
 
+/// Arguments for "cesty fuzz ...", the [Run] options select
+/// the files & compiler settings the fuzz target is found with.
+#[derive(Args, Clone, Debug, Default)]
+pub struct Fuzz {
+
+    /// Fuzz target to run, its full name or the name without the prefix
+    /// (`cesty_fuzz_parse` or `fuzz_parse`).
+    pub target: String,
+
+    #[arg(long = "max-time")]
+    /// Seconds to fuzz for, until the first crash if not set.
+    pub max_time: Option<u64>,
+
+    #[command(flatten)]
+    pub run: Run
+
+}
+
+/// What "cesty cache ..." does with the compilation cache.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default)]
+pub enum CacheAction {
+
+    /// Print the size of the cache & how often it was hit.
+    #[default]
+    Stats,
+
+    /// Remove every cached binary.
+    Clear
+
+}
+
+/// Arguments for "cesty cache ...", the [Run] options select
+/// the config & the `.cesty` directory the cache is inside of.
+#[derive(Args, Clone, Debug, Default)]
+pub struct Cache {
+
+    /// What to do with the cache.
+    pub action: CacheAction,
+
+    #[command(flatten)]
+    pub run: Run
+
+}
+
+/// Arguments for "cesty clean ...", without `--all` the batches
+/// are removed according to [Run::keep] & [Run::older_than].
+#[derive(Args, Clone, Debug, Default)]
+pub struct Clean {
+
+    #[arg(long = "all", conflicts_with_all = ["keep", "older_than"])]
+    /// Remove every batch, including the latest one.
+    pub all: bool,
+
+    #[command(flatten)]
+    pub run: Run
+
+}
+
+/// Arguments for "cesty history", the [Run] options select
+/// the `.cesty` directory the history is inside of.
+#[derive(Args, Clone, Debug, Default)]
+pub struct History {
+
+    #[command(flatten)]
+    pub run: Run
+
+}
+
+/// Arguments for "cesty diff ...", runs are selected by their number
+/// inside of `cesty history`, their id or a unique start of it.
+#[derive(Args, Clone, Debug, Default)]
+pub struct Diff {
+
+    /// The older run, the one before the latest if not set.
+    pub run_a: Option<String>,
+
+    /// The newer run, the latest if not set.
+    pub run_b: Option<String>,
+
+    #[command(flatten)]
+    pub run: Run
+
+}
+
+/// Arguments for "cesty flaky ...", the [Run] options select
+/// the config & the `.cesty` directory the history is inside of.
+#[derive(Args, Clone, Debug, Default)]
+pub struct Flaky {
+
+    #[arg(long = "runs")]
+    /// Amount of the newest runs looked at, if not set
+    /// [crate::defaults::DEFAULT_FLAKY_RUNS] is used.
+    pub runs: Option<usize>,
+
+    #[arg(long = "quarantine")]
+    /// Add the flaky tests to the `quarantine` list of the config,
+    /// quarantined tests are reported separately & never fail the run.
+    pub quarantine: bool,
+
+    #[command(flatten)]
+    pub run: Run
+
+}
+
 /// Config initialization options.
 #[derive(Args, Clone, Debug, Default)]
 pub struct InitConfigOptions {
@@ -226,6 +554,27 @@ pub enum Commands {
     /// Run cesty inside the current PWD (or some other PWD specified with args).
     Run(Run),
 
+    /// Run a fuzz target with libFuzzer, crashing inputs
+    /// are replayed as regression tests by `cesty run`.
+    Fuzz(Fuzz),
+
+    /// Show the size of the compilation cache or clear it.
+    Cache(Cache),
+
+    /// Remove old batches of compiled tests from `.cesty` (or `--build-dir`).
+    Clean(Clean),
+
+    /// List the results of past runs stored inside of `.cesty/history`.
+    History(History),
+
+    /// Compare the results of two past runs, by default the latest
+    /// run against the one before it.
+    Diff(Diff),
+
+    /// List the tests whose outcome changed between runs of the same
+    /// binary, ranked by how often it changed.
+    Flaky(Flaky),
+
     /// Initalize a cesty config file.
     #[clap(subcommand)]
     Init(ConfigLanguage),
@@ -249,6 +598,28 @@ impl Run {
         None
         
     }
+
+    /// Are benchmarks ran, set by `--bench`, `--baseline` or `--compare`.
+    pub fn runs_benchmarks(&self) -> bool {
+
+        self.bench || self.baseline.is_some() || self.compare.is_some()
+
+    }
+
+    /// Are files & tests shuffled, set by `--shuffle` or `--seed`.
+    pub fn shuffles(&self) -> bool {
+
+        self.shuffle || self.seed.is_some()
+
+    }
+
+    /// Are the failed tests of the latest run looked up,
+    /// set by `--failed` or `--failed-first`.
+    pub fn reruns_failed(&self) -> bool {
+
+        self.failed || self.failed_first
+
+    }
     
 }
 
@@ -551,6 +922,96 @@ impl Config {
                     command: Commands::Run(full_run_conf)
                 }, warnings))
 
+            }
+            Commands::Fuzz(mut fuzz_conf) => {
+
+                fuzz_conf.run = match fuzz_conf.run.reinit() {
+                    Ok((initialized, mut ret_warnings)) => {
+                        warnings.append(&mut ret_warnings);
+                        initialized
+                    }
+                    Err(err) => return Err(debugpush!(err))
+                };
+
+                Ok((Config {
+                    command: Commands::Fuzz(fuzz_conf)
+                }, warnings))
+
+            }
+            Commands::Cache(mut cache_conf) => {
+
+                cache_conf.run = match cache_conf.run.reinit() {
+                    Ok((initialized, mut ret_warnings)) => {
+                        warnings.append(&mut ret_warnings);
+                        initialized
+                    }
+                    Err(err) => return Err(debugpush!(err))
+                };
+
+                Ok((Config {
+                    command: Commands::Cache(cache_conf)
+                }, warnings))
+
+            }
+            Commands::Clean(mut clean_conf) => {
+
+                clean_conf.run = match clean_conf.run.reinit() {
+                    Ok((initialized, mut ret_warnings)) => {
+                        warnings.append(&mut ret_warnings);
+                        initialized
+                    }
+                    Err(err) => return Err(debugpush!(err))
+                };
+
+                Ok((Config {
+                    command: Commands::Clean(clean_conf)
+                }, warnings))
+
+            }
+            Commands::History(mut history_conf) => {
+
+                history_conf.run = match history_conf.run.reinit() {
+                    Ok((initialized, mut ret_warnings)) => {
+                        warnings.append(&mut ret_warnings);
+                        initialized
+                    }
+                    Err(err) => return Err(debugpush!(err))
+                };
+
+                Ok((Config {
+                    command: Commands::History(history_conf)
+                }, warnings))
+
+            }
+            Commands::Diff(mut diff_conf) => {
+
+                diff_conf.run = match diff_conf.run.reinit() {
+                    Ok((initialized, mut ret_warnings)) => {
+                        warnings.append(&mut ret_warnings);
+                        initialized
+                    }
+                    Err(err) => return Err(debugpush!(err))
+                };
+
+                Ok((Config {
+                    command: Commands::Diff(diff_conf)
+                }, warnings))
+
+            }
+            Commands::Flaky(mut flaky_conf) => {
+
+                flaky_conf.run = match flaky_conf.run.reinit() {
+                    Ok((initialized, mut ret_warnings)) => {
+                        warnings.append(&mut ret_warnings);
+                        initialized
+                    }
+                    Err(err) => return Err(debugpush!(err))
+                };
+
+                Ok((Config {
+                    command: Commands::Flaky(flaky_conf)
+                }, warnings))
+
             }
             Commands::Init(init_conf) => {
 
@@ -752,6 +1213,15 @@ impl Run {
 
         }
 
+        self
+            .prefix
+                .retain(|s| !s.is_empty());
+
+        self.build_dir = self
+            .build_dir
+                .as_ref()
+                .map(|x| current_directory.join(x.clean()));
+
         Ok(())
 
     }
@@ -771,6 +1241,40 @@ impl Run {
 
             config_path: self.config_path.to_owned(),
             list_paths:  self.list_paths.to_owned(),
+            full_parse:  self.full_parse || config.full_parse,
+            prefix: 
+                if !self.prefix.is_empty() {
+                    self.prefix.to_owned()
+                } else {
+                    config.prefix
+                },
+            sanitize:
+                if !self.sanitize.is_empty() {
+                    self.sanitize.to_owned()
+                } else {
+                    config.sanitize
+                },
+            valgrind:    self.valgrind || config.valgrind,
+            coverage:    self.coverage || config.coverage,
+            bench:       self.bench || config.bench,
+            baseline:    self.baseline.to_owned(),
+            compare:     self.compare.to_owned(),
+            bench_threshold: self.bench_threshold.or(config.bench_threshold),
+            harness:     self.harness.or(config.harness),
+            no_cache:    self.no_cache || config.no_cache,
+            cache_size:  self.cache_size.or(config.cache_size),
+            keep:        self.keep.or(config.keep),
+            older_than:  self.older_than.to_owned().or(config.older_than),
+            keep_artifacts: self.keep_artifacts,
+            wait:        self.wait,
+            build_dir:   self.build_dir.to_owned().or(config.build_dir),
+            retries:     self.retries.or(config.retries),
+            fail_on_flaky: self.fail_on_flaky || config.fail_on_flaky,
+            include_ignored: self.include_ignored,
+            shuffle:     self.shuffle || config.shuffle,
+            seed:        self.seed,
+            failed:      self.failed,
+            failed_first: self.failed_first,
 
             compiler: 
             if self.compiler.is_some() 
@@ -826,6 +1330,8 @@ impl Run {
                 None
             },
 
+            quarantine: config.quarantine,
+
             recipes: config.recipes
         };
 
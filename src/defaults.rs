@@ -21,21 +21,243 @@ pub const DEFAULT_CONFIG_FILENAME: &'static str = "config";
 /// Private directory used by cesty, kind of like `.git`.
 pub const DEFAULT_PRIVATE_DIRECTORY: &'static str = ".cesty";
 
-/// Function prefix to detect what function is used for testing.
+/// Function prefix to detect what function is used for testing,
+/// used when no `prefix` is set in the config, recipe or CLI.
 /// 
 /// Example
 /// -------
 /// Valid tests.
 /// ```C
-/// int cesty_addition_test(int a, int b) { ... }
-/// int cesty_image_compression_test(struct bitmap image) { ... }
+/// bool cesty_addition_test(void) { ... }
+/// int cesty_image_compression_test(void) { ... }
 /// ```
 pub const DEFAULT_FUNCTION_PREFIX: &'static str = "cesty_";
 
+/// Annotation that marks a function as a test regardless of its name.
+/// 
+/// Example
+/// -------
+/// ```C
+/// __attribute__((annotate("cesty"))) bool ut_parse_empty(void) { ... }
+/// ```
+pub const TEST_ANNOTATION: &'static str = "cesty";
+
+/// Line inside of a function comment that marks the function as a test
+/// regardless of its name.
+/// 
+/// Example
+/// -------
+/// ```C
+/// /// #?cesty
+/// bool test_parse_empty(void) { ... }
+/// ```
+pub const TEST_MARKER: &'static str = "#?cesty";
+
+/// Line inside of a comment that marks the rest of the comment as
+/// the file wide config, applied to every test inside of the file.
+/// 
+/// Example
+/// -------
+/// ```C
+/// // #?cesty-file
+/// // [settings]
+/// // standalone = false
+/// ```
+pub const FILE_CONFIG_MARKER: &'static str = "#?cesty-file";
+
+/// Name of a fixture (without the prefix) ran once before
+/// all the tests of a file.
+/// 
+/// Example
+/// -------
+/// ```C
+/// bool cesty_setup(void) { ... }
+/// bool cesty_teardown(void) { ... }
+/// void *cesty_before_each(void) { ... }
+/// bool cesty_after_each(void *context) { ... }
+/// ```
+pub const FIXTURE_SETUP: &'static str = "setup";
+
+/// Name of a fixture (without the prefix) ran once after
+/// all the tests of a file.
+pub const FIXTURE_TEARDOWN: &'static str = "teardown";
+
+/// Name of a fixture (without the prefix) ran inside of
+/// the test process before the test.
+pub const FIXTURE_BEFORE_EACH: &'static str = "before_each";
+
+/// Name of a fixture (without the prefix) ran inside of
+/// the test process after the test.
+pub const FIXTURE_AFTER_EACH: &'static str = "after_each";
+
+/// Start of a test name (without the prefix) that marks the
+/// test as a benchmark, ran repeatedly instead of once.
+/// 
+/// Example
+/// -------
+/// ```C
+/// void cesty_bench_sort(void) { ... }
+/// ```
+pub const BENCH_NAME_PREFIX: &'static str = "bench_";
+
+/// Directory inside of [DEFAULT_PRIVATE_DIRECTORY] that
+/// benchmark results & baselines are stored in.
+pub const BENCH_DIRECTORY: &'static str = "bench";
+
+/// Name of the benchmark results of the latest run, stored
+/// inside of [BENCH_DIRECTORY] next to the named baselines.
+pub const BENCH_LATEST_NAME: &'static str = "latest";
+
+/// Environment variable naming the file a benchmark harness
+/// writes its samples into.
+pub const BENCH_REPORT_ENVIRONMENT: &'static str = "CESTY_BENCH_FILE";
+
+/// Environment variable with the amount of samples a benchmark takes.
+pub const BENCH_SAMPLES_ENVIRONMENT: &'static str = "CESTY_BENCH_SAMPLES";
+
+/// Environment variable with the milliseconds a benchmark is
+/// measured for, a tenth of it is spent warming up before.
+pub const BENCH_TIME_ENVIRONMENT: &'static str = "CESTY_BENCH_TIME_MS";
+
+/// Amount of samples a benchmark takes when no
+/// `bench_samples` is set in the test or file config.
+pub const DEFAULT_BENCH_SAMPLES: u64 = 20;
+
+/// Milliseconds a benchmark is measured for when no
+/// `bench_time` is set in the test or file config.
+pub const DEFAULT_BENCH_TIME: u64 = 1000;
+
+/// Percent the mean of a benchmark may be slower than its
+/// baseline before it's reported as a regression.
+pub const DEFAULT_BENCH_THRESHOLD: f64 = 5.0;
+
+/// Start of a test name (without the prefix) that marks the
+/// function as a fuzz target for `cesty fuzz <target>`.
+/// 
+/// Example
+/// -------
+/// ```C
+/// int cesty_fuzz_parse(const uint8_t *data, size_t size) { ... }
+/// ```
+pub const FUZZ_NAME_PREFIX: &'static str = "fuzz_";
+
+/// Directory inside of [DEFAULT_PRIVATE_DIRECTORY] holding
+/// a directory per fuzz target.
+pub const FUZZ_DIRECTORY: &'static str = "fuzz";
+
+/// Directory inside of a fuzz targets directory holding its corpus.
+pub const FUZZ_CORPUS_DIRECTORY: &'static str = "corpus";
+
+/// Directory inside of a fuzz targets directory holding the inputs
+/// that crashed it, replayed by `cesty run` as regression tests.
+pub const FUZZ_CRASH_DIRECTORY: &'static str = "crashes";
+
+/// Compiler used for fuzz targets when the configured
+/// compiler isn't clang, libFuzzer is only available in clang.
+pub const DEFAULT_FUZZ_COMPILER: &'static str = "clang";
+
+/// Amount of rows a property test runs when `runs` isn't set.
+pub const DEFAULT_PROPERTY_RUNS: u64 = 100;
+
+/// Maximum amount of shrinking rounds of a failing property.
+pub const MAX_PROPERTY_SHRINKS: usize = 200;
+
+/// Environment variable naming the file with the
+/// input rows of a property test.
+pub const PROPERTY_INPUT_ENVIRONMENT: &'static str = "CESTY_PROPERTY_INPUT";
+
+/// Environment variable naming the file the harness writes
+/// the index of the row it's running into.
+pub const PROPERTY_PROGRESS_ENVIRONMENT: &'static str = "CESTY_PROPERTY_PROGRESS";
+
+/// Environment variable holding the seed of the run, set
+/// for every test when `--shuffle` or `--seed` is used.
+pub const SEED_ENVIRONMENT: &'static str = "CESTY_SEED";
+
+/// Directory inside of [DEFAULT_PRIVATE_DIRECTORY] holding the
+/// compiled harnesses reused between runs.
+pub const CACHE_DIRECTORY: &'static str = "cache";
+
+/// File inside of [CACHE_DIRECTORY] counting the cache hits & misses.
+pub const CACHE_STATS_NAME: &'static str = "stats.toml";
+
+/// Size in MiB the [CACHE_DIRECTORY] is kept under when no
+/// `cache_size` is set, the least recently used binaries are removed first.
+pub const DEFAULT_CACHE_SIZE: u64 = 512;
+
+/// Directory inside of [DEFAULT_PRIVATE_DIRECTORY] holding
+/// the results of past runs, see [crate::test::history].
+pub const HISTORY_DIRECTORY: &'static str = "history";
+
+/// Amount of runs kept inside of [HISTORY_DIRECTORY],
+/// the oldest ones are removed first.
+pub const MAX_HISTORY_RUNS: usize = 100;
+
+/// Amount of the newest runs `cesty flaky` looks at when no `--runs` is set.
+pub const DEFAULT_FLAKY_RUNS: usize = 20;
+
+/// Argument passed to a test harness to run the [FIXTURE_SETUP]
+/// fixture instead of the test.
+pub const HARNESS_SETUP_ARGUMENT: &'static str = "--cesty-setup";
+
+/// Argument passed to a test harness to run the [FIXTURE_TEARDOWN]
+/// fixture instead of the test.
+pub const HARNESS_TEARDOWN_ARGUMENT: &'static str = "--cesty-teardown";
+
+/// Exit code valgrind exits with when it found errors
+/// inside of a test, see [crate::test::valgrind].
+pub const VALGRIND_ERROR_EXIT_CODE: i32 = 98;
+
+/// Name of the bundled assertion header, placed inside of
+/// [ASSERTION_HEADER_DIRECTORY] of every test batch.
+pub const ASSERTION_HEADER_NAME: &'static str = "cesty.h";
+
+/// Directory inside of a test batch folder added to the include
+/// path of every test harness.
+pub const ASSERTION_HEADER_DIRECTORY: &'static str = "include";
+
+/// Environment variable naming the file a test harness writes
+/// its failed assertions into.
+pub const ASSERTION_REPORT_ENVIRONMENT: &'static str = "CESTY_REPORT_FILE";
+
+/// Exit code of a test harness whose [FIXTURE_BEFORE_EACH] or
+/// [FIXTURE_AFTER_EACH] fixture failed.
+pub const HARNESS_FIXTURE_FAILURE_CODE: i32 = 99;
+
+/// Value an integer returning test returns when it passes, 
+/// used when no `pass_value` is set in the test or file config.
+pub const DEFAULT_INT_PASS_VALUE: i64 = 0;
+
 /// Name of the default compiler when no compiler
 /// is specified.
 pub const DEFAULT_COMPILER_NAME: &'static str = "gcc";
 
+/// File next to the batches locked while a batch is created
+/// or old batches are removed, see [crate::test::lock].
+pub const BATCHES_LOCK_NAME: &'static str = "batches.cesty.lock";
+
+/// File inside of a test batch folder locked by the run using it.
+pub const BATCH_RUNNING_LOCK_NAME: &'static str = "run.cesty.lock";
+
+/// File inside of [CACHE_DIRECTORY] locked while the cache is written to.
+pub const CACHE_LOCK_NAME: &'static str = "cache.cesty.lock";
+
+/// File inside of [HISTORY_DIRECTORY] locked while a run is recorded.
+pub const HISTORY_LOCK_NAME: &'static str = "history.cesty.lock";
+
+/// Milliseconds a lock held by another run is waited on
+/// before failing, when `--wait` isn't set.
+pub const LOCK_GRACE_PERIOD: u64 = 2000;
+
+/// Amount of batches kept when neither `keep` nor `older_than` is set.
+pub const DEFAULT_KEPT_BATCHES: usize = 10;
+
+/// Symlink inside of the batches directory pointing at the most recent batch.
+pub const LATEST_BATCH_NAME: &'static str = "latest";
+
+/// File created inside of a test batch folder once the batch finishes.
+pub const BATCH_FINISH_LOCK_NAME: &'static str = "finish.cesty.lock";
+
 /// Amount of attempts at creating a unique directory name
 pub const MAX_BATCH_ROOT_NAME_CREATION_ATTEMPTS: usize = 10;
 
@@ -188,8 +188,9 @@ impl AlertCode {
         let mut fix_iter = self.fix.iter().peekable();
         while let Some(fix) = fix_iter.next() {
 
-            let line: usize = self.line;
-            // println!("line");
+            // `line` is the line of the first fix, others are offset from it.
+            let line: usize = (self.line + fix.relative_line)
+                .saturating_sub(self.fix.first().map(|first| first.relative_line).unwrap_or(0));
 
             // Get code snippet from relative line.
             let snippet: ColoredString = if code_lines.get(fix.relative_line).is_some() 
@@ -505,6 +506,56 @@ impl Alert {
 
     }
 
+    pub(crate) fn from_clang_diagnostic(
+        diagnostic: &crate::rustclang::Diagnostic,
+        debug:      Vec<AlertDebug>
+    ) -> Self {
+
+        use crate::rustclang::DiagnosticSeverity;
+
+        let description = match diagnostic.severity {
+            DiagnosticSeverity::Note    => "libclang emitted a note while parsing",
+            DiagnosticSeverity::Warning => "libclang emitted a warning while parsing",
+            DiagnosticSeverity::Error 
+            | DiagnosticSeverity::Fatal => "libclang failed to parse a part of the file"
+        }.to_owned();
+
+        let info = match std::fs::read_to_string(&diagnostic.file) {
+            Ok(file_contents) if diagnostic.line > 0 => AlertInfo {
+                description: description,
+                debug: debug,
+                example: Some(AlertExample::Code(AlertCode {
+                    line: diagnostic.line,
+                    file: diagnostic.file.clone(),
+                    code: file_contents,
+                    fix:  vec![AlertCodeFix {
+                        relative_line: diagnostic.line-1,
+                        column: diagnostic.column,
+                        comment: diagnostic.message.clone()
+                    }]
+                })),
+                note: vec![]
+            },
+            _ => AlertInfo {
+                description: description,
+                debug: debug,
+                example: None,
+                note: vec![
+                    format!("libclang reported the following for `{}`:", diagnostic.file),
+                    diagnostic.message.clone()
+                ]
+            }
+        };
+
+        match diagnostic.severity {
+            DiagnosticSeverity::Note
+            | DiagnosticSeverity::Warning => Alert::Warning(info),
+            DiagnosticSeverity::Error 
+            | DiagnosticSeverity::Fatal   => Alert::Error(info)
+        }
+
+    }
+
 }
 
 macro_rules! function {
@@ -93,9 +93,37 @@ pub fn init(init_conf: ConfigLanguage) -> Result<Vec<Alert>, Alert> {
 
         no_config:  false,
         list_paths: false,
+        full_parse: false,
+
+        prefix: vec![
+            crate::defaults::DEFAULT_FUNCTION_PREFIX.to_owned()
+        ],
 
         files: vec![],
 
+        sanitize: vec![],
+        valgrind: false,
+        coverage: false,
+        bench: false,
+        baseline: None,
+        compare: None,
+        bench_threshold: None,
+        harness: None,
+        no_cache: false,
+        cache_size: None,
+        keep: None,
+        older_than: None,
+        keep_artifacts: false,
+        build_dir: None,
+        wait: false,
+        retries: None,
+        fail_on_flaky: false,
+        include_ignored: false,
+        shuffle: false,
+        seed: None,
+        failed: false,
+        failed_first: false,
+
         compiler: Some(crate::arg_conf::CompilerConfig {
 
             name: Some("gcc".to_owned()),
@@ -109,6 +137,8 @@ pub fn init(init_conf: ConfigLanguage) -> Result<Vec<Alert>, Alert> {
 
         }),
 
+        quarantine: vec![],
+
         recipes: vec![
 
             crate::arg_conf::Recipe {
@@ -125,7 +155,13 @@ pub fn init(init_conf: ConfigLanguage) -> Result<Vec<Alert>, Alert> {
                         path: PathBuf::from("."),
                         recursive: Some(true)
                     }
-                ]
+                ],
+                full_parse: None,
+                prefix: vec![],
+                standalone: None,
+                objects: vec![],
+                sanitize: vec![],
+                suppressions: vec![]
 
             }
 
@@ -219,6 +255,7 @@ pub fn init(init_conf: ConfigLanguage) -> Result<Vec<Alert>, Alert> {
     if options.clean {
         match std::fs::remove_dir_all(DEFAULT_PRIVATE_DIRECTORY) {
             Ok(_) => (),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
             Err(err) => return error!{
                 debug: debuginfo!(),
                 description: format!("failed to remove private directory `{}`", DEFAULT_PRIVATE_DIRECTORY),
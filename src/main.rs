@@ -4,8 +4,13 @@
 //! library to be included in your code for running tests.
 //! 
 //! Cesty via. its configuration file scans the source code
-//! for function with the prefix `cesty_` and runs them,
-//! listing if they failed or not (like a test 0_o).
+//! for function with the prefix `cesty_` (or any prefix set in
+//! the config) and runs them, listing if they failed or not 
+//! (like a test 0_o).
+//! 
+//! Functions with other names can be marked as tests with
+//! `__attribute__((annotate("cesty")))` or a `/// #?cesty`
+//! line in their comment.
 //! 
 //! Due to this, for more complex projects the library/binary
 //! must be compiled prior to the test and the resulting 
@@ -43,11 +48,72 @@ mod lister;
 mod rustclang;
 mod test;
 
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 
 pub fn cesty(conf: arg_conf::Config) -> Result<(), Box<dyn std::error::Error>> {
 
-    let run_conf = match conf.command {
+    let mut run_conf = match conf.command {
         arg_conf::Commands::Run(run_conf) => run_conf,
+        arg_conf::Commands::Fuzz(fuzz_conf) => match test::fuzz::fuzz(&fuzz_conf) {
+            Ok(warnings) => {
+                for warning in warnings {eprintln!("{warning}")}
+                return Ok(())
+            },
+            Err(err) => {
+                eprintln!("{err}");
+                return Err(Box::new(err))
+            }
+        },
+        arg_conf::Commands::Cache(cache_conf) => match test::cache::cache(&cache_conf) {
+            Ok(warnings) => {
+                for warning in warnings {eprintln!("{warning}")}
+                return Ok(())
+            },
+            Err(err) => {
+                eprintln!("{err}");
+                return Err(Box::new(err))
+            }
+        },
+        arg_conf::Commands::Clean(clean_conf) => match test::batch::clean(&clean_conf) {
+            Ok(warnings) => {
+                for warning in warnings {eprintln!("{warning}")}
+                return Ok(())
+            },
+            Err(err) => {
+                eprintln!("{err}");
+                return Err(Box::new(err))
+            }
+        },
+        arg_conf::Commands::History(history_conf) => match test::history::history(&history_conf) {
+            Ok(warnings) => {
+                for warning in warnings {eprintln!("{warning}")}
+                return Ok(())
+            },
+            Err(err) => {
+                eprintln!("{err}");
+                return Err(Box::new(err))
+            }
+        },
+        arg_conf::Commands::Diff(diff_conf) => match test::history::diff(&diff_conf) {
+            Ok(warnings) => {
+                for warning in warnings {eprintln!("{warning}")}
+                return Ok(())
+            },
+            Err(err) => {
+                eprintln!("{err}");
+                return Err(Box::new(err))
+            }
+        },
+        arg_conf::Commands::Flaky(flaky_conf) => match test::flaky::flaky(&flaky_conf) {
+            Ok(warnings) => {
+                for warning in warnings {eprintln!("{warning}")}
+                return Ok(())
+            },
+            Err(err) => {
+                eprintln!("{err}");
+                return Err(Box::new(err))
+            }
+        },
         arg_conf::Commands::Init(init_conf) => match init::init(init_conf) {
             Ok(res) => {
                 for warning in res {eprintln!("{warning}")}
@@ -60,7 +126,13 @@ pub fn cesty(conf: arg_conf::Config) -> Result<(), Box<dyn std::error::Error>> {
         }
     };
     
-    let (list, _recipe) = match lister::list(&run_conf) {
+    if run_conf.shuffles() && run_conf.seed.is_none() {
+        run_conf.seed = Some(rand::random());
+    }
+
+    let mut shuffle_rng = run_conf.seed.map(StdRng::seed_from_u64);
+
+    let (mut list, recipe) = match lister::list(&run_conf) {
         Ok(((list, recipe), warnings)) => {
             for warning in warnings {eprintln!("{warning}")}
             (list, recipe)
@@ -80,9 +152,60 @@ pub fn cesty(conf: arg_conf::Config) -> Result<(), Box<dyn std::error::Error>> {
         
     }
 
+    if let Some(rng) = shuffle_rng.as_mut() {
+        list.shuffle(rng);
+    }
+
+    let mut rerun = match run_conf.reruns_failed() {
+        false => None,
+        true  => match test::history::Rerun::latest(&run_conf) {
+            Ok((rerun, warnings)) => {
+                for warning in warnings {eprintln!("{warning}")}
+                Some(rerun)
+            },
+            Err(err) => {
+                eprintln!("{err}");
+                return Err(Box::new(err))
+            }
+        }
+    };
+
+    if let Some(rerun) = rerun.as_ref() {
+        if rerun.is_empty() && run_conf.failed {
+            println!("every test passed in `{}`, nothing to re-run", rerun.id);
+            return Ok(())
+        }
+        list.sort_by_key(|path| !rerun.contains_file(path));
+    }
+
+    let batch_folder = match test::batch::TestBatchFolder::new(&run_conf) {
+        Ok(batch_folder) => batch_folder,
+        Err(err) => {
+            eprintln!("{err}");
+            return Err(Box::new(err))
+        }
+    };
+
+    if !run_conf.keep_artifacts {
+        match batch_folder.prune(&run_conf) {
+            Ok(warnings) => for warning in warnings {eprintln!("{warning}")},
+            Err(err) => {
+                eprintln!("{err}");
+                return Err(Box::new(err))
+            }
+        }
+    }
+
+    let mut results: Vec<test::run::TestResult> = vec![];
+
     for path in list {
 
-        let parsed_file = match test::extract::extract(path){
+        // Files without a failed test have nothing to re-run.
+        if run_conf.failed && rerun.as_ref().is_some_and(|rerun| !rerun.contains_file(&path)) {
+            continue
+        }
+
+        let mut parsed_file = match test::extract::extract(path, &run_conf, recipe){
             Ok((parsed_file, warnings)) => {
                 for warning in warnings {eprintln!("{warning}")}
                 parsed_file
@@ -93,27 +216,113 @@ pub fn cesty(conf: arg_conf::Config) -> Result<(), Box<dyn std::error::Error>> {
             }
         };
 
-        if parsed_file.test.is_empty() == true {
+        if let Some(rerun) = rerun.as_mut() {
+            rerun.select(&mut parsed_file, run_conf.failed);
+        }
+
+        if parsed_file.test.is_empty()
+        && parsed_file.fuzz.is_empty()
+        && (parsed_file.bench.is_empty() || !run_conf.runs_benchmarks()) {
             continue
         }
         
-        
-        println!("Found test inside of {:?}", &parsed_file.path);
+        let mut compilable_tests = match test::compilable::CompilableTest::from_parsed_file(
+            &parsed_file, &run_conf, recipe, &batch_folder
+        ) {
+            Ok((compilable_tests, warnings)) => {
+                for warning in warnings {eprintln!("{warning}")}
+                compilable_tests
+            },
+            Err(err) => {
+                eprintln!("{err}");
+                return Err(Box::new(err));
+            }
+        };
 
-        println!("{:#?}", parsed_file);
-        
-        test::compilable::CompilableTest::from_parsed_file(parsed_file, &run_conf);
+        if let Some(rng) = shuffle_rng.as_mut() {
+            compilable_tests.shuffle(rng);
+        }
 
-        // for test in parsed_file.test.iter() {
-        //     println!("{:?}", test.get_test_file_stem(&parsed_file));
-        //     println!("{:#?}", test);
-        // }
+        if let Some(rerun) = rerun.as_ref() {
+            compilable_tests.sort_by_key(|test| !rerun.contains(&test::run::identity(&test.file, &test.name)));
+        }
 
-        // println!("{:#?}",test::compilable::CompiledFolderLock::new(&run_conf));
+        results.append(&mut test::run::run_file(
+            compilable_tests,
+            &parsed_file.fixtures
+        ));
 
-        // println!("{:#?}", parsed_file);
+    }
 
+    if run_conf.runs_benchmarks() {
+        match test::bench::finish(&mut results, &run_conf, batch_folder.private_path()) {
+            Ok(warnings) => for warning in warnings {eprintln!("{warning}")},
+            Err(err) => {
+                eprintln!("{err}");
+                return Err(Box::new(err));
+            }
+        }
+    }
+
+    if let Some(rerun) = rerun.as_ref() {
+        for warning in rerun.missing() {eprintln!("{warning}")}
+    }
+
+    let mut summary = test::run::Summary::from_results(&results, &run_conf.quarantine);
+    summary.seed = run_conf.seed;
+    println!("{summary}");
+
+    for warning in test::history::finish(&results, &run_conf, batch_folder.path()) {
+        eprintln!("{warning}")
+    }
+
+    if run_conf.coverage {
+
+        let coverage = match test::coverage::Coverage::collect(batch_folder.path()) {
+            Ok((coverage, warnings)) => {
+                for warning in warnings {eprintln!("{warning}")}
+                coverage
+            },
+            Err(err) => {
+                eprintln!("{err}");
+                return Err(Box::new(err));
+            }
+        };
+
+        println!("{coverage}");
+
+        match coverage.write_reports(batch_folder.path()) {
+            Ok((info, html)) => println!("\nlcov: {}\nhtml: {}", info.to_string_lossy(), html.to_string_lossy()),
+            Err(err) => {
+                eprintln!("{err}");
+                return Err(Box::new(err));
+            }
+        }
+
+    }
 
+    if summary.unsuccessful() > 0 {
+        let err = error::Alert::Error(error::AlertInfo {
+            description: format!("{} out of {} tests did not pass", summary.unsuccessful(), results.len()),
+            debug: error::debuginfo!(),
+            example: None,
+            note: vec![]
+        });
+        eprintln!("{err}");
+        return Err(Box::new(err))
+    }
+
+    if run_conf.fail_on_flaky && summary.flaky > 0 {
+        let err = error::Alert::Error(error::AlertInfo {
+            description: format!("{} out of {} tests were flaky", summary.flaky, results.len()),
+            debug: error::debuginfo!(),
+            example: None,
+            note: vec![
+                "`--fail-on-flaky` is set, flaky tests fail the run.".to_owned()
+            ]
+        });
+        eprintln!("{err}");
+        return Err(Box::new(err))
     }
 
     return Ok(())
@@ -35,14 +35,72 @@ pub struct Clang<'a> {
 
 }
 
+/// How much of a file libclang is going to parse.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ParseMode {
+
+    /// Only the file itself is parsed, `#include`s are left
+    /// unresolved so types from headers are unknown.
+    #[default]
+    SingleFile,
+
+    /// Includes are resolved with the passed `-I` / `-D` flags,
+    /// giving full type information.
+    Full
+
+}
+
+/// Severity of a [Diagnostic], [libclang::CXDiagnostic_Ignored]
+/// diagnostics are never collected.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum DiagnosticSeverity {
+
+    Note,
+    Warning,
+    Error,
+    Fatal
+
+}
+
+/// A diagnostic returned by [libclang::clang_getDiagnostic]
+/// converted into Rust types.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+
+    pub severity: DiagnosticSeverity,
+    pub file:     String,
+    pub line:     usize,
+    pub column:   usize,
+    pub message:  String
+
+}
+
 pub trait Open<T, O> {
     fn open(path: T, args: O) -> Result<Self, Alert> where Self: Sized;
 }
 
 impl<'a> Open<&'a PathBuf, &'a str> for Clang<'a> {
 
+    /// Opens the file with [ParseMode::SingleFile] & whitespace separated `args`.
     fn open(file: &'a PathBuf, args: &'a str) -> Result<Self, Alert> {
 
+        let vec_args: Vec<String> = args
+            .to_owned()
+            .split_whitespace()
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect();
+
+        Clang::open(file, (vec_args.as_slice(), ParseMode::SingleFile))
+
+    }
+
+}
+
+impl<'a, 'b> Open<&'a PathBuf, (&'b [String], ParseMode)> for Clang<'a> {
+
+    fn open(file: &'a PathBuf, (args, mode): (&'b [String], ParseMode)) -> Result<Self, Alert> {
+
         let index = unsafe {
             let index = libclang::clang_createIndex(0, 0);
             if index.is_null() {
@@ -60,18 +118,11 @@ impl<'a> Open<&'a PathBuf, &'a str> for Clang<'a> {
             file.to_string_lossy().to_string()
         ).expect("Failed to convert &PathBuf into CString");
 
-        let vec_args: Vec<String> = args
-            .to_owned()
-            .split_whitespace()
-            .filter(|x| !x.is_empty())
-            .map(String::from)
-            .collect();
-
         let mut vec_cstring_args: Vec<CString> = vec![];
 
-        for arg in vec_args.into_iter() {
+        for arg in args.iter() {
 
-            let cstring_arg = match CString::new(arg) {
+            let cstring_arg = match CString::new(arg.as_str()) {
                 Ok(cstring) => cstring,
                 Err(err) => return error!{
                     description: "failed to convert `&str` into `CString`".to_owned(),
@@ -92,6 +143,17 @@ impl<'a> Open<&'a PathBuf, &'a str> for Clang<'a> {
 
         let final_args: *const *const i8 = vec_pointer_args.as_ptr();
 
+        let options = match mode {
+            ParseMode::SingleFile => 
+                libclang::CXTranslationUnit_None 
+                | libclang::CXTranslationUnit_DetailedPreprocessingRecord
+                | libclang::CXTranslationUnit_SingleFileParse,
+            ParseMode::Full => 
+                libclang::CXTranslationUnit_None 
+                | libclang::CXTranslationUnit_DetailedPreprocessingRecord
+                | libclang::CXTranslationUnit_KeepGoing
+        };
+
         let translation_unit = unsafe { 
             let tu = libclang::clang_parseTranslationUnit(
                 index, 
@@ -100,9 +162,7 @@ impl<'a> Open<&'a PathBuf, &'a str> for Clang<'a> {
                 vec_pointer_args.len() as i32,
                 null_mut(),
                 0,
-                libclang::CXTranslationUnit_None 
-                | libclang::CXTranslationUnit_DetailedPreprocessingRecord
-                | libclang::CXTranslationUnit_SingleFileParse
+                options
             );
             if tu.is_null() {
                 if !index.is_null() {
@@ -129,6 +189,76 @@ impl<'a> Open<&'a PathBuf, &'a str> for Clang<'a> {
 
 } 
 
+impl Clang<'_> {
+
+    /// Collects all diagnostics libclang emitted while parsing
+    /// the translation unit, notes included.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+
+        let mut diagnostics: Vec<Diagnostic> = vec![];
+
+        let amount = unsafe { libclang::clang_getNumDiagnostics(self.translation_unit) };
+
+        for i in 0..amount {
+
+            let mut file:   libclang::CXFile = null_mut();
+            let mut line:   u32 = 0;
+            let mut column: u32 = 0;
+
+            let (severity, message) = unsafe {
+
+                let diagnostic = libclang::clang_getDiagnostic(self.translation_unit, i);
+
+                libclang::clang_getFileLocation(
+                    libclang::clang_getDiagnosticLocation(diagnostic),
+                    std::ptr::addr_of_mut!(file) as *mut libclang::CXFile, 
+                    std::ptr::addr_of_mut!(line) as *mut c_uint,
+                    std::ptr::addr_of_mut!(column) as *mut c_uint,
+                    null_mut()
+                );
+
+                let severity = libclang::clang_getDiagnosticSeverity(diagnostic);
+                let message = cxstring_to_string_consumable(
+                    libclang::clang_getDiagnosticSpelling(diagnostic)
+                ).unwrap_or_default();
+
+                libclang::clang_disposeDiagnostic(diagnostic);
+
+                (severity, message)
+
+            };
+
+            let severity = match severity {
+                libclang::CXDiagnostic_Note    => DiagnosticSeverity::Note,
+                libclang::CXDiagnostic_Warning => DiagnosticSeverity::Warning,
+                libclang::CXDiagnostic_Error   => DiagnosticSeverity::Error,
+                libclang::CXDiagnostic_Fatal   => DiagnosticSeverity::Fatal,
+                _ => continue
+            };
+
+            let file = if file.is_null() {
+                self.file.to_string_lossy().to_string()
+            } else {
+                cxstring_to_string_consumable(unsafe{libclang::clang_getFileName(file)})
+                    .unwrap_or_else(|_| self.file.to_string_lossy().to_string())
+            };
+
+            diagnostics.push(Diagnostic {
+                severity,
+                file,
+                line: line as usize,
+                column: column as usize,
+                message
+            });
+
+        }
+
+        diagnostics
+
+    }
+
+}
+
 impl Drop for Clang<'_> {
 
     fn drop(&mut self) {
@@ -271,6 +401,75 @@ pub fn position_from_cursor_location(
 }
 
 
+/// Checks if the cursor is located inside of the main file, following
+/// macro expansions back to where the macro was invoked.
+/// 
+/// Unlike [libclang::clang_Location_isFromMainFile] this is true for
+/// a function declared through a macro invoked in the main file even
+/// if the macro itself is defined inside of a header.
+pub fn is_from_main_file(
+    cursor: libclang::CXCursor
+) -> bool
+{
+
+    let mut file:   libclang::CXFile = null_mut();
+    let mut offset: u32 = 0;
+
+    unsafe {
+
+        let location = libclang::clang_getCursorLocation(cursor);
+
+        if libclang::clang_Location_isFromMainFile(location) != 0 {
+            return true
+        }
+
+        libclang::clang_getExpansionLocation(
+            location,
+            std::ptr::addr_of_mut!(file) as *mut libclang::CXFile,
+            null_mut(),
+            null_mut(),
+            std::ptr::addr_of_mut!(offset) as *mut c_uint
+        );
+
+        if file.is_null() {
+            return false
+        }
+
+        libclang::clang_Location_isFromMainFile(
+            libclang::clang_getLocationForOffset(
+                libclang::clang_Cursor_getTranslationUnit(cursor),
+                file,
+                offset
+            )
+        ) != 0
+
+    }
+
+}
+
+/// File offset of where the cursor was expanded, for cursors
+/// that don't come from a macro this is their normal offset.
+pub fn expansion_offset_from_cursor(
+    cursor: libclang::CXCursor
+) -> usize
+{
+
+    let mut offset: u32 = 0;
+
+    unsafe {
+        libclang::clang_getExpansionLocation(
+            libclang::clang_getCursorLocation(cursor),
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            std::ptr::addr_of_mut!(offset) as *mut c_uint
+        );
+    }
+
+    offset as usize
+
+}
+
 /// Given a [libclang::CXCursor] find its origin file.
 pub fn filename_from_cursor(
     cursor: libclang::CXCursor
@@ -297,6 +496,44 @@ pub fn filename_from_cursor(
 
 }
 
+/// Collects the strings of every `__attribute__((annotate("...")))`
+/// attached to the cursor.
+pub fn annotations_from_cursor(
+    cursor: libclang::CXCursor
+) -> Vec<String>
+{
+
+    extern "C" fn collect(
+        cursor: libclang::CXCursor,
+        _p:     libclang::CXCursor,
+        data:   libclang::CXClientData
+    ) -> libclang::CXChildVisitResult { unsafe {
+
+        if libclang::clang_getCursorKind(cursor) == libclang::CXCursor_AnnotateAttr {
+            let annotations = &mut *(data as *mut Vec<String>);
+            if let Ok(annotation) = cxstring_to_string_consumable(libclang::clang_getCursorSpelling(cursor)) {
+                annotations.push(annotation);
+            }
+        }
+
+        libclang::CXChildVisit_Continue
+
+    }}
+
+    let mut annotations: Vec<String> = vec![];
+
+    unsafe {
+        libclang::clang_visitChildren(
+            cursor,
+            collect,
+            std::ptr::addr_of_mut!(annotations) as libclang::CXClientData
+        );
+    }
+
+    annotations
+
+}
+
 /// Converts a [libclang::CXString] into rusts [String] whilst
 /// consuming the original [libclang::CXString] (aka no need for 
 /// [libclang::clang_disposeString]).
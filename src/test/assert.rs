@@ -0,0 +1,287 @@
+//! The bundled `cesty.h` assertion header & reading back
+//! the assertion failures a test reported.
+//!
+//! Report format
+//! -------------
+//! Every failed assertion is a single line of tab separated fields
+//! written into the file named by [ASSERTION_REPORT_ENVIRONMENT]:
+//! ```text
+//! <assert/expect>\t<file>\t<line>\t<macro>\t<expression>\t<expected>\t<actual>
+//! ```
+//! Tabs, newlines & backslashes inside of the fields are escaped.
+//!
+//! The report is a file rather than a pipe, records written before
+//! a test crashed are still there & a test can't block on a full pipe.
+
+use std::path::{Path, PathBuf};
+
+use crate::{
+    defaults::{
+        ASSERTION_HEADER_DIRECTORY,
+        ASSERTION_HEADER_NAME,
+        ASSERTION_REPORT_ENVIRONMENT
+    },
+    error::{
+        debuginfo, error, function_message,
+        warning, Alert, AlertCode, AlertCodeFix,
+        AlertExample, AlertInfo
+    }
+};
+
+/// Contents of `cesty.h`.
+pub const HEADER: &'static str = include_str!("cesty.h");
+
+/// A single failed assertion reported by a test.
+#[derive(Clone, Debug)]
+pub struct AssertionFailure {
+
+    /// `CESTY_ASSERT_*` failures stop the test, `CESTY_EXPECT_*` don't.
+    pub fatal: bool,
+
+    /// File the assertion is written in.
+    pub file: String,
+
+    /// Line the assertion is written on.
+    pub line: usize,
+
+    /// Name of the assertion macro.
+    pub assertion: String,
+
+    /// Arguments of the assertion as written.
+    pub expression: String,
+
+    pub expected: String,
+
+    pub actual: String
+
+}
+
+/// Writes [HEADER] into the [ASSERTION_HEADER_DIRECTORY] of
+/// `batch_root` & returns the directory to add to the include path.
+pub fn write_header(batch_root: &Path) -> Result<PathBuf, Alert> {
+
+    let directory = batch_root.join(ASSERTION_HEADER_DIRECTORY);
+
+    match std::fs::create_dir_all(&directory) {
+        Ok(_) => (),
+        Err(err) => return error!{
+            debug: debuginfo!(),
+            description: format!("failed to create directory `{}` for `{ASSERTION_HEADER_NAME}`", directory.to_string_lossy()),
+            example: None,
+            note: function_message!("std::fs::create_dir_all()", err.to_string())
+        }
+    }
+
+    match std::fs::write(directory.join(ASSERTION_HEADER_NAME), HEADER) {
+        Ok(_) => Ok(directory),
+        Err(err) => error!{
+            debug: debuginfo!(),
+            description: format!("failed to write `{ASSERTION_HEADER_NAME}` into `{}`", directory.to_string_lossy()),
+            example: None,
+            note: function_message!("std::fs::write()", err.to_string())
+        }
+    }
+
+}
+
+/// Report file of the harness `binary`, passed to it
+/// through [ASSERTION_REPORT_ENVIRONMENT].
+pub fn report_path(binary: &Path) -> PathBuf {
+
+    binary.with_extension("report")
+
+}
+
+fn unescape(field: &str) -> String {
+
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('t')   => unescaped.push('\t'),
+            Some('n')   => unescaped.push('\n'),
+            Some('r')   => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None        => unescaped.push('\\')
+        }
+    }
+
+    unescaped
+
+}
+
+/// Parses the contents of a report file, malformed lines
+/// (from a test that died mid write) are skipped.
+pub fn parse_report(contents: &str) -> Vec<AssertionFailure> {
+
+    contents
+        .lines()
+        .filter_map(|line| {
+
+            let fields: Vec<String> = line.split('\t').map(unescape).collect();
+
+            if fields.len() != 7 {
+                return None
+            }
+
+            Some(AssertionFailure {
+                fatal:      fields[0] == "assert",
+                file:       fields[1].clone(),
+                line:       fields[2].parse().ok()?,
+                assertion:  fields[3].clone(),
+                expression: fields[4].clone(),
+                expected:   fields[5].clone(),
+                actual:     fields[6].clone()
+            })
+
+        })
+        .collect()
+
+}
+
+impl AssertionFailure {
+
+    /// Renders the failure with an excerpt of the file the assertion
+    /// is written in, soft failures are rendered as warnings.
+    pub fn to_alert(&self, test: &str) -> Alert {
+
+        let description = format!("{} `{}({})` failed in test `{test}`",
+            if self.fatal { "assertion" } else { "expectation" },
+            self.assertion,
+            self.expression
+        );
+
+        let comment = format!("expected {}, got {}", self.expected, self.actual);
+
+        let example = match std::fs::read_to_string(&self.file) {
+            Ok(contents) if self.line > 0 => {
+                let column = contents
+                    .lines()
+                    .nth(self.line - 1)
+                    .and_then(|line| line.find(&self.assertion))
+                    .map(|column| column + 1)
+                    .unwrap_or(1);
+                Some(AlertExample::Code(AlertCode {
+                    line: self.line,
+                    file: self.file.clone(),
+                    code: contents,
+                    fix: vec![AlertCodeFix {
+                        relative_line: self.line - 1,
+                        column,
+                        comment: comment.clone()
+                    }]
+                }))
+            }
+            _ => None
+        };
+
+        let note = match example {
+            Some(_) => vec![],
+            None    => vec![format!("`{}`:{} {comment}", self.file, self.line)]
+        };
+
+        if self.fatal {
+            Alert::Error(AlertInfo {
+                description,
+                debug: debuginfo!(),
+                example,
+                note
+            })
+        } else {
+            warning!{
+                debug: debuginfo!(),
+                description: description,
+                example: example,
+                note: note
+            }
+        }
+
+    }
+
+}
+
+/// Sets [ASSERTION_REPORT_ENVIRONMENT] on `command` & clears any
+/// report left over from a previous run of `binary`.
+pub fn prepare_report(command: &mut std::process::Command, binary: &Path) {
+
+    let report = report_path(binary);
+    _ = std::fs::remove_file(&report);
+    command.env(ASSERTION_REPORT_ENVIRONMENT, report);
+
+}
+
+/// Reads the failed assertions `binary` reported, empty
+/// if it didn't report any.
+pub fn read_report(binary: &Path) -> Vec<AssertionFailure> {
+
+    match std::fs::read_to_string(report_path(binary)) {
+        Ok(contents) => parse_report(&contents),
+        Err(_)       => vec![]
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::parse_report;
+
+    #[test]
+    fn parses_assert_and_expect_records() {
+
+        let failures = parse_report(concat!(
+            "assert\ttests/ds.c\t12\tCESTY_ASSERT_EQ_INT\tsum(2, 2), 5\t5\t4\n",
+            "expect\ttests/ds.c\t14\tCESTY_EXPECT_EQ_STR\tname, \"cesty\"\t\"cesty\"\t\"cest\"\n"
+        ));
+
+        assert_eq!(failures.len(), 2);
+
+        assert!(failures[0].fatal);
+        assert_eq!(failures[0].file, "tests/ds.c");
+        assert_eq!(failures[0].line, 12);
+        assert_eq!(failures[0].assertion, "CESTY_ASSERT_EQ_INT");
+        assert_eq!(failures[0].expression, "sum(2, 2), 5");
+        assert_eq!(failures[0].expected, "5");
+        assert_eq!(failures[0].actual, "4");
+
+        assert!(!failures[1].fatal);
+        assert_eq!(failures[1].actual, "\"cest\"");
+
+    }
+
+    #[test]
+    fn unescapes_fields() {
+
+        let failures = parse_report(
+            "assert\ta.c\t3\tCESTY_ASSERT_EQ_STR\ts, \"a\\tb\"\t\"a\\tb\"\t\"a\\nb\\\\\"\n"
+        );
+
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].expression, "s, \"a\tb\"");
+        assert_eq!(failures[0].expected, "\"a\tb\"");
+        assert_eq!(failures[0].actual, "\"a\nb\\\"");
+
+    }
+
+    #[test]
+    fn skips_malformed_lines() {
+
+        let failures = parse_report(concat!(
+            "assert\ta.c\t3\tCESTY_ASSERT_EQ_INT\tx, 1\t1\t0\n",
+            "assert\ta.c\tthree\tCESTY_ASSERT_EQ_INT\tx, 1\t1\t0\n",
+            "\n",
+            // Test killed in the middle of writing the record.
+            "expect\ta.c\t4\tCESTY_EXP"
+        ));
+
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].line, 3);
+
+    }
+
+}
@@ -0,0 +1,514 @@
+//! The folder a single `cesty run` places its compiled tests in.
+//!
+//! Retention
+//! ---------
+//! Batches are placed inside of `.cesty` or [Run::build_dir], at the start
+//! of every run (unless `--keep-artifacts` is set) & by `cesty clean` the
+//! batches beyond the newest [Run::keep] or older than [Run::older_than]
+//! are removed. Batches of runs that are still going are never removed,
+//! see [super::lock].
+//!
+//! The [LATEST_BATCH_NAME] symlink next to the batches always points at
+//! the most recently created one.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime}
+};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::{
+    arg_conf::{Clean, Run},
+    defaults::{
+        BATCH_FINISH_LOCK_NAME,
+        DEFAULT_KEPT_BATCHES,
+        DEFAULT_PRIVATE_DIRECTORY,
+        LATEST_BATCH_NAME,
+        MAX_BATCH_ROOT_NAME_CREATION_ATTEMPTS
+    },
+    error::{
+        debuginfo, debugpush, error, function_message,
+        warning, Alert, AlertInfo
+    }
+};
+
+use super::lock::FileLock;
+
+lazy_static! {
+
+    /// Names created by [name_from_local_time].
+    static ref BATCH_NAME: Regex = Regex::new(r"^\d{4}_\d{2}_\d{2}-\d{2}_\d{2}_\d{4}-\d+$").unwrap();
+
+}
+
+/// What folder is the current test batch going to be
+/// placed inside of?
+///
+/// Use
+/// ---
+/// Creating a test batch folder with:
+/// ```
+/// let batch_folder = TestBatchFolder::new(&run_conf)?;
+/// ```
+/// & creating tests inside of the folder with:
+/// ```
+/// let compilable = CompilableTest::from_parsed_file(parsed_file, &run_conf, recipe, &batch_folder);
+/// ```
+///
+/// No config was found
+/// -------------------
+/// If no config file exists, instead of creating a `.cesty` folder
+/// inside of the folder where `config.cesty.{toml, yaml}` resides,
+/// it will create the folder inside of [`std::env::temp_dir()`].
+///
+/// Drop trait
+/// ----------
+/// Upon finishing, the [Drop] trait of [TestBatchFolder] will spawn
+/// a `finish.cesty.lock` file inside of the test batch folder.
+#[derive(Debug)]
+pub struct TestBatchFolder {
+
+    /// Root of the test folder...
+    path: PathBuf,
+
+    /// If a config file doesn't exist, we create a
+    /// [DEFAULT_PRIVATE_DIRECTORY] inside of [std::env::temp_dir].
+    path_inside_temp: bool,
+
+    /// Directory containing the bundled `cesty.h`.
+    include_path: PathBuf,
+
+    /// The [DEFAULT_PRIVATE_DIRECTORY] the batch is placed inside of.
+    private_path: PathBuf,
+
+    /// Held until the batch is dropped, see [super::lock::running].
+    _running: FileLock
+
+}
+
+impl TestBatchFolder {
+
+    pub fn new(config: &crate::arg_conf::Run) -> Result<Self, Alert> {
+
+        let batch_folder_inside_temp = config.config_path.is_none();
+        let private_path = private_path(config);
+        let batches_path = batches_path(config);
+
+        match std::fs::create_dir_all(&batches_path) {
+            Ok(_) => (),
+            Err(err) => return error!{
+                debug: debuginfo!(),
+                description: format!("failed to create the batches folder `{}`", batches_path.to_string_lossy()),
+                example: None,
+                note: function_message!("std::fs::create_dir_all()", err.to_string())
+            }
+        }
+
+        let batches_lock = match super::lock::batches(&batches_path, config.wait) {
+            Ok(lock) => lock,
+            Err(err) => return Err(debugpush!(err))
+        };
+
+        let batch_folder = {
+
+            let mut batch_folder = PathBuf::new();
+
+            for attempt in 0..=MAX_BATCH_ROOT_NAME_CREATION_ATTEMPTS {
+
+                batch_folder = batches_path.join(
+                    name_from_local_time() + "-" + attempt.to_string().as_str());
+
+                if !batch_folder.exists() {
+                    break;
+                }
+
+                if attempt == MAX_BATCH_ROOT_NAME_CREATION_ATTEMPTS {
+                    return error!{
+                        debug: debuginfo!(),
+                        description: "failed to create a unique directory name after 10 attempts for this test batch.".to_owned(),
+                        note: vec![
+                            "This could be a 1/1000000 failure or...".to_owned(),
+                            "maybe you are running cesty in parallel inside the same directory?".to_owned()
+                        ],
+                        example: None
+                    }
+                }
+
+            }
+            batch_folder
+        };
+
+        match std::fs::create_dir_all(&batch_folder) {
+            Ok(_) => (),
+            Err(err) => return error!{
+                debug: debuginfo!(),
+                description: format!("failed to create the test batch folder `{}`", batch_folder.to_string_lossy()),
+                example: None,
+                note: function_message!("std::fs::create_dir_all()", err.to_string())
+            }
+        }
+
+        let running = match FileLock::try_acquire(&super::lock::running(&batch_folder)) {
+            Ok(Some(running)) => running,
+            Ok(None) => return error!{
+                debug: debuginfo!(),
+                description: format!("the test batch folder `{}` is used by another run", batch_folder.to_string_lossy()),
+                example: None,
+                note: vec![]
+            },
+            Err(err) => return error!{
+                debug: debuginfo!(),
+                description: format!("failed to lock the test batch folder `{}`", batch_folder.to_string_lossy()),
+                example: None,
+                note: function_message!("std::fs::File::try_lock()", err.to_string())
+            }
+        };
+
+        let include_path = match super::assert::write_header(&batch_folder) {
+            Ok(include_path) => include_path,
+            Err(err) => return Err(debugpush!(err))
+        };
+
+        link_latest(&batches_path, &batch_folder);
+
+        drop(batches_lock);
+
+        Ok(Self {
+
+            path:             batch_folder,
+            path_inside_temp: batch_folder_inside_temp,
+            include_path,
+            private_path,
+            _running: running
+
+        })
+
+    }
+
+    /// Root of the test batch.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Directory added to the include path of every
+    /// harness, contains the bundled `cesty.h`.
+    pub fn include_path(&self) -> &PathBuf {
+        &self.include_path
+    }
+
+    /// The [DEFAULT_PRIVATE_DIRECTORY] containing this & all the
+    /// other batches, along with data kept between runs.
+    pub fn private_path(&self) -> &PathBuf {
+        &self.private_path
+    }
+
+    /// Removes the batches next to this one that are past the
+    /// retention of `config`, never this batch or running ones.
+    /// Batches that fail to be removed are returned as warnings.
+    pub fn prune(&self, config: &Run) -> Result<Vec<Alert>, Alert> {
+
+        let _lock = match super::lock::batches(&batches_path(config), config.wait) {
+            Ok(lock) => lock,
+            Err(err) => return Err(debugpush!(err))
+        };
+
+        let batches = batches(&batches_path(config))
+            .into_iter()
+            .filter(|batch| batch.path != self.path)
+            .collect::<Vec<Batch>>();
+
+        let (keep, age) = retention(config)?;
+
+        // This batch counts towards the kept ones.
+        let keep = keep.map(|keep| keep.saturating_sub(1));
+
+        let (_, warnings) = remove(expired(batches, keep, age));
+
+        Ok(warnings)
+
+    }
+
+    /// Is the batch placed inside of [std::env::temp_dir] due
+    /// to no config being found.
+    #[allow(dead_code)]
+    pub fn is_inside_temp(&self) -> bool {
+        self.path_inside_temp
+    }
+
+}
+
+impl Drop for TestBatchFolder {
+
+    fn drop(&mut self) {
+
+        // Marks the batch as finished, nothing to be done if it fails.
+        _ = std::fs::File::create(self.path.join(BATCH_FINISH_LOCK_NAME));
+
+    }
+
+}
+
+/// The [DEFAULT_PRIVATE_DIRECTORY] next to the config, inside of
+/// [std::env::temp_dir] if no config was found.
+pub fn private_path(config: &crate::arg_conf::Run) -> PathBuf {
+
+    let cesty_root = match config.config_path.as_ref() {
+        Some(config_path) => config_path.parent().map(PathBuf::from).unwrap_or_default(),
+        None => std::env::temp_dir()
+    };
+
+    cesty_root.join(DEFAULT_PRIVATE_DIRECTORY)
+
+}
+
+/// The directory batches are placed in, [Run::build_dir] relative to
+/// the config or the [DEFAULT_PRIVATE_DIRECTORY] if it isn't set.
+pub fn batches_path(config: &Run) -> PathBuf {
+
+    match config.build_dir.as_ref() {
+        Some(build_dir) => private_path(config)
+            .parent()
+            .map(|root| root.join(build_dir))
+            .unwrap_or(build_dir.clone()),
+        None => private_path(config)
+    }
+
+}
+
+/// A batch found inside of [batches_path].
+#[derive(Clone, Debug)]
+struct Batch {
+
+    path: PathBuf,
+
+    /// When the batch was last written to.
+    modified: SystemTime,
+
+    /// Is the batch used by a run that's still going.
+    running: bool
+
+}
+
+/// Is the [super::lock::running] lock of `batch` held by another run,
+/// batches created before the lock existed are never running.
+fn is_running(batch: &Path) -> bool {
+
+    let lock = super::lock::running(batch);
+
+    lock.exists() && !matches!(FileLock::try_acquire(&lock), Ok(Some(_)))
+
+}
+
+/// Every batch inside of `root`, newest first.
+fn batches(root: &Path) -> Vec<Batch> {
+
+    let Ok(read_dir) = std::fs::read_dir(root) else {
+        return vec![]
+    };
+
+    let mut batches: Vec<Batch> = read_dir
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .filter(|entry| BATCH_NAME.is_match(&entry.file_name().to_string_lossy()))
+        .map(|entry| Batch {
+            path:     entry.path(),
+            modified: entry.metadata().and_then(|metadata| metadata.modified()).unwrap_or(SystemTime::UNIX_EPOCH),
+            running:  is_running(&entry.path())
+        })
+        .collect();
+
+    batches.sort_by(|a, b| b.modified.cmp(&a.modified).then(b.path.cmp(&a.path)));
+
+    batches
+
+}
+
+/// Parses an age like `30s`, `15m`, `12h`, `7d` or `2w`.
+pub fn parse_age(age: &str) -> Result<Duration, String> {
+
+    let age = age.trim();
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let (amount, unit) = age.split_at(split);
+
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("`{age}` doesn't start with a number"))?;
+
+    let seconds = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        unit => return Err(format!("unknown unit `{unit}`, use one of `s`, `m`, `h`, `d` or `w`"))
+    };
+
+    Ok(Duration::from_secs(amount * seconds))
+
+}
+
+/// The amount of batches kept & their maximum age according to `config`,
+/// [DEFAULT_KEPT_BATCHES] if neither is set.
+fn retention(config: &Run) -> Result<(Option<usize>, Option<Duration>), Alert> {
+
+    let age = match config.older_than.as_ref().map(|age| parse_age(age)) {
+        Some(Ok(age)) => Some(age),
+        Some(Err(err)) => return error!{
+            debug: debuginfo!(),
+            description: "invalid `older_than`".to_owned(),
+            example: None,
+            note: vec![
+                err,
+                "for example `--older-than 7d` removes the batches older than a week.".to_owned()
+            ]
+        },
+        None => None
+    };
+
+    Ok(match (config.keep, age) {
+        (None, None) => (Some(DEFAULT_KEPT_BATCHES), None),
+        (keep, age)  => (keep, age)
+    })
+
+}
+
+/// The `batches` (newest first) beyond the newest `keep` or older
+/// than `age`, batches of running runs are always kept.
+fn expired(batches: Vec<Batch>, keep: Option<usize>, age: Option<Duration>) -> Vec<Batch> {
+
+    let now = SystemTime::now();
+
+    batches
+        .into_iter()
+        .enumerate()
+        .filter(|(_, batch)| !batch.running)
+        .filter(|(index, batch)| keep.is_some_and(|keep| *index >= keep)
+            || age.is_some_and(|age| now.duration_since(batch.modified).unwrap_or_default() > age))
+        .map(|(_, batch)| batch)
+        .collect()
+
+}
+
+/// Size of everything inside of `path` in bytes.
+fn size(path: &Path) -> u64 {
+
+    let Ok(read_dir) = std::fs::read_dir(path) else {
+        return path.symlink_metadata().map(|metadata| metadata.len()).unwrap_or_default()
+    };
+
+    read_dir
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => size(&entry.path()),
+            _ => entry.metadata().map(|metadata| metadata.len()).unwrap_or_default()
+        })
+        .sum()
+
+}
+
+/// Removes `batches`, returns the amount of bytes removed
+/// & a warning for every batch that couldn't be.
+fn remove(batches: Vec<Batch>) -> (u64, Vec<Alert>) {
+
+    let mut removed: u64 = 0;
+    let mut warnings: Vec<Alert> = vec![];
+
+    for batch in batches {
+        let batch_size = size(&batch.path);
+        match std::fs::remove_dir_all(&batch.path) {
+            Ok(_) => removed += batch_size,
+            Err(err) => warnings.push(warning!{
+                debug: debuginfo!(),
+                description: format!("failed to remove the batch `{}`", batch.path.to_string_lossy()),
+                example: None,
+                note: function_message!("std::fs::remove_dir_all()", err.to_string())
+            })
+        }
+    }
+
+    (removed, warnings)
+
+}
+
+/// Points [LATEST_BATCH_NAME] inside of `root` at `batch`, the link is
+/// replaced through a rename such that it always exists. Nothing to be
+/// done if it fails.
+fn link_latest(root: &Path, batch: &Path) {
+
+    let Some(name) = batch.file_name() else {
+        return
+    };
+
+    let latest = root.join(LATEST_BATCH_NAME);
+    let partial = root.join(format!("{LATEST_BATCH_NAME}.{}", std::process::id()));
+
+    #[cfg(unix)]
+    let linked = std::os::unix::fs::symlink(name, &partial);
+
+    #[cfg(windows)]
+    let linked = std::os::windows::fs::symlink_dir(name, &partial);
+
+    if linked.is_ok() && std::fs::rename(&partial, &latest).is_err() {
+        _ = std::fs::remove_file(&partial);
+    }
+
+}
+
+/// Runs "cesty clean ...".
+pub fn clean(clean_conf: &Clean) -> Result<Vec<Alert>, Alert> {
+
+    let root = batches_path(&clean_conf.run);
+
+    let _lock = match super::lock::batches(&root, clean_conf.run.wait) {
+        Ok(lock) => lock,
+        Err(err) => return Err(debugpush!(err))
+    };
+
+    let batches = batches(&root);
+
+    let expired = if clean_conf.all {
+        expired(batches, Some(0), None)
+    } else {
+        let (keep, age) = retention(&clean_conf.run)?;
+        expired(batches, keep, age)
+    };
+
+    let count = expired.len();
+    let (removed, warnings) = remove(expired);
+
+    let latest = root.join(LATEST_BATCH_NAME);
+    if latest.symlink_metadata().is_ok() && !latest.exists() {
+        // Points at a removed batch.
+        _ = std::fs::remove_file(&latest);
+    }
+
+    println!("removed {count} batches ({:.1} MiB) from `{}`",
+        removed as f64 / 1048576.0,
+        root.to_string_lossy()
+    );
+
+    Ok(warnings)
+
+}
+
+fn name_from_local_time() -> String { // Stolen :P
+
+    let utc = time::OffsetDateTime::UNIX_EPOCH
+        + time::Duration::try_from(
+            std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap()
+        ).unwrap();
+
+    let local = utc.to_offset(time::UtcOffset::local_offset_at(utc).unwrap_or(time::UtcOffset::UTC));
+
+    format!("{}_{:0>2}_{:0>2}-{:0>2}_{:0>2}_{:0>4}",
+        local.year(),
+        local.month() as u8,
+        local.day(),
+        local.hour(),
+        local.minute(),
+        local.millisecond()
+    )
+
+}
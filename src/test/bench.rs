@@ -0,0 +1,463 @@
+//! Benchmarks, tests named `<prefix>bench_*` that the harness runs
+//! repeatedly through `cesty_bench()` from `cesty.h`.
+//!
+//! Samples
+//! -------
+//! The harness warms up first & then writes every sample into the file
+//! named by [BENCH_REPORT_ENVIRONMENT] as a line of tab separated fields:
+//! ```text
+//! <iterations>\t<nanoseconds>
+//! ```
+//!
+//! Baselines
+//! ---------
+//! The results of every run are stored as [BENCH_LATEST_NAME] inside of
+//! [BENCH_DIRECTORY], `--baseline <name>` stores them under `<name>` as
+//! well & `--compare <name>` fails every benchmark whose mean is slower
+//! than the one inside of `<name>` by more than [Run::bench_threshold].
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command
+};
+
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arg_conf::Run,
+    defaults::{
+        BENCH_DIRECTORY,
+        BENCH_LATEST_NAME,
+        BENCH_REPORT_ENVIRONMENT,
+        BENCH_SAMPLES_ENVIRONMENT,
+        BENCH_TIME_ENVIRONMENT,
+        DEFAULT_BENCH_SAMPLES,
+        DEFAULT_BENCH_THRESHOLD,
+        DEFAULT_BENCH_TIME
+    },
+    error::{
+        debuginfo, error, function_message,
+        warning, Alert, AlertInfo
+    }
+};
+
+use super::{
+    run::{Outcome, TestResult},
+    Config
+};
+
+/// How a benchmark is sampled.
+#[derive(Clone, Copy, Debug)]
+pub struct BenchSettings {
+
+    /// Amount of samples taken.
+    pub samples: u64,
+
+    /// Milliseconds all the samples take together.
+    pub time: u64
+
+}
+
+impl BenchSettings {
+
+    /// The test config takes precedence over the file config,
+    /// falling back to [DEFAULT_BENCH_SAMPLES] & [DEFAULT_BENCH_TIME].
+    pub fn resolve(file_config: &Config, config: &Config) -> Self {
+
+        BenchSettings {
+            samples: config.settings.bench_samples
+                .or(file_config.settings.bench_samples)
+                .unwrap_or(DEFAULT_BENCH_SAMPLES),
+            time: config.settings.bench_time
+                .or(file_config.settings.bench_time)
+                .unwrap_or(DEFAULT_BENCH_TIME)
+        }
+
+    }
+
+}
+
+/// Statistics of a benchmark, all times are
+/// in nanoseconds per iteration.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BenchStats {
+
+    /// Iterations ran over all the samples, without the warm-up.
+    pub iterations: u64,
+
+    pub samples: usize,
+
+    pub mean: f64,
+
+    pub median: f64,
+
+    /// Sample standard deviation of the per sample means.
+    pub stddev: f64,
+
+    /// Iterations per second, based on the mean.
+    pub throughput: f64
+
+}
+
+/// Stored result of a single benchmark.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BenchRecord {
+
+    /// Identity of the benchmark, see [TestResult::identity].
+    pub name: String,
+
+    #[serde(flatten)]
+    pub stats: BenchStats
+
+}
+
+/// Contents of a results file inside of [BENCH_DIRECTORY].
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct BenchFile {
+
+    #[serde(default)]
+    bench: Vec<BenchRecord>
+
+}
+
+impl BenchStats {
+
+    /// Statistics over samples of `(iterations, nanoseconds)`,
+    /// [None] without any usable sample.
+    pub fn from_samples(samples: &[(u64, f64)]) -> Option<Self> {
+
+        let mut per_iteration: Vec<f64> = samples
+            .iter()
+            .filter(|(iterations, _)| *iterations > 0)
+            .map(|(iterations, nanoseconds)| nanoseconds / *iterations as f64)
+            .collect();
+
+        if per_iteration.is_empty() {
+            return None
+        }
+
+        per_iteration.sort_by(|a, b| a.total_cmp(b));
+
+        let count = per_iteration.len();
+        let mean = per_iteration.iter().sum::<f64>() / count as f64;
+
+        let median = if count.is_multiple_of(2) {
+            (per_iteration[count / 2 - 1] + per_iteration[count / 2]) / 2.0
+        } else {
+            per_iteration[count / 2]
+        };
+
+        let stddev = if count > 1 {
+            (per_iteration.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (count - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+
+        Some(BenchStats {
+            iterations: samples.iter().map(|(iterations, _)| iterations).sum(),
+            samples: count,
+            mean,
+            median,
+            stddev,
+            throughput: if mean > 0.0 { 1e9 / mean } else { 0.0 }
+        })
+
+    }
+
+}
+
+/// Samples file of the harness `binary`.
+pub fn report_path(binary: &Path) -> PathBuf {
+
+    binary.with_extension("bench")
+
+}
+
+/// Sets the sampling environment on `command` & clears any
+/// samples left over from a previous run of `binary`.
+pub fn prepare(command: &mut Command, binary: &Path, settings: &BenchSettings) {
+
+    let report = report_path(binary);
+    _ = std::fs::remove_file(&report);
+
+    command
+        .env(BENCH_REPORT_ENVIRONMENT, report)
+        .env(BENCH_SAMPLES_ENVIRONMENT, settings.samples.to_string())
+        .env(BENCH_TIME_ENVIRONMENT, settings.time.to_string());
+
+}
+
+/// Reads the statistics of the last run of `binary`, [None]
+/// if it didn't write any samples.
+pub fn read_report(binary: &Path) -> Option<BenchStats> {
+
+    let contents = std::fs::read_to_string(report_path(binary)).ok()?;
+
+    let samples: Vec<(u64, f64)> = contents
+        .lines()
+        .filter_map(|line| {
+            let (iterations, nanoseconds) = line.split_once('\t')?;
+            Some((iterations.trim().parse().ok()?, nanoseconds.trim().parse().ok()?))
+        })
+        .collect();
+
+    BenchStats::from_samples(&samples)
+
+}
+
+/// Nanoseconds with the largest fitting unit.
+pub fn format_time(nanoseconds: f64) -> String {
+
+    match nanoseconds {
+        n if n >= 1e9 => format!("{:.3} s", n / 1e9),
+        n if n >= 1e6 => format!("{:.3} ms", n / 1e6),
+        n if n >= 1e3 => format!("{:.3} µs", n / 1e3),
+        n             => format!("{:.1} ns", n)
+    }
+
+}
+
+impl std::fmt::Display for BenchStats {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        write!(f, "{}/iter (median {}, ± {}) {:.0} iter/s over {} iterations",
+            format_time(self.mean).bold(),
+            format_time(self.median),
+            format_time(self.stddev),
+            self.throughput,
+            self.iterations
+        )
+
+    }
+
+}
+
+fn file_path(private_path: &Path, name: &str) -> PathBuf {
+
+    private_path.join(BENCH_DIRECTORY).join(format!("{name}.toml"))
+
+}
+
+fn save(private_path: &Path, name: &str, records: &[BenchRecord]) -> Result<PathBuf, Alert> {
+
+    let path = file_path(private_path, name);
+
+    let contents = match toml::to_string(&BenchFile { bench: records.to_vec() }) {
+        Ok(contents) => contents,
+        Err(err) => return error!{
+            debug: debuginfo!(),
+            description: format!("failed to serialize the benchmark results `{name}`"),
+            example: None,
+            note: function_message!("toml::to_string()", err.to_string())
+        }
+    };
+
+    if let Err(err) = std::fs::create_dir_all(private_path.join(BENCH_DIRECTORY))
+        .and_then(|_| std::fs::write(&path, contents))
+    {
+        return error!{
+            debug: debuginfo!(),
+            description: format!("failed to write the benchmark results `{}`", path.to_string_lossy()),
+            example: None,
+            note: function_message!("std::fs::write()", err.to_string())
+        }
+    }
+
+    Ok(path)
+
+}
+
+fn load(private_path: &Path, name: &str) -> Result<Vec<BenchRecord>, Alert> {
+
+    let path = file_path(private_path, name);
+
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) => return error!{
+            debug: debuginfo!(),
+            description: format!("failed to read the benchmark baseline `{name}`"),
+            example: None,
+            note: vec![
+                format!("expected it at `{}`, create it with `--baseline {name}`.", path.to_string_lossy()),
+                err.to_string()
+            ]
+        }
+    };
+
+    match toml::from_str::<BenchFile>(&contents) {
+        Ok(file) => Ok(file.bench),
+        Err(err) => error!{
+            debug: debuginfo!(),
+            description: format!("failed to parse the benchmark baseline `{}`", path.to_string_lossy()),
+            example: None,
+            note: function_message!("toml::from_str()", err.message().to_owned())
+        }
+    }
+
+}
+
+/// Stores the benchmark results & compares them against
+/// [Run::compare], benchmarks that regressed beyond the threshold
+/// are marked as [Outcome::Failed] & reported as errors.
+pub fn finish(
+    results:      &mut [TestResult],
+    run_conf:     &Run,
+    private_path: &Path
+) -> Result<Vec<Alert>, Alert> {
+
+    let mut warnings: Vec<Alert> = vec![];
+
+    let records: Vec<BenchRecord> = results
+        .iter()
+        .filter_map(|result| result.bench.as_ref().map(|stats| BenchRecord {
+            name:  result.identity(),
+            stats: stats.clone()
+        }))
+        .collect();
+
+    if let Err(err) = save(private_path, BENCH_LATEST_NAME, &records) {
+        warnings.push(err);
+    }
+
+    if let Some(name) = run_conf.baseline.as_ref() {
+        let path = save(private_path, name, &records)?;
+        println!("\nsaved {} benchmarks as baseline `{name}` into `{}`", records.len(), path.to_string_lossy());
+    }
+
+    let Some(name) = run_conf.compare.as_ref() else {
+        return Ok(warnings)
+    };
+
+    let baseline = load(private_path, name)?;
+    let threshold = run_conf.bench_threshold.unwrap_or(DEFAULT_BENCH_THRESHOLD);
+
+    println!("\n{}", format!("compared against `{name}`:").bold());
+
+    for result in results.iter_mut() {
+
+        let Some(stats) = result.bench.as_ref() else {
+            continue
+        };
+
+        let identity = result.identity();
+
+        let Some(base) = baseline.iter().find(|record| record.name == identity) else {
+            warnings.push(warning!{
+                debug: debuginfo!(),
+                description: format!("benchmark `{identity}` isn't inside of the baseline `{name}`"),
+                example: None,
+                note: vec![]
+            });
+            continue
+        };
+
+        let change = if base.stats.mean > 0.0 {
+            (stats.mean - base.stats.mean) / base.stats.mean * 100.0
+        } else {
+            0.0
+        };
+
+        let formatted = format!("{change:+.2}%");
+        println!("  {identity} {} -> {} {}",
+            format_time(base.stats.mean),
+            format_time(stats.mean),
+            if change > threshold {
+                formatted.red()
+            } else if change < -threshold {
+                formatted.green()
+            } else {
+                formatted.normal()
+            }
+        );
+
+        if change > threshold {
+            result.outcome = Outcome::Failed;
+            eprintln!("{}", Alert::Error(AlertInfo {
+                description: format!("benchmark `{identity}` regressed by {change:.2}% compared to `{name}`"),
+                debug: debuginfo!(),
+                example: None,
+                note: vec![
+                    format!("the mean went from {} to {}, the threshold is {threshold}%.",
+                        format_time(base.stats.mean), format_time(stats.mean)),
+                    "raise it with `--bench-threshold <percent>` if the benchmark is noisy.".to_owned()
+                ]
+            }));
+        }
+
+    }
+
+    Ok(warnings)
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::BenchStats;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn odd_sample_count() {
+
+        // 10, 20 & 60 nanoseconds per iteration.
+        let stats = BenchStats::from_samples(&[(100, 1000.0), (50, 1000.0), (10, 600.0)]).unwrap();
+
+        assert_eq!(stats.iterations, 160);
+        assert_eq!(stats.samples, 3);
+        assert!(close(stats.mean, 30.0));
+        assert!(close(stats.median, 20.0));
+        // Sample standard deviation: sqrt((400 + 100 + 900) / 2).
+        assert!(close(stats.stddev, 700f64.sqrt()));
+        assert!(close(stats.throughput, 1e9 / 30.0));
+
+    }
+
+    #[test]
+    fn even_sample_count_averages_the_middle() {
+
+        let stats = BenchStats::from_samples(&[(1, 40.0), (1, 10.0), (1, 30.0), (1, 20.0)]).unwrap();
+
+        assert!(close(stats.median, 25.0));
+        assert!(close(stats.mean, 25.0));
+
+    }
+
+    #[test]
+    fn single_sample_has_no_deviation() {
+
+        let stats = BenchStats::from_samples(&[(4, 100.0)]).unwrap();
+
+        assert_eq!(stats.samples, 1);
+        assert!(close(stats.mean, 25.0));
+        assert!(close(stats.median, 25.0));
+        assert_eq!(stats.stddev, 0.0);
+
+    }
+
+    #[test]
+    fn empty_samples_are_skipped() {
+
+        assert!(BenchStats::from_samples(&[]).is_none());
+        assert!(BenchStats::from_samples(&[(0, 10.0)]).is_none());
+
+        let stats = BenchStats::from_samples(&[(0, 10.0), (2, 10.0)]).unwrap();
+        assert_eq!(stats.samples, 1);
+        assert!(close(stats.mean, 5.0));
+
+    }
+
+    #[test]
+    fn zero_time_has_no_throughput() {
+
+        let stats = BenchStats::from_samples(&[(10, 0.0)]).unwrap();
+
+        assert_eq!(stats.throughput, 0.0);
+
+    }
+
+}
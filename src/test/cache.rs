@@ -0,0 +1,606 @@
+//! Content addressed cache of compiled harnesses inside of
+//! [CACHE_DIRECTORY], re-running unchanged tests reuses the binary
+//! built by a previous run instead of compiling it again.
+//!
+//! Keys
+//! ----
+//! A binary is stored as `<key>.out` where the key is the SHA-256 of:
+//! - the generated harness source after preprocessing, such that
+//!   changes to the headers it includes are picked up as well,
+//! - the resolved path & `--version` output of the compiler,
+//! - every flag & library the harness is compiled with,
+//! - the path & contents of every linked object.
+//!
+//! The key doubles as the fingerprint of the results of the tests
+//! inside of the harness, see [super::flaky].
+//!
+//! Coverage builds aren't cached, their notes files are written
+//! next to the binary while compiling.
+//!
+//! Eviction
+//! --------
+//! A hit updates the modification time of the binary, after storing
+//! a binary the least recently used ones are removed until the cache
+//! fits inside of [Run::cache_size] MiB.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+    time::SystemTime
+};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    arg_conf::{Cache as CacheConfig, CacheAction, Run},
+    defaults::{
+        CACHE_DIRECTORY,
+        CACHE_STATS_NAME,
+        DEFAULT_CACHE_SIZE
+    },
+    error::{
+        debuginfo, debugpush, error, function_message,
+        Alert, AlertInfo
+    }
+};
+
+use super::compilable::CompilableTest;
+
+lazy_static! {
+
+    /// Resolved path & version of every compiler seen so far,
+    /// [None] if it couldn't be found.
+    static ref COMPILERS: Mutex<HashMap<String, Option<String>>> = Mutex::new(HashMap::new());
+
+}
+
+/// The compilation cache of a `.cesty` directory.
+#[derive(Clone, Debug)]
+pub struct Cache {
+
+    /// The [CACHE_DIRECTORY] itself.
+    path: PathBuf,
+
+    /// Size in bytes the cache is kept under.
+    limit: u64,
+
+    /// Wait for other runs writing into the cache, see [Run::wait].
+    wait: bool
+
+}
+
+/// Hits & misses kept inside of [CACHE_STATS_NAME].
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+struct Counters {
+
+    hits: u64,
+
+    misses: u64
+
+}
+
+/// What `cesty cache stats` prints.
+#[derive(Clone, Debug)]
+pub struct CacheStats {
+
+    pub path: PathBuf,
+
+    /// Amount of cached binaries.
+    pub entries: usize,
+
+    /// Size of all the cached binaries in bytes.
+    pub size: u64,
+
+    /// See [Cache::limit].
+    pub limit: u64,
+
+    pub hits: u64,
+
+    pub misses: u64
+
+}
+
+/// Path & `--version` output of the compiler `name`, [None]
+/// if it can't be found.
+pub(super) fn compiler_identity(name: &str) -> Option<String> {
+
+    if let Some(identity) = COMPILERS.lock().ok()?.get(name) {
+        return identity.clone()
+    }
+
+    let path = if Path::new(name).components().count() > 1 {
+        Some(PathBuf::from(name))
+    } else {
+        std::env::var_os("PATH").and_then(|paths| std::env::split_paths(&paths)
+            .map(|directory| directory.join(name))
+            .find(|candidate| candidate.is_file()))
+    };
+
+    let identity = path
+        .and_then(|path| path.canonicalize().ok())
+        .and_then(|path| {
+            let output = Command::new(&path).arg("--version").output().ok()?;
+            Some(format!("{}\n{}{}",
+                path.to_string_lossy(),
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            ))
+        });
+
+    COMPILERS.lock().ok()?.insert(name.to_owned(), identity.clone());
+
+    identity
+
+}
+
+/// Adds `bytes` prefixed by their length, such that
+/// neighbouring fields can't run into each other.
+fn update(hasher: &mut Sha256, bytes: &[u8]) {
+
+    hasher.update((bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+
+}
+
+/// `bytes` as KiB or MiB.
+fn format_size(bytes: u64) -> String {
+
+    match bytes {
+        0..=1048575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _           => format!("{:.1} MiB", bytes as f64 / 1048576.0)
+    }
+
+}
+
+/// Key of the harness of `test`, [None] if the harness can't be
+/// preprocessed or the compiler or any of the objects can't be
+/// read, such tests are always compiled.
+pub fn key(test: &CompilableTest) -> Option<String> {
+
+    let output = Command::new(&test.compiler.name)
+        .args(test.flags())
+        .arg(format!("-I{}", test.include.to_string_lossy()))
+        .arg("-E")
+        .arg(&test.path)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    let mut hasher = Sha256::new();
+
+    // The harness & the bundled header are placed inside of a new
+    // batch folder every run, their paths end up in line markers.
+    let source = String::from_utf8_lossy(&output.stdout)
+        .replace(test.path.to_string_lossy().as_ref(), "<harness>")
+        .replace(test.include.to_string_lossy().as_ref(), "<include>");
+    update(&mut hasher, source.as_bytes());
+
+    update(&mut hasher, compiler_identity(&test.compiler.name)?.as_bytes());
+
+    for flag in test.flags() {
+        update(&mut hasher, flag.as_bytes());
+    }
+
+    update(&mut hasher, b"libraries");
+    for library in test.compiler.libraries.iter() {
+        update(&mut hasher, library.as_bytes());
+    }
+
+    update(&mut hasher, b"objects");
+    for object in test.compiler.objects.iter() {
+        update(&mut hasher, object.as_bytes());
+        update(&mut hasher, &Sha256::digest(std::fs::read(object).ok()?));
+    }
+
+    Some(format!("{:x}", hasher.finalize()))
+
+}
+
+impl Cache {
+
+    /// The cache inside of `private_path`, limited by [Run::cache_size].
+    pub fn new(private_path: &Path, config: &Run) -> Self {
+
+        Self {
+            path:  private_path.join(CACHE_DIRECTORY),
+            limit: config.cache_size.unwrap_or(DEFAULT_CACHE_SIZE) * 1024 * 1024,
+            wait:  config.wait
+        }
+
+    }
+
+    fn entry(&self, key: &str) -> PathBuf {
+
+        self.path.join(key).with_extension("out")
+
+    }
+
+    /// Copies the binary cached under `key` to `binary`, returns
+    /// `false` if there is none.
+    pub fn fetch(&self, key: &str, binary: &Path) -> bool {
+
+        let entry = self.entry(key);
+
+        let hit = entry.is_file() && std::fs::copy(&entry, binary).is_ok();
+
+        if hit {
+            // Marks the binary as recently used, nothing
+            // to be done if it fails.
+            _ = std::fs::File::options()
+                .write(true)
+                .open(&entry)
+                .and_then(|file| file.set_modified(SystemTime::now()));
+        }
+
+        self.count(hit);
+
+        hit
+
+    }
+
+    /// Caches `binary` under `key` & evicts the least recently
+    /// used binaries if the cache grew past its limit.
+    pub fn store(&self, key: &str, binary: &Path) -> std::io::Result<()> {
+
+        std::fs::create_dir_all(&self.path)?;
+
+        // Another run is writing into the cache,
+        // the binary is cached by the next run.
+        let Ok(_lock) = super::lock::cache(&self.path, self.wait) else {
+            return Ok(())
+        };
+
+        // Copied next to the entry first such that other runs
+        // never see a partially written binary.
+        let partial = self.entry(key).with_extension(format!("out.{}", std::process::id()));
+        std::fs::copy(binary, &partial)?;
+        std::fs::rename(&partial, self.entry(key))?;
+
+        self.evict();
+
+        Ok(())
+
+    }
+
+    /// Cached binaries along with their size & last use.
+    fn entries(&self) -> Vec<(PathBuf, u64, SystemTime)> {
+
+        let Ok(read_dir) = std::fs::read_dir(&self.path) else {
+            return vec![]
+        };
+
+        read_dir
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "out"))
+            .filter_map(|path| {
+                let metadata = path.metadata().ok()?;
+                Some((path, metadata.len(), metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+            })
+            .collect()
+
+    }
+
+    /// Removes the least recently used binaries until
+    /// the cache fits inside of [Cache::limit].
+    fn evict(&self) {
+
+        let mut entries = self.entries();
+        let mut size: u64 = entries.iter().map(|(_, size, _)| size).sum();
+
+        entries.sort_by_key(|(_, _, used)| *used);
+
+        for (path, entry_size, _) in entries {
+            if size <= self.limit {
+                break
+            }
+            if std::fs::remove_file(&path).is_ok() {
+                size -= entry_size;
+            }
+        }
+
+    }
+
+    fn counters(&self) -> Counters {
+
+        std::fs::read_to_string(self.path.join(CACHE_STATS_NAME))
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default()
+
+    }
+
+    /// Counts a hit or a miss, nothing to be done if it fails.
+    fn count(&self, hit: bool) {
+
+        if std::fs::create_dir_all(&self.path).is_err() {
+            return
+        }
+
+        let Ok(_lock) = super::lock::cache(&self.path, self.wait) else {
+            return
+        };
+
+        let mut counters = self.counters();
+
+        match hit {
+            true  => counters.hits += 1,
+            false => counters.misses += 1
+        }
+
+        if let Ok(contents) = toml::to_string(&counters) {
+            _ = std::fs::write(self.path.join(CACHE_STATS_NAME), contents);
+        }
+
+    }
+
+    pub fn stats(&self) -> CacheStats {
+
+        let entries = self.entries();
+        let counters = self.counters();
+
+        CacheStats {
+            path:    self.path.clone(),
+            entries: entries.len(),
+            size:    entries.iter().map(|(_, size, _)| size).sum(),
+            limit:   self.limit,
+            hits:    counters.hits,
+            misses:  counters.misses
+        }
+
+    }
+
+    /// Removes every cached binary & resets the counters,
+    /// returns the stats from before clearing.
+    pub fn clear(&self) -> Result<CacheStats, Alert> {
+
+        let stats = self.stats();
+
+        if !self.path.exists() {
+            return Ok(stats)
+        }
+
+        let _lock = match super::lock::cache(&self.path, self.wait) {
+            Ok(lock) => lock,
+            Err(err) => return Err(debugpush!(err))
+        };
+
+        let entries = self.entries()
+            .into_iter()
+            .map(|(path, _, _)| path)
+            .chain(Some(self.path.join(CACHE_STATS_NAME)).filter(|stats| stats.exists()));
+
+        for path in entries {
+            match std::fs::remove_file(&path) {
+                Ok(_) => (),
+                Err(err) => return error!{
+                    debug: debuginfo!(),
+                    description: format!("failed to clear the cache `{}`", self.path.to_string_lossy()),
+                    example: None,
+                    note: function_message!("std::fs::remove_file()", err.to_string())
+                }
+            }
+        }
+
+        Ok(stats)
+
+    }
+
+}
+
+impl std::fmt::Display for CacheStats {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        write!(f, "cache `{}`\n    {} binaries, {} of {}",
+            self.path.to_string_lossy(),
+            self.entries,
+            format_size(self.size),
+            format_size(self.limit)
+        )?;
+
+        let total = self.hits + self.misses;
+
+        if total > 0 {
+            write!(f, "\n    {} hits, {} misses ({:.1}% hit rate)",
+                self.hits,
+                self.misses,
+                self.hits as f64 * 100.0 / total as f64
+            )?;
+        }
+
+        Ok(())
+
+    }
+
+}
+
+/// Runs "cesty cache ...".
+pub fn cache(cache_conf: &CacheConfig) -> Result<Vec<Alert>, Alert> {
+
+    let cache = Cache::new(&super::batch::private_path(&cache_conf.run), &cache_conf.run);
+
+    match cache_conf.action {
+        CacheAction::Stats => println!("{}", cache.stats()),
+        CacheAction::Clear => {
+            let stats = cache.clear()?;
+            println!("removed {} binaries ({}) from `{}`",
+                stats.entries,
+                format_size(stats.size),
+                stats.path.to_string_lossy()
+            );
+        }
+    }
+
+    Ok(vec![])
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{
+        path::{Path, PathBuf},
+        time::{Duration, SystemTime}
+    };
+
+    use sha2::{Digest, Sha256};
+
+    use crate::{
+        defaults::CACHE_STATS_NAME,
+        test::compilable::{CompilableTest, ResolvedCompiler}
+    };
+
+    use super::{format_size, key, update, Cache};
+
+    /// Empty directory `name` inside of the temporary directory.
+    fn directory(name: &str) -> PathBuf {
+
+        let path = std::env::temp_dir().join(format!("cesty-cache-{name}-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+
+        path
+
+    }
+
+    fn test(harness: &Path, compiler: ResolvedCompiler) -> CompilableTest {
+
+        CompilableTest {
+            config: Default::default(),
+            path: harness.to_owned(),
+            artifacts: harness.with_extension(""),
+            file: PathBuf::from("ds.c"),
+            name: "cesty_a".to_owned(),
+            compiler,
+            include: harness.parent().unwrap().to_owned(),
+            sanitizers: vec![],
+            valgrind: false,
+            suppressions: vec![],
+            coverage: None,
+            bench: None,
+            fuzz_inputs: vec![],
+            property: None,
+            seed: None,
+            retries: 0,
+            skip: None,
+            xfail: None,
+            compile_fail: false,
+            expect_diagnostic: None,
+            cache: None
+        }
+
+    }
+
+    #[test]
+    fn update_separates_neighbouring_fields() {
+
+        let hash = |fields: &[&str]| {
+            let mut hasher = Sha256::new();
+            for field in fields {
+                update(&mut hasher, field.as_bytes());
+            }
+            hasher.finalize()
+        };
+
+        assert_eq!(hash(&["ab", "c"]), hash(&["ab", "c"]));
+        assert_ne!(hash(&["ab", "c"]), hash(&["a", "bc"]));
+        assert_ne!(hash(&["abc"]), hash(&["abc", ""]));
+
+    }
+
+    #[test]
+    fn formats_sizes() {
+
+        assert_eq!(format_size(0), "0.0 KiB");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(1048575), "1024.0 KiB");
+        assert_eq!(format_size(1048576), "1.0 MiB");
+        assert_eq!(format_size(5 * 1048576 + 524288), "5.5 MiB");
+
+    }
+
+    #[test]
+    fn evicts_least_recently_used_first() {
+
+        let path = directory("evict");
+        let cache = Cache { path: path.clone(), limit: 250, wait: false };
+
+        let now = SystemTime::now();
+
+        // Used `age` seconds ago.
+        for (name, age) in [("a", 20), ("b", 30), ("c", 10)] {
+            let entry = cache.entry(name);
+            std::fs::write(&entry, [0; 100]).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&entry)
+                .and_then(|file| file.set_modified(now - Duration::from_secs(age)))
+                .unwrap();
+        }
+        // Only binaries count towards the limit.
+        std::fs::write(path.join(CACHE_STATS_NAME), [0; 1000]).unwrap();
+
+        let cached = |cache: &Cache| ["a", "b", "c"].map(|name| cache.entry(name).exists());
+
+        cache.evict();
+        assert_eq!(cached(&cache), [true, false, true]);
+
+        let cache = Cache { limit: 100, ..cache };
+        cache.evict();
+        assert_eq!(cached(&cache), [false, false, true]);
+        assert_eq!(cache.stats().size, 100);
+
+        std::fs::remove_dir_all(path).unwrap();
+
+    }
+
+    #[test]
+    fn keys_depend_on_the_harness_not_where_it_is() {
+
+        let (first, second) = (directory("key-first"), directory("key-second"));
+
+        let compiler = ResolvedCompiler {
+            name: "gcc".to_owned(),
+            ..Default::default()
+        };
+
+        for directory in [&first, &second] {
+            std::fs::write(directory.join("harness.c"), "int main(void) { return 0; }\n").unwrap();
+        }
+
+        let Some(a) = key(&test(&first.join("harness.c"), compiler.clone())) else {
+            // No gcc to preprocess with.
+            return
+        };
+
+        assert_eq!(key(&test(&second.join("harness.c"), compiler.clone())), Some(a.clone()));
+        assert_eq!(a.len(), 64);
+
+        let optimized = ResolvedCompiler {
+            flags: vec!["-O2".to_owned()],
+            ..compiler.clone()
+        };
+        assert_ne!(key(&test(&first.join("harness.c"), optimized)), Some(a.clone()));
+
+        std::fs::write(second.join("harness.c"), "int main(void) { return 1; }\n").unwrap();
+        assert_ne!(key(&test(&second.join("harness.c"), compiler.clone())), Some(a));
+
+        // Objects that can't be read aren't cached.
+        let missing = ResolvedCompiler {
+            objects: vec![first.join("missing.o").to_string_lossy().to_string()],
+            ..compiler
+        };
+        assert_eq!(key(&test(&first.join("harness.c"), missing)), None);
+
+        for directory in [first, second] {
+            std::fs::remove_dir_all(directory).unwrap();
+        }
+
+    }
+
+}
@@ -0,0 +1,509 @@
+/*
+ * cesty.h - assertions for tests ran by cesty.
+ *
+ * Made available on the include path of every test harness, include
+ * it inside of a test file with `#include "cesty.h"`.
+ *
+ * CESTY_ASSERT_* stop the test on failure, CESTY_EXPECT_* record the
+ * failure & let the test continue. A test with any failed assertion
+ * fails regardless of what it returns.
+ *
+ * Failures are written to the file named by the CESTY_REPORT_FILE
+ * environment variable (one tab separated record per line) which cesty
+ * reads back after the test finishes, without it they're printed to stderr.
+ *
+ * Benchmarks are ran through cesty_bench(), which writes one sample per
+ * line (iterations & nanoseconds, tab separated) to the file named by
+ * CESTY_BENCH_FILE, or stdout without it.
+ *
+ * Inputs that crashed a fuzz target are replayed through cesty_fuzz_replay().
+ *
+ * Property tests are called once per row of CESTY_PROPERTY_INPUT through
+ * cesty_property_run(), the index of the row being ran is kept inside of
+ * CESTY_PROPERTY_PROGRESS so a failing or crashing row can be found.
+ */
+
+#ifndef CESTY_H
+#define CESTY_H
+
+#include <setjmp.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+
+#define CESTY_VALUE_LENGTH 256
+
+/* Amount of failed assertions inside of the current process. */
+static int cesty_failures = 0;
+
+/* Set by the harness, a failed CESTY_ASSERT_* jumps back to it. */
+static jmp_buf cesty_jump;
+static int     cesty_jump_set = 0;
+
+static inline FILE *cesty_report_file(void) {
+
+    static FILE *file   = NULL;
+    static int   opened = 0;
+
+    if (!opened) {
+        const char *path = getenv("CESTY_REPORT_FILE");
+        opened = 1;
+        if (path != NULL) {
+            file = fopen(path, "a");
+        }
+    }
+
+    return file;
+
+}
+
+static inline void cesty_report_escaped(FILE *file, const char *text) {
+
+    for (; text != NULL && *text != '\0'; text++) {
+        switch (*text) {
+            case '\\': fputs("\\\\", file); break;
+            case '\t': fputs("\\t", file);  break;
+            case '\n': fputs("\\n", file);  break;
+            case '\r': fputs("\\r", file);  break;
+            default:   fputc(*text, file);
+        }
+    }
+
+}
+
+static inline void cesty_report(
+    int         fatal,
+    const char *file,
+    int         line,
+    const char *macro,
+    const char *expression,
+    const char *expected,
+    const char *actual
+) {
+
+    FILE *report = cesty_report_file();
+
+    cesty_failures++;
+
+    if (report != NULL) {
+        fprintf(report, "%s\t", fatal ? "assert" : "expect");
+        cesty_report_escaped(report, file);
+        fprintf(report, "\t%d\t", line);
+        cesty_report_escaped(report, macro);
+        fputc('\t', report);
+        cesty_report_escaped(report, expression);
+        fputc('\t', report);
+        cesty_report_escaped(report, expected);
+        fputc('\t', report);
+        cesty_report_escaped(report, actual);
+        fputc('\n', report);
+        fflush(report);
+    } else {
+        fprintf(stderr, "%s:%d: %s(%s) failed, expected %s, got %s\n",
+            file, line, macro, expression, expected, actual);
+    }
+
+    if (fatal) {
+        if (cesty_jump_set) {
+            longjmp(cesty_jump, 1);
+        }
+        exit(1);
+    }
+
+}
+
+static inline void cesty_check(int fatal, int passed, const char *file, int line, const char *macro, const char *expression) {
+
+    if (!passed) {
+        cesty_report(fatal, file, line, macro, expression, "true", "false");
+    }
+
+}
+
+static inline void cesty_check_int(int fatal, int equal, long long expected, long long actual,
+    const char *file, int line, const char *macro, const char *expression) {
+
+    char expected_text[CESTY_VALUE_LENGTH];
+    char actual_text[CESTY_VALUE_LENGTH];
+
+    if ((expected == actual) == equal) {
+        return;
+    }
+
+    snprintf(expected_text, sizeof(expected_text), "%s%lld", equal ? "" : "not ", expected);
+    snprintf(actual_text,   sizeof(actual_text),   "%lld", actual);
+    cesty_report(fatal, file, line, macro, expression, expected_text, actual_text);
+
+}
+
+static inline void cesty_quote(char *out, size_t size, const char *text) {
+
+    if (text == NULL) {
+        snprintf(out, size, "NULL");
+    } else {
+        snprintf(out, size, "\"%s\"", text);
+    }
+
+}
+
+static inline void cesty_check_str(int fatal, const char *expected, const char *actual,
+    const char *file, int line, const char *macro, const char *expression) {
+
+    char expected_text[CESTY_VALUE_LENGTH];
+    char actual_text[CESTY_VALUE_LENGTH];
+
+    if (expected == actual
+    || (expected != NULL && actual != NULL && strcmp(expected, actual) == 0)) {
+        return;
+    }
+
+    cesty_quote(expected_text, sizeof(expected_text), expected);
+    cesty_quote(actual_text,   sizeof(actual_text),   actual);
+    cesty_report(fatal, file, line, macro, expression, expected_text, actual_text);
+
+}
+
+static inline void cesty_hex(char *out, size_t size, const unsigned char *bytes, size_t length, size_t from) {
+
+    size_t written = 0;
+    size_t index;
+
+    out[0] = '\0';
+
+    if (from > 0) {
+        written += snprintf(out + written, size - written, "[%lu] ", (unsigned long) from);
+    }
+
+    for (index = from; index < length && written + 4 < size; index++) {
+        written += snprintf(out + written, size - written, "%02x ", bytes[index]);
+    }
+
+    if (index < length && written + 4 < size) {
+        snprintf(out + written, size - written, "...");
+    }
+
+}
+
+static inline void cesty_check_mem(int fatal, const void *expected, const void *actual, size_t length,
+    const char *file, int line, const char *macro, const char *expression) {
+
+    char expected_text[CESTY_VALUE_LENGTH];
+    char actual_text[CESTY_VALUE_LENGTH];
+    const unsigned char *expected_bytes = (const unsigned char *) expected;
+    const unsigned char *actual_bytes   = (const unsigned char *) actual;
+    size_t first = 0;
+
+    if (memcmp(expected, actual, length) == 0) {
+        return;
+    }
+
+    while (first < length && expected_bytes[first] == actual_bytes[first]) {
+        first++;
+    }
+
+    cesty_hex(expected_text, sizeof(expected_text), expected_bytes, length, first);
+    cesty_hex(actual_text,   sizeof(actual_text),   actual_bytes,   length, first);
+    cesty_report(fatal, file, line, macro, expression, expected_text, actual_text);
+
+}
+
+static inline void cesty_check_near(int fatal, double expected, double actual, double epsilon,
+    const char *file, int line, const char *macro, const char *expression) {
+
+    char expected_text[CESTY_VALUE_LENGTH];
+    char actual_text[CESTY_VALUE_LENGTH];
+    double difference = expected > actual ? expected - actual : actual - expected;
+
+    if (difference <= epsilon) {
+        return;
+    }
+
+    snprintf(expected_text, sizeof(expected_text), "%.17g (+- %g)", expected, epsilon);
+    snprintf(actual_text,   sizeof(actual_text),   "%.17g", actual);
+    cesty_report(fatal, file, line, macro, expression, expected_text, actual_text);
+
+}
+
+/* Monotonic time in nanoseconds, falls back to the wall clock
+ * when CLOCK_MONOTONIC isn't exposed by the selected C standard. */
+static inline double cesty_bench_now(void) {
+
+    struct timespec now;
+
+#if defined(CLOCK_MONOTONIC)
+    clock_gettime(CLOCK_MONOTONIC, &now);
+#elif defined(TIME_UTC)
+    timespec_get(&now, TIME_UTC);
+#else
+    return (double) clock() * 1e9 / CLOCKS_PER_SEC;
+#endif
+
+    return (double) now.tv_sec * 1e9 + (double) now.tv_nsec;
+
+}
+
+static inline long cesty_bench_setting(const char *name, long fallback) {
+
+    const char *value = getenv(name);
+    long parsed;
+
+    if (value == NULL) {
+        return fallback;
+    }
+
+    parsed = strtol(value, NULL, 10);
+    return parsed > 0 ? parsed : fallback;
+
+}
+
+/* Runs body repeatedly, first warming up for a tenth of the measuring
+ * time while doubling the iterations to estimate a single iteration,
+ * then taking CESTY_BENCH_SAMPLES samples which together take roughly
+ * CESTY_BENCH_TIME_MS milliseconds. */
+static inline void cesty_bench(void (*body)(void *), void *context) {
+
+    long   samples = cesty_bench_setting("CESTY_BENCH_SAMPLES", 20);
+    double time    = (double) cesty_bench_setting("CESTY_BENCH_TIME_MS", 1000) * 1e6;
+    const char *path = getenv("CESTY_BENCH_FILE");
+    FILE  *out = path != NULL ? fopen(path, "w") : NULL;
+
+    unsigned long long iterations = 1;
+    unsigned long long done       = 0;
+    unsigned long long index;
+    double start = cesty_bench_now();
+    double elapsed = 0;
+    double per_sample;
+    long   sample;
+
+    if (out == NULL) {
+        out = stdout;
+    }
+
+    while (elapsed < time / 10 || done == 0) {
+        for (index = 0; index < iterations; index++) {
+            body(context);
+        }
+        done      += iterations;
+        iterations *= 2;
+        elapsed    = cesty_bench_now() - start;
+    }
+
+    per_sample = (time / samples) / (elapsed / done);
+    iterations = per_sample < 1 ? 1 : (unsigned long long) per_sample;
+
+    for (sample = 0; sample < samples; sample++) {
+        start = cesty_bench_now();
+        for (index = 0; index < iterations; index++) {
+            body(context);
+        }
+        fprintf(out, "%llu\t%.0f\n", iterations, cesty_bench_now() - start);
+    }
+
+    fflush(out);
+
+    if (out != stdout) {
+        fclose(out);
+    }
+
+}
+
+/* Reads the file at path & passes it to a fuzz target, returns
+ * non zero if the file can't be read. */
+static inline int cesty_fuzz_replay(const char *path, int (*target)(const uint8_t *, size_t)) {
+
+    FILE    *file = path != NULL ? fopen(path, "rb") : NULL;
+    uint8_t *data = NULL;
+    size_t   size = 0;
+    size_t   capacity = 0;
+    size_t   read;
+
+    if (file == NULL) {
+        fprintf(stderr, "cesty: failed to open the fuzz input `%s`\n", path != NULL ? path : "");
+        return 1;
+    }
+
+    do {
+        if (size == capacity) {
+            uint8_t *grown;
+            capacity = capacity == 0 ? 4096 : capacity * 2;
+            grown = (uint8_t *) realloc(data, capacity);
+            if (grown == NULL) {
+                free(data);
+                fclose(file);
+                return 1;
+            }
+            data = grown;
+        }
+        read  = fread(data + size, 1, capacity - size, file);
+        size += read;
+    } while (read > 0);
+
+    fclose(file);
+
+    target(data, size);
+    free(data);
+
+    return 0;
+
+}
+
+/* Reads a line of any length, NULL at the end of the file. */
+static inline char *cesty_property_line(FILE *file) {
+
+    size_t length = 0;
+    size_t capacity = 128;
+    char  *line = (char *) malloc(capacity);
+    int    ch;
+
+    if (line == NULL) {
+        return NULL;
+    }
+
+    while ((ch = fgetc(file)) != EOF && ch != '\n') {
+        if (length + 1 == capacity) {
+            char *grown = (char *) realloc(line, capacity * 2);
+            if (grown == NULL) {
+                free(line);
+                return NULL;
+            }
+            line = grown;
+            capacity *= 2;
+        }
+        line[length++] = (char) ch;
+    }
+
+    if (ch == EOF && length == 0) {
+        free(line);
+        return NULL;
+    }
+
+    line[length] = '\0';
+    return line;
+
+}
+
+/* Splits line into arity tab separated fields & unescapes them in place. */
+static inline int cesty_property_fields(char *line, char **fields, int arity) {
+
+    int   field = 0;
+    char *read  = line;
+    char *write = line;
+
+    if (arity > 0) {
+        fields[field++] = write;
+    }
+
+    for (; *read != '\0'; read++) {
+        if (*read == '\t') {
+            *write++ = '\0';
+            if (field == arity) {
+                return 0;
+            }
+            fields[field++] = write;
+        } else if (*read == '\\' && read[1] != '\0') {
+            read++;
+            switch (*read) {
+                case 't': *write++ = '\t'; break;
+                case 'n': *write++ = '\n'; break;
+                case 'r': *write++ = '\r'; break;
+                default:  *write++ = *read;
+            }
+        } else {
+            *write++ = *read;
+        }
+    }
+
+    *write = '\0';
+    return field == arity;
+
+}
+
+/* Calls row with the fields of every line of CESTY_PROPERTY_INPUT, stops
+ * at the first row that fails or fails an assertion. Returns non zero if a
+ * row failed, CESTY_PROPERTY_PROGRESS then holds its index. */
+static inline int cesty_property_run(int (*row)(char **), int arity) {
+
+    const char *input_path    = getenv("CESTY_PROPERTY_INPUT");
+    const char *progress_path = getenv("CESTY_PROPERTY_PROGRESS");
+    FILE  *input    = input_path != NULL ? fopen(input_path, "r") : NULL;
+    FILE  *progress = progress_path != NULL ? fopen(progress_path, "w") : NULL;
+    char **fields   = (char **) calloc(arity > 0 ? (size_t) arity : 1, sizeof(char *));
+    char  *line;
+    long   index = 0;
+    int    failed = 0;
+
+    if (input == NULL || fields == NULL) {
+        fprintf(stderr, "cesty: failed to open the property input `%s`\n", input_path != NULL ? input_path : "");
+        return 1;
+    }
+
+    while (!failed && (line = cesty_property_line(input)) != NULL) {
+
+        if (!cesty_property_fields(line, fields, arity)) {
+            fprintf(stderr, "cesty: property input row %ld doesn't have %d fields\n", index, arity);
+            failed = 1;
+        } else {
+            if (progress != NULL) {
+                rewind(progress);
+                fprintf(progress, "%ld\n", index);
+                fflush(progress);
+            }
+            failed = !row(fields) || cesty_failures != 0;
+        }
+
+        free(line);
+        index++;
+
+    }
+
+    free(fields);
+    fclose(input);
+
+    if (progress != NULL) {
+        fclose(progress);
+    }
+
+    return failed;
+
+}
+
+#define CESTY_ASSERT(expression) \
+    cesty_check(1, (expression) ? 1 : 0, __FILE__, __LINE__, "CESTY_ASSERT", #expression)
+
+#define CESTY_EXPECT(expression) \
+    cesty_check(0, (expression) ? 1 : 0, __FILE__, __LINE__, "CESTY_EXPECT", #expression)
+
+#define CESTY_ASSERT_EQ_INT(expected, actual) \
+    cesty_check_int(1, 1, (long long) (expected), (long long) (actual), __FILE__, __LINE__, "CESTY_ASSERT_EQ_INT", #expected ", " #actual)
+
+#define CESTY_EXPECT_EQ_INT(expected, actual) \
+    cesty_check_int(0, 1, (long long) (expected), (long long) (actual), __FILE__, __LINE__, "CESTY_EXPECT_EQ_INT", #expected ", " #actual)
+
+#define CESTY_ASSERT_NE_INT(expected, actual) \
+    cesty_check_int(1, 0, (long long) (expected), (long long) (actual), __FILE__, __LINE__, "CESTY_ASSERT_NE_INT", #expected ", " #actual)
+
+#define CESTY_EXPECT_NE_INT(expected, actual) \
+    cesty_check_int(0, 0, (long long) (expected), (long long) (actual), __FILE__, __LINE__, "CESTY_EXPECT_NE_INT", #expected ", " #actual)
+
+#define CESTY_ASSERT_EQ_STR(expected, actual) \
+    cesty_check_str(1, (expected), (actual), __FILE__, __LINE__, "CESTY_ASSERT_EQ_STR", #expected ", " #actual)
+
+#define CESTY_EXPECT_EQ_STR(expected, actual) \
+    cesty_check_str(0, (expected), (actual), __FILE__, __LINE__, "CESTY_EXPECT_EQ_STR", #expected ", " #actual)
+
+#define CESTY_ASSERT_EQ_MEM(expected, actual, length) \
+    cesty_check_mem(1, (expected), (actual), (length), __FILE__, __LINE__, "CESTY_ASSERT_EQ_MEM", #expected ", " #actual ", " #length)
+
+#define CESTY_EXPECT_EQ_MEM(expected, actual, length) \
+    cesty_check_mem(0, (expected), (actual), (length), __FILE__, __LINE__, "CESTY_EXPECT_EQ_MEM", #expected ", " #actual ", " #length)
+
+#define CESTY_ASSERT_NEAR(expected, actual, epsilon) \
+    cesty_check_near(1, (expected), (actual), (epsilon), __FILE__, __LINE__, "CESTY_ASSERT_NEAR", #expected ", " #actual ", " #epsilon)
+
+#define CESTY_EXPECT_NEAR(expected, actual, epsilon) \
+    cesty_check_near(0, (expected), (actual), (epsilon), __FILE__, __LINE__, "CESTY_EXPECT_NEAR", #expected ", " #actual ", " #epsilon)
+
+#endif /* CESTY_H */
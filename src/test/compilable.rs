@@ -11,64 +11,1088 @@
 //!   - Otherwise the test is created inside the ".cesty" folder located
 //!     in the same directory as the config file.
 //!
+//! Standalone
+//! ----------
+//! A standalone test is created from [super::extract::Environment::mainless],
+//! it recompiles everything inside of the file together with the test.
+//!
+//! A test that isn't standalone is created from [super::extract::Environment::templated]
+//! (only declarations remain) and is linked against the object files of
+//! the build instead, such that the test runs the code that was
+//! actually compiled by the build.
+//!
+//! Harnesses
+//! ---------
+//! By default the tests of a file are compiled once into a single
+//! harness whose `main()` runs the test named by its first argument,
+//! every test still runs in its own process. Tests with different
+//! compiler settings, sanitizers, valgrind or coverage are split into
+//! separate harnesses (`<file>-2.c`...), tests with `harness = "test"`,
+//! `compile_fail` tests & skipped tests get their own (`<file>_<test>.c`).
+//!
+//! Fuzz targets with saved crash inputs are always compiled with
+//! [Sanitizer::Address], with clang when it's available, as the
+//! inputs were found under it & most of them don't crash without it.
+//!
 //! ./cesty run all -D ../..
 
-use std::path::PathBuf;
+use std::{
+    path::{Component, Path, PathBuf},
+    process::{Command, Output}
+};
+
+use indoc::formatdoc;
+use regex::Regex;
+
+use crate::{
+    arg_conf::{HarnessMode, Recipe, Run, Sanitizer},
+    defaults::{
+        ASSERTION_HEADER_NAME,
+        DEFAULT_COMPILER_NAME,
+        DEFAULT_FUZZ_COMPILER,
+        DEFAULT_INT_PASS_VALUE,
+        HARNESS_FIXTURE_FAILURE_CODE,
+        HARNESS_SETUP_ARGUMENT,
+        HARNESS_TEARDOWN_ARGUMENT
+    },
+    error::{
+        debuginfo, error, function_message,
+        warning, Alert, AlertInfo
+    }
+};
+
+use super::{
+    batch::TestBatchFolder,
+    extract::{
+        blank_range, Function, ParsedFile, 
+        ParsedTest, ReturnConvention
+    },
+    property::Property,
+    Config, Marker
+};
 
-use indoc::{formatdoc, indoc};
+/// Compiler, flags, libraries & objects a test is compiled with
+/// after merging the global, file & test configs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResolvedCompiler {
 
-use super::extract::{ParsedFile, ParsedTest};
+    /// A C compiler to use while compiling/linking.
+    pub name: String,
+
+    /// What flags to use while compiling/linking.
+    pub flags: Vec<String>,
+
+    /// What libraries to use while compiling/linking.
+    pub libraries: Vec<String>,
+
+    /// Object files/static libraries the test is linked against.
+    pub objects: Vec<String>
+
+}
 
 /// A file created from a [super::extract::ParsedTest].
+#[derive(Clone, Debug)]
 pub struct CompilableTest {
 
     /// Parsed from the function docs.
     pub config: super::Config,
 
-    /// File path of the compilable test.
-    pub path: PathBuf
+    /// File path of the harness the test is compiled from, shared
+    /// with the other tests of its file, see [HarnessMode].
+    pub path: PathBuf,
+
+    /// Path the reports, logs & inputs of the test are named after,
+    /// by replacing its extension.
+    pub artifacts: PathBuf,
+
+    /// File the test was extracted from.
+    pub file: PathBuf,
+
+    /// Full function name of the test.
+    pub name: String,
+
+    /// Compiler settings used for this test.
+    pub compiler: ResolvedCompiler,
+
+    /// Directory containing the bundled `cesty.h`.
+    pub include: PathBuf,
+
+    /// Sanitizers the test is compiled & ran with.
+    pub sanitizers: Vec<Sanitizer>,
+
+    /// Run the test under valgrind.
+    pub valgrind: bool,
+
+    /// Valgrind suppression files from [Recipe::suppressions].
+    pub suppressions: Vec<String>,
+
+    /// Build the test with coverage instrumentation, see [Run::coverage].
+    pub coverage: Option<super::coverage::Toolchain>,
+
+    /// Sampling settings if the test is a benchmark,
+    /// see [ParsedTest::is_benchmark].
+    pub bench: Option<super::bench::BenchSettings>,
+
+    /// Inputs that crashed the test if it's a fuzz target, each is
+    /// replayed as its own regression test under [Sanitizer::Address].
+    pub fuzz_inputs: Vec<PathBuf>,
+
+    /// Generators & seed if the test is a property test.
+    pub property: Option<Property>,
+
+    /// Seed of the run, see [Run::seed].
+    pub seed: Option<u64>,
+
+    /// Times the test is retried after failing, see [super::Settings::retries].
+    pub retries: u32,
+
+    /// Reason the test is skipped, see [super::Settings::skip].
+    pub skip: Option<String>,
+
+    /// Reason the test is expected to fail, see [super::Settings::xfail].
+    pub xfail: Option<String>,
+
+    /// The test passes only if it fails to compile, see [super::Settings::compile_fail].
+    pub compile_fail: bool,
+
+    /// Compiled [super::Settings::expect_diagnostic].
+    pub expect_diagnostic: Option<Regex>,
+
+    /// Cache the binary is reused from, [None] with `--no-cache`
+    /// & for coverage builds.
+    pub cache: Option<super::cache::Cache>
+
+}
+
+/// A [CompilableTest] that was successfully compiled.
+#[derive(Clone, Debug)]
+pub struct CompiledTest {
+
+    /// The test the binary was compiled from.
+    pub test: CompilableTest,
+
+    /// Path of the compiled binary.
+    pub binary: PathBuf,
+
+    /// Key of the harness, see [super::cache::key], [None]
+    /// if it couldn't be computed.
+    pub fingerprint: Option<String>
 
 }
 
-fn create_compilable_test(parsed_test: &ParsedTest, parsed_file: &ParsedFile) -> String {
-    
+/// C expression that calls `function`, evaluating to a non zero
+/// value if the function succeeded.
+/// 
+/// - `void` functions always succeed.
+/// - `bool` functions succeed when they return `true`.
+/// - pointer functions store their result into `cesty_context`
+///   & succeed when it's not `NULL`.
+/// - anything else (`int`...) succeeds when it returns `0`.
+fn fixture_call(function: &Function) -> String {
+
+    let call = format!("{}({})", function.name, context_argument(function));
+
+    match function.return_convention() {
+        Some(ReturnConvention::Void) => format!("({call}, 1)"),
+        Some(ReturnConvention::Bool) => format!("({call})"),
+        _ if function.returns.trim_end().ends_with('*') 
+            => format!("((cesty_context = (void *) {call}) != 0)"),
+        _ => format!("(({call}) == 0)")
+    }
+
+}
+
+/// C statements that call the test & set `cesty_result` to `0`
+/// if it passed, based on its [ReturnConvention].
+fn test_call(function: &Function, pass_value: i64) -> String {
+
+    let call = format!("{}({})", function.name, context_argument(function));
+
+    match function.return_convention() {
+        Some(ReturnConvention::Bool) => format!("cesty_result = {call} ? 0 : 1;"),
+        Some(ReturnConvention::Int)  => format!("cesty_result = ({call}) == {pass_value} ? 0 : 1;"),
+        _ => format!("{call};")
+    }
+
+}
+
+/// Function wrapping a benchmark for `cesty_bench()` from `cesty.h`,
+/// the context is passed along like it would be to a test.
+fn bench_body(function: &Function, index: usize) -> String {
+
     formatdoc!{
         "
+            static void cesty_bench_body_{index}(void *cesty_context) {{
+                (void) cesty_context;
+                (void) {name}({argument});
+            }}
+        ",
+        name     = function.name,
+        argument = context_argument(function)
+    }
+
+}
+
+/// Function calling a property test with the fields of a single row,
+/// see `cesty_property_run()` from `cesty.h`. Evaluates to a non zero
+/// value if the row passed.
+fn property_row(function: &Function, property: &Property, pass_value: i64, index: usize) -> String {
+
+    let call = format!("{}({})",
+        function.name,
+        (0..property.args.len())
+            .map(|index| property.conversion(index))
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+
+    formatdoc!{
+        "
+            static int cesty_property_row_{index}(char **cesty_fields) {{
+                return {passed};
+            }}
+        ",
+        passed = match function.return_convention() {
+            Some(ReturnConvention::Bool) => format!("{call} ? 1 : 0"),
+            Some(ReturnConvention::Int)  => format!("({call}) == {pass_value}"),
+            _ => format!("({call}, 1)")
+        }
+    }
+
+}
+
+/// Reason of `marker` if its conditions hold for a test compiled
+/// with `compiler` from a file containing `contents`.
+fn marker_reason(marker: &Marker, compiler: &ResolvedCompiler, contents: &str) -> Option<String> {
+
+    let (reason, compiler_name, defined) = match marker {
+        Marker::Reason(reason) => return Some(reason.clone()),
+        Marker::Conditional { reason, compiler, defined } => (reason, compiler, defined)
+    };
+
+    if let Some(compiler_name) = compiler_name {
+        let name = Path::new(&compiler.name)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if !name.starts_with(compiler_name.as_str()) {
+            return None
+        }
+    }
+
+    if let Some(defined) = defined {
+        let by_flag = compiler.flags.iter().enumerate().any(|(index, flag)| {
+            let definition = match flag.strip_prefix("-D") {
+                Some("") => compiler.flags.get(index + 1).map(String::as_str).unwrap_or_default(),
+                Some(definition) => definition,
+                None => return false
+            };
+            definition.split('=').next() == Some(defined.as_str())
+        });
+        let by_define = contents
+            .lines()
+            .filter_map(|line| line.trim_start().strip_prefix('#'))
+            .filter_map(|line| line.trim_start().strip_prefix("define"))
+            .any(|line| line.starts_with(char::is_whitespace)
+                && line.trim_start().split(|c: char| !c.is_alphanumeric() && c != '_').next() == Some(defined.as_str()));
+        if !by_flag && !by_define {
+            return None
+        }
+    }
+
+    Some(reason.clone())
+
+}
+
+/// The context argument passed to a fixture/test, a pointer to
+/// the context for `**` arguments, the context itself for
+/// other pointers & nothing otherwise.
+fn context_argument(function: &Function) -> &'static str {
+
+    match function.args.first().map(|arg| arg.replace(' ', "")) {
+        Some(arg) if function.args.len() == 1 && arg.ends_with("**") => "(void *) &cesty_context",
+        Some(arg) if function.args.len() == 1 && arg.ends_with('*')  => "cesty_context",
+        _ => ""
+    }
+
+}
+
+/// Function running the test at `index` of a harness, its
+/// fixtures & assertions, returns `0` if the test passed.
+fn test_function(
+    parsed_test: &ParsedTest,
+    compilable:  &CompilableTest,
+    parsed_file: &ParsedFile,
+    index:       usize
+) -> String {
+
+    let fixtures = &parsed_file.fixtures;
+
+    let pass_value = parsed_test.config.settings.pass_value
+        .or(parsed_file.config.settings.pass_value)
+        .unwrap_or(DEFAULT_INT_PASS_VALUE);
+
+    let each = |fixture: &Option<ParsedTest>| match fixture {
+        Some(fixture) => formatdoc!{
+            "
+                if (!{call}) {{
+                    return {HARNESS_FIXTURE_FAILURE_CODE};
+                }}
+            ",
+            call = fixture_call(&fixture.function)
+        },
+        None => String::new()
+    };
+
+    let helper = if let Some(property) = compilable.property.as_ref() {
+        property_row(&parsed_test.function, property, pass_value, index)
+    } else if parsed_test.is_benchmark() {
+        bench_body(&parsed_test.function, index)
+    } else {
+        String::new()
+    };
+
+    let test = if let Some(property) = compilable.property.as_ref() {
+        format!("cesty_result = cesty_property_run(cesty_property_row_{index}, {});", property.args.len())
+    } else if parsed_test.is_benchmark() {
+        format!("cesty_bench(cesty_bench_body_{index}, cesty_context);")
+    } else if parsed_test.is_fuzz_target() {
+        format!("cesty_result = cesty_fuzz_replay(argc > 2 ? argv[2] : 0, (int (*)(const uint8_t *, size_t)) {});",
+            parsed_test.function.name)
+    } else {
+        test_call(&parsed_test.function, pass_value)
+    };
+
+    formatdoc!{
+        "
+            {helper}
+            static int cesty_harness_test_{index}(int argc, char **argv) {{
+
+                void *cesty_context = 0;
+                (void) cesty_context;
+                (void) argc;
+                (void) argv;
+
+            {before_each}
+                volatile int cesty_result = 0;
+                if (setjmp(cesty_jump) == 0) {{
+                    cesty_jump_set = 1;
+                    {test}
+                }} else {{
+                    cesty_result = 1;
+                }}
+                cesty_jump_set = 0;
+                if (cesty_failures != 0) {{
+                    cesty_result = 1;
+                }}
+
+            {after_each}
+                return cesty_result;
+
+            }}
+        ",
+        before_each = indent(&each(&fixtures.before_each)),
+        after_each  = indent(&each(&fixtures.after_each))
+    }
+
+}
+
+/// Source of a harness placed at `path` containing `tests`, its
+/// `main()` runs the test named by the first argument or a file
+/// wide fixture when given [HARNESS_SETUP_ARGUMENT] or
+/// [HARNESS_TEARDOWN_ARGUMENT].
+/// 
+/// The environment is preceded by a `#line` directive pointing at the
+/// parsed file, such that diagnostics point at the code the user wrote,
+/// & followed by one pointing back at the harness itself.
+fn create_harness(
+    tests:       &[(&ParsedTest, &CompilableTest)],
+    parsed_file: &ParsedFile,
+    path:        &Path,
+    standalone:  bool
+) -> String {
+
+    let fixtures = &parsed_file.fixtures;
+
+    let env = if standalone {
+        let mut mainless = parsed_file.environment.mainless.clone();
+        for compile_fail in parsed_file.test.iter().filter(|test| test.config.settings.compile_fail
+            && !tests.iter().any(|(parsed_test, _)| test.function.name == parsed_test.function.name))
+        {
+            blank_range(&mut mainless, (compile_fail.range.template.0, compile_fail.range.body.1));
+        }
+        mainless
+    } else {
+        parsed_file.environment.templated.clone()
+    };
+
+    // The include & the `#line` directive before the environment
+    // take 2 lines, the directive after it takes 1 more.
+    let harness_line = env.matches('\n').count() + 5;
+
+    let fixture_branch = |fixture: &Option<ParsedTest>, argument: &str| match fixture {
+        Some(fixture) => formatdoc!{
+            "
+                if (cesty_harness_equal(argv[1], \"{argument}\")) {{
+                    return {call} ? 0 : 1;
+                }}
+            ",
+            call = fixture_call(&fixture.function)
+        },
+        None => String::new()
+    };
+
+    let dispatch: String = tests
+        .iter()
+        .enumerate()
+        .map(|(index, (parsed_test, _))| formatdoc!{
+            "
+                if (cesty_harness_equal(argv[1], \"{name}\")) {{
+                    return cesty_harness_test_{index}(argc, argv);
+                }}
+            ",
+            name = parsed_test.function.name
+        })
+        .collect();
+
+    formatdoc!{
+        "
+            #include \"{ASSERTION_HEADER_NAME}\"
+            #line 1 \"{origin}\"
             {env}
-            
-            int main() {{
-                
-                return {func}() == true ? 0 : 1;
-                
+            #line {harness_line} \"{harness}\"
+            static int cesty_harness_equal(const char *a, const char *b) {{
+                while (*a != '\\0' && *a == *b) {{
+                    a++;
+                    b++;
+                }}
+                return *a == *b;
+            }}
+            {functions}
+            int main(int argc, char **argv) {{
+
+                void *cesty_context = 0;
+                (void) cesty_context;
+
+                if (argc < 2) {{
+                    fprintf(stderr, \"cesty: no test to run was given\\n\");
+                    return 1;
+                }}
+
+            {setup}
+            {teardown}
+            {dispatch}
+                fprintf(stderr, \"cesty: unknown test `%s`\\n\", argv[1]);
+                return 1;
+
             }}
         ",
-        env  = parsed_file.environment.mainless,
-        func = parsed_test.function.name
+        origin    = parsed_file.path.to_string_lossy().replace('\\', "\\\\"),
+        harness   = path.to_string_lossy().replace('\\', "\\\\"),
+        functions = tests
+            .iter()
+            .enumerate()
+            .map(|(index, (parsed_test, compilable))| test_function(parsed_test, compilable, parsed_file, index))
+            .collect::<Vec<String>>()
+            .join("\n"),
+        setup    = indent(&fixture_branch(&fixtures.setup, HARNESS_SETUP_ARGUMENT)),
+        teardown = indent(&fixture_branch(&fixtures.teardown, HARNESS_TEARDOWN_ARGUMENT)),
+        dispatch = indent(&dispatch)
     }
-    
+
+}
+
+/// Indents every line of a generated harness block by 4 spaces.
+fn indent(block: &str) -> String {
+
+    block.lines()
+        .map(|line| if line.is_empty() { String::new() } else { format!("    {line}") })
+        .collect::<Vec<String>>()
+        .join("\n")
+
+}
+
+/// Matches `text` against `pattern` where `*` in the pattern
+/// matches any amount of characters, used for [super::CompilerReplaceItem::old].
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+
+    let parts: Vec<&str> = pattern.split('*').collect();
+
+    if parts.len() == 1 {
+        return pattern == text
+    }
+
+    let (first, last) = (parts[0], parts[parts.len()-1]);
+
+    if !text.starts_with(first)
+    || !text[first.len()..].ends_with(last)
+    {
+        return false
+    }
+
+    let mut remaining = &text[first.len()..text.len()-last.len()];
+
+    for part in &parts[1..parts.len()-1] {
+        match remaining.find(part) {
+            Some(position) => remaining = &remaining[position + part.len()..],
+            None => return false
+        }
+    }
+
+    true
+
+}
+
+impl ResolvedCompiler {
+
+    /// Merges the compiler configs, the test config takes precedence
+    /// over the file config which takes precedence over the global config.
+    ///
+    /// - `name`, `flags` & `libraries` are overwritten by the most
+    ///   specific config that sets them.
+    /// - `append` & `replace` from both the file & test config are applied.
+    /// - `objects` are only used when the test isn't standalone, they're
+    ///   accumulated from the recipe, file & test configs.
+    pub fn resolve(
+        run_conf:    &Run,
+        recipe:      Option<&Recipe>,
+        file_config: &Config,
+        config:      &Config,
+        standalone:  bool
+    ) -> Self {
+
+        let global = run_conf.compiler.clone().unwrap_or_default();
+
+        let name = config.compiler.name.clone()
+            .or(file_config.compiler.name.clone())
+            .or(global.name)
+            .unwrap_or(DEFAULT_COMPILER_NAME.to_owned());
+
+        let mut flags = if !config.compiler.flags.is_empty() {
+            config.compiler.flags.clone()
+        } else if !file_config.compiler.flags.is_empty() {
+            file_config.compiler.flags.clone()
+        } else {
+            global.flags
+        };
+
+        let mut libraries = if !config.compiler.libraries.is_empty() {
+            config.compiler.libraries.clone()
+        } else if !file_config.compiler.libraries.is_empty() {
+            file_config.compiler.libraries.clone()
+        } else {
+            global.libraries
+        };
+
+        for compiler in [&file_config.compiler, &config.compiler] {
+
+            if let Some(append) = compiler.append.as_ref() {
+                flags.append(&mut append.flags.clone());
+                libraries.append(&mut append.libraries.clone());
+            }
+
+            if let Some(replace) = compiler.replace.as_ref() {
+                for item in replace.flag.iter() {
+                    flags.iter_mut()
+                        .filter(|flag| wildcard_match(&item.old, flag))
+                        .for_each(|flag| *flag = item.new.clone());
+                }
+                for item in replace.library.iter() {
+                    libraries.iter_mut()
+                        .filter(|library| wildcard_match(&item.old, library))
+                        .for_each(|library| *library = item.new.clone());
+                }
+            }
+
+        }
+
+        let objects = if standalone {
+            vec![]
+        } else {
+            recipe
+                .map(|recipe| recipe.objects.clone())
+                .unwrap_or_default()
+                .into_iter()
+                .chain(file_config.compiler.objects.clone())
+                .chain(config.compiler.objects.clone())
+                .collect()
+        };
+
+        ResolvedCompiler {
+            name,
+            flags,
+            libraries,
+            objects
+        }
+
+    }
+
 }
 
 impl CompilableTest {
 
+    /// Creates a harness for every test inside of `parsed_file` and
+    /// writes them into the `batch_folder`, mirroring the directory
+    /// structure of the parsed file relative to the current pwd.
+    /// 
+    /// Benchmarks are only included if [Run::runs_benchmarks], fuzz
+    /// targets only if they have crashing inputs to replay.
     pub fn from_parsed_file(
-        parsed_file: ParsedFile,
-        config: &crate::arg_conf::Run
-    ) -> Vec<CompilableTest> {
-        
-        let compilable_tests: Vec<CompilableTest> = vec![];
-        
-        for parsed_test in parsed_file.test.iter() {
-            
-            let file = create_compilable_test(&parsed_test, &parsed_file);
-            
-            println!("{}", file);
-            
-            
+        parsed_file:  &ParsedFile,
+        config:       &Run,
+        recipe:       Option<&Recipe>,
+        batch_folder: &TestBatchFolder
+    ) -> Result<(Vec<CompilableTest>, Vec<Alert>), Alert> {
+
+        let mut warnings: Vec<Alert> = vec![];
+        let mut pending: Vec<(&ParsedTest, bool, bool, CompilableTest)> = vec![];
+
+        let directory = {
+            let relative = match std::env::current_dir() {
+                Ok(pwd) => parsed_file.path.strip_prefix(pwd).unwrap_or(&parsed_file.path).to_path_buf(),
+                Err(_)  => parsed_file.path.clone()
+            };
+            let mut directory: PathBuf = batch_folder.path().clone();
+            directory.extend(relative
+                .parent()
+                .map(|parent| parent.components().filter(|x| matches!(x, Component::Normal(_))).collect::<Vec<_>>())
+                .unwrap_or_default()
+            );
+            directory
+        };
+
+        match std::fs::create_dir_all(&directory) {
+            Ok(_) => (),
+            Err(err) => return error!{
+                debug: debuginfo!(),
+                description: format!("failed to create directory `{}` for tests", directory.to_string_lossy()),
+                example: None,
+                note: function_message!("std::fs::create_dir_all()", err.to_string())
+            }
+        }
+
+        let mut warned_about_main = false;
+        let mut warned_about_sanitizers = false;
+
+        let benchmarks = if config.runs_benchmarks() {
+            parsed_file.bench.as_slice()
+        } else {
+            &[]
+        };
+
+        let fuzz_inputs: Vec<(&ParsedTest, Vec<PathBuf>)> = parsed_file.fuzz
+            .iter()
+            .map(|target| (target, super::fuzz::crash_inputs(batch_folder.private_path(), &target.function.name)))
+            .filter(|(_, inputs)| !inputs.is_empty())
+            .collect();
+
+        for parsed_test in parsed_file.test
+            .iter()
+            .chain(benchmarks)
+            .chain(fuzz_inputs.iter().map(|(target, _)| *target))
+        {
+
+            let standalone = parsed_test.config.settings.compile_fail
+                || parsed_test.config.settings.standalone
+                    .or(parsed_file.config.settings.standalone)
+                    .or(recipe.and_then(|recipe| recipe.standalone))
+                    .unwrap_or(true);
+
+            let mut compiler = ResolvedCompiler::resolve(
+                config,
+                recipe,
+                &parsed_file.config,
+                &parsed_test.config,
+                standalone
+            );
+
+            let replayed_inputs = fuzz_inputs
+                .iter()
+                .find(|(target, _)| target.function.name == parsed_test.function.name)
+                .map(|(_, inputs)| inputs.clone())
+                .unwrap_or_default();
+
+            if !replayed_inputs.is_empty()
+            && super::coverage::Toolchain::from_compiler(&compiler.name) != super::coverage::Toolchain::Llvm
+            && super::cache::compiler_identity(DEFAULT_FUZZ_COMPILER).is_some()
+            {
+                compiler.name = DEFAULT_FUZZ_COMPILER.to_owned();
+            }
+
+            if !standalone && parsed_file.main.is_some() && !warned_about_main {
+                warned_about_main = true;
+                warnings.push(warning!{
+                    debug: debuginfo!(),
+                    description: format!("file `{}` contains a main() and has tests that aren't standalone",
+                        parsed_file.path.to_string_lossy()),
+                    example: None,
+                    note: vec![
+                        "the harness defines its own main(), linking against this files object will fail.".to_owned(),
+                        "move main() into a separate file or set `standalone = true` for these tests.".to_owned()
+                    ]
+                });
+            }
+
+            if !standalone && compiler.objects.is_empty() {
+                warnings.push(warning!{
+                    debug: debuginfo!(),
+                    description: format!("test `{}` isn't standalone but has no objects to link against",
+                        parsed_test.function.name),
+                    example: None,
+                    note: vec![
+                        "add the object files of your build to `objects` inside of the recipe or the tests `[compiler]`.".to_owned(),
+                        "alternatively pass them through `libraries`.".to_owned()
+                    ]
+                });
+            }
+
+            let (mut sanitizers, sanitizer_warning) = super::sanitize::resolve(
+                config,
+                recipe,
+                &parsed_file.config,
+                &parsed_test.config
+            );
+
+            if let Some(sanitizer_warning) = sanitizer_warning {
+                if !warned_about_sanitizers {
+                    warned_about_sanitizers = true;
+                    warnings.push(sanitizer_warning);
+                }
+            }
+
+            if !replayed_inputs.is_empty() && !sanitizers.contains(&Sanitizer::Address) {
+                if sanitizers.contains(&Sanitizer::Thread) {
+                    sanitizers.retain(|sanitizer| *sanitizer != Sanitizer::Thread);
+                    warnings.push(warning!{
+                        debug: debuginfo!(),
+                        description: format!("the crash inputs of fuzz target `{}` are replayed without the `thread` sanitizer",
+                            parsed_test.function.name),
+                        example: None,
+                        note: vec![
+                            "the inputs are replayed with the `address` sanitizer they were found with, which can't be combined with `thread`.".to_owned()
+                        ]
+                    });
+                }
+                sanitizers.push(Sanitizer::Address);
+            }
+
+            let mut valgrind = parsed_test.config.settings.valgrind
+                .or(parsed_file.config.settings.valgrind)
+                .unwrap_or(config.valgrind);
+
+            if valgrind && !sanitizers.is_empty() {
+                valgrind = false;
+                warnings.push(warning!{
+                    debug: debuginfo!(),
+                    description: format!("test `{}` can't run under valgrind & sanitizers at once", 
+                        parsed_test.function.name),
+                    example: None,
+                    note: vec![
+                        "the test will run only with sanitizers.".to_owned()
+                    ]
+                });
+            }
+
+            let expect_diagnostic = match parsed_test.config.settings.expect_diagnostic.as_ref().map(|pattern| Regex::new(pattern)) {
+                Some(Ok(regex)) => Some(regex),
+                Some(Err(err)) => {
+                    warnings.push(warning!{
+                        debug: debuginfo!(),
+                        description: format!("invalid `expect_diagnostic` of test `{}`", parsed_test.function.name),
+                        example: None,
+                        note: vec![
+                            err.to_string(),
+                            format!("test `{}` will not be ran.", parsed_test.function.name)
+                        ]
+                    });
+                    continue
+                },
+                None => None
+            };
+
+            let property = match parsed_test.config.property.as_ref() {
+                Some(property_config) => match Property::resolve(
+                    property_config,
+                    &parsed_test.function,
+                    config.seed,
+                    &super::run::identity(&parsed_file.path, &parsed_test.function.name)
+                ) {
+                    Ok(property) => Some(property),
+                    Err(err) => {
+                        warnings.push(warning!{
+                            debug: debuginfo!(),
+                            description: format!("failed to resolve the property of test `{}`", parsed_test.function.name),
+                            example: None,
+                            note: vec![
+                                err,
+                                format!("test `{}` will not be ran.", parsed_test.function.name)
+                            ]
+                        });
+                        continue
+                    }
+                },
+                None => None
+            };
+
+            let skip = if config.include_ignored || recipe.is_some_and(|recipe| recipe.force == Some(true)) {
+                None
+            } else if !parsed_test.config.settings.run {
+                Some("`run = false`".to_owned())
+            } else {
+                parsed_test.config.settings.skip
+                    .as_ref()
+                    .or(parsed_file.config.settings.skip.as_ref())
+                    .and_then(|marker| marker_reason(marker, &compiler, &parsed_file.environment.full))
+            };
+
+            let xfail = parsed_test.config.settings.xfail
+                .as_ref()
+                .or(parsed_file.config.settings.xfail.as_ref())
+                .and_then(|marker| marker_reason(marker, &compiler, &parsed_file.environment.full));
+
+            let coverage = if config.coverage {
+                Some(super::coverage::Toolchain::from_compiler(&compiler.name))
+            } else {
+                None
+            };
+
+            let mut path = directory.join(parsed_test.get_test_file_stem(parsed_file));
+            path.set_extension("c");
+
+            let harness = parsed_test.config.settings.harness
+                .or(parsed_file.config.settings.harness)
+                .or(config.harness)
+                .unwrap_or(HarnessMode::File);
+
+            // Tests that aren't compiled alongside the others or that
+            // have to be compiled on their own get their own harness.
+            let own_harness = harness == HarnessMode::Test
+                || skip.is_some()
+                || parsed_test.config.settings.compile_fail;
+
+            pending.push((parsed_test, standalone, own_harness, CompilableTest {
+                config: parsed_test.config.clone(),
+                artifacts: path.with_extension("out"),
+                path,
+                file: parsed_file.path.clone(),
+                name: parsed_test.function.name.clone(),
+                compiler,
+                include: batch_folder.include_path().clone(),
+                sanitizers,
+                valgrind,
+                suppressions: recipe
+                    .map(|recipe| recipe.suppressions.clone())
+                    .unwrap_or_default(),
+                coverage,
+                bench: if parsed_test.is_benchmark() {
+                    Some(super::bench::BenchSettings::resolve(&parsed_file.config, &parsed_test.config))
+                } else {
+                    None
+                },
+                fuzz_inputs: replayed_inputs,
+                property,
+                seed: config.seed,
+                retries: parsed_test.config.settings.retries
+                    .or(parsed_file.config.settings.retries)
+                    .or(config.retries)
+                    .unwrap_or(0),
+                skip,
+                xfail,
+                compile_fail: parsed_test.config.settings.compile_fail,
+                expect_diagnostic,
+                cache: if config.no_cache || config.coverage {
+                    None
+                } else {
+                    Some(super::cache::Cache::new(batch_folder.private_path(), config))
+                }
+            }));
+
+        }
+
+        // Indices into `pending` of the tests sharing a harness.
+        let mut groups: Vec<Vec<usize>> = vec![];
+
+        for (index, (_, standalone, own_harness, test)) in pending.iter().enumerate() {
+            let group = groups.iter_mut().find(|group| {
+                let (_, first_standalone, first_own_harness, first) = &pending[group[0]];
+                !own_harness && !first_own_harness && standalone == first_standalone && first.shares_binary(test)
+            });
+            match group {
+                Some(group) => group.push(index),
+                None => groups.push(vec![index])
+            }
+        }
+
+        let mut shared_harnesses: usize = 0;
+
+        for group in groups {
+
+            let (_, standalone, own_harness, first) = &pending[group[0]];
+
+            let path = if *own_harness {
+                first.path.clone()
+            } else {
+                let mut stem = parsed_file.stem.clone();
+                if shared_harnesses > 0 {
+                    stem.push(format!("-{}", shared_harnesses + 1));
+                }
+                shared_harnesses += 1;
+                directory.join(stem).with_extension("c")
+            };
+
+            let tests: Vec<(&ParsedTest, &CompilableTest)> = group
+                .iter()
+                .map(|index| (pending[*index].0, &pending[*index].3))
+                .collect();
+
+            let file = create_harness(&tests, parsed_file, &path, *standalone);
+
+            match std::fs::write(&path, file) {
+                Ok(_) => (),
+                Err(err) => return error!{
+                    debug: debuginfo!(),
+                    description: format!("failed to write test `{}`", path.to_string_lossy()),
+                    example: None,
+                    note: function_message!("std::fs::write()", err.to_string())
+                }
+            }
+
+            for index in group {
+                pending[index].3.path = path.clone();
+            }
+
+        }
+
+        let compilable_tests = pending
+            .into_iter()
+            .map(|(_, _, _, test)| test)
+            .collect();
+
+        Ok((compilable_tests, warnings))
+
+    }
+
+    /// Whether `other` can be compiled into the same binary as this
+    /// test, see [HarnessMode::File].
+    fn shares_binary(&self, other: &CompilableTest) -> bool {
+
+        self.compiler == other.compiler
+            && self.sanitizers == other.sanitizers
+            && self.valgrind == other.valgrind
+            && self.coverage == other.coverage
+
+    }
+
+    /// Flags the test is compiled with, besides the include path.
+    pub fn flags(&self) -> Vec<String> {
+
+        self.compiler.flags
+            .iter()
+            .cloned()
+            .chain(super::sanitize::flags(&self.sanitizers))
+            .chain(if self.valgrind { Some("-g".to_owned()) } else { None })
+            .chain(self.coverage.map(|toolchain| toolchain.flags()).unwrap_or_default())
+            .chain(super::diagnostic::FLAGS.map(String::from))
+            .collect()
+
+    }
+
+    /// Runs the compiler on the test, producing `binary`.
+    pub fn compile_output(&self, binary: &Path) -> Result<Output, Alert> {
+
+        match Command::new(&self.compiler.name)
+            .args(self.flags())
+            .arg(format!("-I{}", self.include.to_string_lossy()))
+            .arg(&self.path)
+            .arg("-o")
+            .arg(&binary)
+            .args(&self.compiler.objects)
+            .args(&self.compiler.libraries)
+            .output()
+        {
+            Ok(output) => Ok(output),
+            Err(err) => error!{
+                debug: debuginfo!(),
+                description: format!("failed to run compiler `{}` for test `{}`", self.compiler.name, self.name),
+                example: None,
+                note: function_message!("std::process::Command::output()", err.to_string())
+            }
         }
-        
-        compilable_tests
-        
+
     }
-    
-    
+
+    /// Compiles the test with its [ResolvedCompiler] into a binary
+    /// next to the test source, or copies it from the [CompilableTest::cache].
+    /// 
+    /// On failure every diagnostic of the compiler is returned as its
+    /// own alert (see [super::diagnostic]), followed by an error naming
+    /// the test & its harness.
+    pub fn compile(&self) -> Result<CompiledTest, Vec<Alert>> {
+
+        let binary = self.path.with_extension("out");
+
+        // Computed even without a cache, it fingerprints the results.
+        let key = super::cache::key(self);
+
+        if let (Some(cache), Some(key)) = (self.cache.as_ref(), key.as_ref()) {
+            if cache.fetch(key, &binary) {
+                return Ok(CompiledTest {
+                    test: self.clone(),
+                    binary,
+                    fingerprint: Some(key.clone())
+                })
+            }
+        }
+
+        let output = match self.compile_output(&binary) {
+            Ok(output) => output,
+            Err(err) => return Err(vec![err])
+        };
+
+        if !output.status.success() {
+
+            let stderr = String::from_utf8_lossy(&output.stderr);
+
+            let diagnostics = super::diagnostic::parse(&stderr);
+
+            let mut alerts: Vec<Alert> = diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.to_alert(&self.name))
+                .collect();
+
+            let mut note = vec![
+                format!("compiled `{}` from `{}`", self.path.to_string_lossy(), self.file.to_string_lossy())
+            ];
+
+            // Linker errors aren't diagnostics, only warnings may have been parsed.
+            if !diagnostics.iter().any(|diagnostic| diagnostic.severity == super::diagnostic::Severity::Error) {
+                note.push("the compiler returned the following:".to_owned());
+                note.extend(stderr.lines().map(String::from));
+            }
+
+            alerts.push(Alert::Error(AlertInfo {
+                description: format!("failed to compile test `{}`", self.name),
+                debug: debuginfo!(),
+                example: None,
+                note
+            }));
+
+            return Err(alerts)
+
+        }
+
+        if let (Some(cache), Some(key)) = (self.cache.as_ref(), key.as_ref()) {
+            // A binary that failed to be cached is compiled
+            // again next time, nothing else to be done.
+            _ = cache.store(key, &binary);
+        }
+
+        Ok(CompiledTest {
+            test: self.clone(),
+            binary,
+            fingerprint: key
+        })
+
+    }
+
 }
@@ -0,0 +1,823 @@
+//! Code coverage of a test batch.
+//!
+//! Tests compiled with gcc use `--coverage` & are read back with
+//! `gcov --json-format`, tests compiled with clang use
+//! `-fprofile-instr-generate -fcoverage-mapping` & are read back with
+//! `llvm-profdata` + `llvm-cov export -format=lcov`.
+//!
+//! Every harness contains its own copy of the file under test, so the
+//! coverage of all the harnesses is merged per source line. Only files
+//! inside of the project are kept, the bundled `cesty.h` & the
+//! harness `main()` are dropped.
+//!
+//! Reports
+//! -------
+//! After the run a summary is printed & `coverage/coverage.info` (lcov)
+//! and `coverage/index.html` are written into the test batch folder.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Command
+};
+
+use colored::Colorize;
+use serde::Deserialize;
+
+use crate::error::{
+    debuginfo, error, function_message,
+    warning, Alert, AlertInfo
+};
+
+use super::sanitize::in_project;
+
+/// Directory inside of the test batch folder the reports are written to.
+const COVERAGE_DIRECTORY: &'static str = "coverage";
+
+/// Compiler family, decides the flags & tools used.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Toolchain {
+    Gcc,
+    Llvm
+}
+
+impl Toolchain {
+
+    /// Anything that isn't clang is treated as gcc.
+    pub fn from_compiler(name: &str) -> Self {
+
+        let name = Path::new(name)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or(name.to_owned());
+
+        if name.contains("clang") {
+            Toolchain::Llvm
+        } else {
+            Toolchain::Gcc
+        }
+
+    }
+
+    /// Compile & link flags that instrument the test.
+    pub fn flags(&self) -> Vec<String> {
+
+        match self {
+            Toolchain::Gcc  => vec!["--coverage".to_owned()],
+            Toolchain::Llvm => vec![
+                "-fprofile-instr-generate".to_owned(),
+                "-fcoverage-mapping".to_owned()
+            ]
+        }
+
+    }
+
+    /// Environment the instrumented `binary` has to run with, `%m`
+    /// merges the profiles of the test & its fixtures into one file.
+    pub fn environment(&self, binary: &Path) -> Vec<(String, String)> {
+
+        match self {
+            Toolchain::Gcc  => vec![],
+            Toolchain::Llvm => vec![(
+                "LLVM_PROFILE_FILE".to_owned(),
+                format!("{}.%m.profraw", binary.to_string_lossy())
+            )]
+        }
+
+    }
+
+}
+
+/// Coverage of a single function.
+#[derive(Clone, Debug, Default)]
+pub struct FunctionCoverage {
+
+    pub start_line: usize,
+
+    /// [None] if the tool didn't report it, the function then
+    /// ends before the next function in the file.
+    pub end_line: Option<usize>,
+
+    /// Times the function was called.
+    pub count: u64
+
+}
+
+/// Coverage of a single source file.
+#[derive(Clone, Debug, Default)]
+pub struct FileCoverage {
+
+    /// Execution count per line.
+    pub lines: BTreeMap<usize, u64>,
+
+    pub functions: BTreeMap<String, FunctionCoverage>,
+
+    /// Times taken per (line, branch index on that line).
+    pub branches: BTreeMap<(usize, usize), u64>
+
+}
+
+/// Merged coverage of every test inside of a batch.
+#[derive(Clone, Debug, Default)]
+pub struct Coverage {
+
+    pub files: BTreeMap<String, FileCoverage>
+
+}
+
+/// Hit & total of lines/functions/branches.
+#[derive(Clone, Copy, Debug, Default)]
+struct Totals {
+    hit:   usize,
+    total: usize
+}
+
+impl Totals {
+
+    fn from_counts<'a>(counts: impl Iterator<Item = &'a u64>) -> Self {
+        counts.fold(Totals::default(), |totals, count| Totals {
+            hit:   totals.hit + if *count > 0 { 1 } else { 0 },
+            total: totals.total + 1
+        })
+    }
+
+    fn percent(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.hit as f64 * 100.0 / self.total as f64
+        }
+    }
+
+}
+
+impl std::fmt::Display for Totals {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        let percent = format!("{:>6.2}%", self.percent());
+        let percent = if self.percent() >= 80.0 {
+            percent.green()
+        } else if self.percent() >= 50.0 {
+            percent.yellow()
+        } else {
+            percent.red()
+        };
+
+        write!(f, "{percent} ({}/{})", self.hit, self.total)
+
+    }
+
+}
+
+#[derive(Deserialize)]
+struct GcovReport {
+    #[serde(default)]
+    current_working_directory: String,
+    #[serde(default)]
+    files: Vec<GcovFile>
+}
+
+#[derive(Deserialize)]
+struct GcovFile {
+    file: String,
+    #[serde(default)]
+    functions: Vec<GcovFunction>,
+    #[serde(default)]
+    lines: Vec<GcovLine>
+}
+
+#[derive(Deserialize)]
+struct GcovFunction {
+    name: String,
+    start_line: usize,
+    end_line: usize,
+    execution_count: u64
+}
+
+#[derive(Deserialize)]
+struct GcovLine {
+    line_number: usize,
+    count: u64,
+    #[serde(default)]
+    branches: Vec<GcovBranch>
+}
+
+#[derive(Deserialize)]
+struct GcovBranch {
+    count: u64
+}
+
+impl FileCoverage {
+
+    fn merge(&mut self, other: FileCoverage) {
+
+        for (line, count) in other.lines {
+            *self.lines.entry(line).or_default() += count;
+        }
+
+        for (name, function) in other.functions {
+            let entry = self.functions.entry(name).or_insert(FunctionCoverage {
+                count: 0,
+                ..function.clone()
+            });
+            entry.count += function.count;
+        }
+
+        for (branch, count) in other.branches {
+            *self.branches.entry(branch).or_default() += count;
+        }
+
+    }
+
+    /// Lines belonging to the function `name`.
+    fn function_lines(&self, name: &str) -> Totals {
+
+        let Some(function) = self.functions.get(name) else {
+            return Totals::default()
+        };
+
+        let end = function.end_line.unwrap_or_else(|| self.functions
+            .values()
+            .map(|other| other.start_line)
+            .filter(|start| *start > function.start_line)
+            .min()
+            .map(|start| start - 1)
+            .unwrap_or(usize::MAX)
+        );
+
+        Totals::from_counts(self.lines
+            .range(function.start_line..=end)
+            .map(|(_, count)| count)
+        )
+
+    }
+
+    /// Drops everything past `length` lines, the harness
+    /// `main()` is numbered after the end of the file.
+    fn truncate(&mut self, length: usize) {
+
+        self.lines.retain(|line, _| *line <= length);
+        self.branches.retain(|(line, _), _| *line <= length);
+        self.functions.retain(|_, function| function.start_line <= length);
+
+    }
+
+}
+
+impl Coverage {
+
+    fn merge(&mut self, file: String, coverage: FileCoverage) {
+
+        self.files.entry(file).or_default().merge(coverage);
+
+    }
+
+    /// Parses the output of `gcov --json-format --stdout`, one
+    /// JSON document per line.
+    fn merge_gcov(&mut self, output: &str) -> Result<(), String> {
+
+        for line in output.lines().filter(|line| !line.trim().is_empty()) {
+
+            let report: GcovReport = serde_json::from_str(line).map_err(|err| err.to_string())?;
+
+            for file in report.files {
+
+                let mut coverage = FileCoverage::default();
+
+                for function in file.functions {
+                    coverage.functions.insert(function.name, FunctionCoverage {
+                        start_line: function.start_line,
+                        end_line:   Some(function.end_line),
+                        count:      function.execution_count
+                    });
+                }
+
+                for line in file.lines {
+                    *coverage.lines.entry(line.line_number).or_default() += line.count;
+                    for (index, branch) in line.branches.iter().enumerate() {
+                        *coverage.branches.entry((line.line_number, index)).or_default() += branch.count;
+                    }
+                }
+
+                let path = Path::new(&report.current_working_directory).join(&file.file);
+                self.merge(path.to_string_lossy().to_string(), coverage);
+
+            }
+
+        }
+
+        Ok(())
+
+    }
+
+    /// Parses a lcov tracefile.
+    fn merge_lcov(&mut self, tracefile: &str) {
+
+        let mut file: Option<(String, FileCoverage)> = None;
+        let mut branch_index: BTreeMap<usize, usize> = BTreeMap::new();
+
+        for line in tracefile.lines() {
+
+            let (key, value) = line.split_once(':').unwrap_or((line, ""));
+            let fields: Vec<&str> = value.split(',').collect();
+
+            match (key, file.as_mut()) {
+                ("SF", _) => {
+                    file = Some((value.to_owned(), FileCoverage::default()));
+                    branch_index.clear();
+                }
+                ("FN", Some((_, coverage))) if fields.len() >= 2 => {
+                    let name = fields[fields.len() - 1].to_owned();
+                    let end_line = if fields.len() >= 3 { fields[1].parse().ok() } else { None };
+                    coverage.functions.insert(name, FunctionCoverage {
+                        start_line: fields[0].parse().unwrap_or(0),
+                        end_line,
+                        count: 0
+                    });
+                }
+                ("FNDA", Some((_, coverage))) if fields.len() == 2 => {
+                    if let Some(function) = coverage.functions.get_mut(fields[1]) {
+                        function.count += fields[0].parse().unwrap_or(0);
+                    }
+                }
+                ("DA", Some((_, coverage))) if fields.len() >= 2 => {
+                    if let (Ok(number), Ok(count)) = (fields[0].parse(), fields[1].parse::<u64>()) {
+                        *coverage.lines.entry(number).or_default() += count;
+                    }
+                }
+                ("BRDA", Some((_, coverage))) if fields.len() == 4 => {
+                    if let Ok(number) = fields[0].parse::<usize>() {
+                        let index = branch_index.entry(number).or_default();
+                        *coverage.branches.entry((number, *index)).or_default() += fields[3].parse().unwrap_or(0);
+                        *index += 1;
+                    }
+                }
+                ("end_of_record", _) => {
+                    if let Some((path, coverage)) = file.take() {
+                        self.merge(path, coverage);
+                    }
+                }
+                _ => ()
+            }
+
+        }
+
+    }
+
+    /// Keeps only files inside of the project & the lines that
+    /// exist inside of them.
+    fn retain_sources(&mut self) {
+
+        self.files.retain(|path, _| in_project(path));
+
+        for (path, coverage) in self.files.iter_mut() {
+            if let Ok(contents) = std::fs::read_to_string(path) {
+                coverage.truncate(contents.lines().count());
+            }
+        }
+
+        self.files.retain(|_, coverage| !coverage.lines.is_empty());
+
+    }
+
+}
+
+fn relative(path: &str) -> String {
+
+    match std::env::current_dir() {
+        Ok(pwd) => Path::new(path).strip_prefix(pwd).map(|path| path.to_string_lossy().to_string()).unwrap_or(path.to_owned()),
+        Err(_)  => path.to_owned()
+    }
+
+}
+
+fn files_with_extension(directory: &Path, extension: &str, found: &mut Vec<PathBuf>) {
+
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files_with_extension(&path, extension, found);
+        } else if path.extension().is_some_and(|x| x == extension) {
+            found.push(path);
+        }
+    }
+
+}
+
+fn gcov(gcda: &Path, coverage: &mut Coverage) -> Result<(), Alert> {
+
+    let output = match Command::new("gcov")
+        .arg("--json-format")
+        .arg("--stdout")
+        .arg("--branch-probabilities")
+        .arg(gcda)
+        .current_dir(gcda.parent().unwrap_or(Path::new(".")))
+        .output()
+    {
+        Ok(output) => output,
+        Err(err) => return error!{
+            debug: debuginfo!(),
+            description: "failed to run `gcov`".to_owned(),
+            example: None,
+            note: function_message!("std::process::Command::output()", err.to_string())
+        }
+    };
+
+    match coverage.merge_gcov(&String::from_utf8_lossy(&output.stdout)) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(warning!{
+            debug: debuginfo!(),
+            description: format!("failed to read the coverage of `{}`", gcda.to_string_lossy()),
+            example: None,
+            note: vec![
+                "`gcov --json-format` requires gcc 9 or newer.".to_owned(),
+                err
+            ]
+        })
+    }
+
+}
+
+fn llvm(profraw: &Path, coverage: &mut Coverage) -> Result<(), Alert> {
+
+    // `<binary>.<signature>.profraw`, see [Toolchain::environment].
+    let name = profraw.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let binary = profraw.with_file_name(name.split(".out.").next().unwrap_or(&name).to_owned() + ".out");
+    let profdata = profraw.with_extension("profdata");
+
+    let merged = Command::new("llvm-profdata")
+        .arg("merge")
+        .arg("-sparse")
+        .arg(profraw)
+        .arg("-o")
+        .arg(&profdata)
+        .output();
+
+    let output = match merged {
+        Ok(merged) if merged.status.success() => Command::new("llvm-cov")
+            .arg("export")
+            .arg("-format=lcov")
+            .arg(format!("-instr-profile={}", profdata.to_string_lossy()))
+            .arg(&binary)
+            .output(),
+        Ok(merged) => return Err(warning!{
+            debug: debuginfo!(),
+            description: format!("`llvm-profdata` failed to merge `{}`", profraw.to_string_lossy()),
+            example: None,
+            note: String::from_utf8_lossy(&merged.stderr).lines().map(String::from).collect()
+        }),
+        Err(err) => Err(err)
+    };
+
+    match output {
+        Ok(output) => {
+            coverage.merge_lcov(&String::from_utf8_lossy(&output.stdout));
+            Ok(())
+        }
+        Err(err) => error!{
+            debug: debuginfo!(),
+            description: "failed to run the llvm coverage tools".to_owned(),
+            example: None,
+            note: function_message!("std::process::Command::output()", err.to_string())
+        }
+    }
+
+}
+
+impl Coverage {
+
+    /// Collects & merges the coverage of every instrumented test
+    /// inside of the test batch folder at `batch`.
+    pub fn collect(batch: &Path) -> Result<(Coverage, Vec<Alert>), Alert> {
+
+        let mut coverage = Coverage::default();
+        let mut warnings: Vec<Alert> = vec![];
+
+        let mut gcda: Vec<PathBuf> = vec![];
+        let mut profraw: Vec<PathBuf> = vec![];
+        files_with_extension(batch, "gcda", &mut gcda);
+        files_with_extension(batch, "profraw", &mut profraw);
+
+        for data in gcda.iter() {
+            match gcov(data, &mut coverage) {
+                Ok(_) => (),
+                Err(err @ Alert::Warning(_)) => warnings.push(err),
+                Err(err) => return Err(err)
+            }
+        }
+
+        for data in profraw.iter() {
+            match llvm(data, &mut coverage) {
+                Ok(_) => (),
+                Err(err @ Alert::Warning(_)) => warnings.push(err),
+                Err(err) => return Err(err)
+            }
+        }
+
+        coverage.retain_sources();
+
+        Ok((coverage, warnings))
+
+    }
+
+    /// The coverage in the lcov tracefile format.
+    pub fn to_lcov(&self) -> String {
+
+        let mut out = String::new();
+
+        for (path, file) in self.files.iter() {
+
+            out += &format!("TN:\nSF:{path}\n");
+
+            for (name, function) in file.functions.iter() {
+                out += &format!("FN:{},{name}\n", function.start_line);
+            }
+            for (name, function) in file.functions.iter() {
+                out += &format!("FNDA:{},{name}\n", function.count);
+            }
+            let functions = Totals::from_counts(file.functions.values().map(|function| &function.count));
+            out += &format!("FNF:{}\nFNH:{}\n", functions.total, functions.hit);
+
+            for ((line, index), count) in file.branches.iter() {
+                out += &format!("BRDA:{line},0,{index},{count}\n");
+            }
+            let branches = Totals::from_counts(file.branches.values());
+            out += &format!("BRF:{}\nBRH:{}\n", branches.total, branches.hit);
+
+            for (line, count) in file.lines.iter() {
+                out += &format!("DA:{line},{count}\n");
+            }
+            let lines = Totals::from_counts(file.lines.values());
+            out += &format!("LF:{}\nLH:{}\nend_of_record\n", lines.total, lines.hit);
+
+        }
+
+        out
+
+    }
+
+    /// A self contained HTML report, a summary table followed
+    /// by every file with its lines marked as hit or missed.
+    pub fn to_html(&self) -> String {
+
+        fn escape(text: &str) -> String {
+            text.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        }
+
+        let cell = |totals: Totals| format!("<td>{:.2}% ({}/{})</td>", totals.percent(), totals.hit, totals.total);
+
+        let mut summary = String::new();
+        let mut sources = String::new();
+
+        for (index, (path, file)) in self.files.iter().enumerate() {
+
+            summary += &format!("<tr><td><a href=\"#file{index}\">{}</a></td>{}{}{}</tr>\n",
+                escape(&relative(path)),
+                cell(Totals::from_counts(file.lines.values())),
+                cell(Totals::from_counts(file.functions.values().map(|function| &function.count))),
+                cell(Totals::from_counts(file.branches.values()))
+            );
+
+            sources += &format!("<h2 id=\"file{index}\">{}</h2>\n<table class=\"source\">\n", escape(&relative(path)));
+
+            let contents = std::fs::read_to_string(path).unwrap_or_default();
+
+            for (number, code) in contents.lines().enumerate().map(|(index, code)| (index + 1, code)) {
+                let (class, count) = match file.lines.get(&number) {
+                    Some(0)     => ("miss", "0".to_owned()),
+                    Some(count) => ("hit", count.to_string()),
+                    None        => ("none", String::new())
+                };
+                sources += &format!("<tr class=\"{class}\"><td class=\"number\">{number}</td><td class=\"count\">{count}</td><td><pre>{}</pre></td></tr>\n",
+                    escape(code));
+            }
+
+            sources += "</table>\n";
+
+        }
+
+        format!(r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>cesty coverage</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+table {{ border-collapse: collapse; }}
+td, th {{ padding: 0 0.75em; text-align: left; }}
+.source td {{ padding: 0 0.5em; }}
+.source pre {{ margin: 0; }}
+.number, .count {{ color: #777; text-align: right; }}
+.hit {{ background: #dfd; }}
+.miss {{ background: #fdd; }}
+</style>
+</head>
+<body>
+<h1>Coverage</h1>
+<table>
+<tr><th>File</th><th>Lines</th><th>Functions</th><th>Branches</th></tr>
+{summary}</table>
+{sources}</body>
+</html>
+"#)
+
+    }
+
+    /// Writes `coverage.info` & `index.html` into the
+    /// coverage directory of the batch at `batch`, returns
+    /// the paths of both.
+    pub fn write_reports(&self, batch: &Path) -> Result<(PathBuf, PathBuf), Alert> {
+
+        let directory = batch.join(COVERAGE_DIRECTORY);
+        let (info, html) = (directory.join("coverage.info"), directory.join("index.html"));
+
+        for (path, contents) in [(&directory, None), (&info, Some(self.to_lcov())), (&html, Some(self.to_html()))] {
+
+            let result = match contents {
+                None           => std::fs::create_dir_all(path),
+                Some(contents) => std::fs::write(path, contents)
+            };
+
+            if let Err(err) = result {
+                return error!{
+                    debug: debuginfo!(),
+                    description: format!("failed to write the coverage report `{}`", path.to_string_lossy()),
+                    example: None,
+                    note: function_message!("std::fs::write()", err.to_string())
+                }
+            }
+
+        }
+
+        Ok((info, html))
+
+    }
+
+}
+
+impl std::fmt::Display for Coverage {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        write!(f, "\n{}", "coverage:".bold())?;
+
+        if self.files.is_empty() {
+            return write!(f, " no coverage data was collected")
+        }
+
+        for (path, file) in self.files.iter() {
+
+            write!(f, "\n  {}\n    lines {}  functions {}  branches {}",
+                relative(path).bold(),
+                Totals::from_counts(file.lines.values()),
+                Totals::from_counts(file.functions.values().map(|function| &function.count)),
+                Totals::from_counts(file.branches.values())
+            )?;
+
+            let mut functions: Vec<(&String, &FunctionCoverage)> = file.functions.iter().collect();
+            functions.sort_by_key(|(_, function)| function.start_line);
+
+            for (name, function) in functions {
+                write!(f, "\n    {} lines {}  called {} times",
+                    format!("{name:<32}").dimmed(),
+                    file.function_lines(name),
+                    function.count
+                )?;
+            }
+
+        }
+
+        Ok(())
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Coverage;
+
+    /// `gcov --json-format --stdout --branch-probabilities` of a harness, gcc 12.
+    const GCOV: &'static str = concat!(
+        r#"{"gcc_version": "12.2.0", "files": [{"lines": [{"branches": [], "count": 1, "line_number": 1, "unexecuted_block": false, "function_name": "sum"}, "#,
+        r#"{"branches": [{"fallthrough": true, "count": 1, "throw": false}, {"fallthrough": false, "count": 0, "throw": false}], "count": 1, "line_number": 2, "unexecuted_block": false, "function_name": "sum"}, "#,
+        r#"{"branches": [], "count": 1, "line_number": 3, "unexecuted_block": false, "function_name": "sum"}, "#,
+        r#"{"branches": [], "count": 0, "line_number": 4, "unexecuted_block": true, "function_name": "sum"}, "#,
+        r#"{"branches": [], "count": 1, "line_number": 7, "unexecuted_block": false, "function_name": "main"}, "#,
+        r#"{"branches": [], "count": 1, "line_number": 8, "unexecuted_block": false, "function_name": "main"}], "#,
+        r#""functions": [{"blocks": 4, "end_column": 1, "start_line": 1, "name": "sum", "blocks_executed": 3, "execution_count": 1, "demangled_name": "sum", "start_column": 5, "end_line": 5}, "#,
+        r#"{"blocks": 3, "end_column": 1, "start_line": 7, "name": "main", "blocks_executed": 3, "execution_count": 1, "demangled_name": "main", "start_column": 5, "end_line": 9}], "#,
+        r#""file": "sum.c"}], "format_version": "1", "current_working_directory": "/project/tests", "data_file": "sum.gcda"}"#,
+        "\n"
+    );
+
+    /// `llvm-cov export -format=lcov` of a harness.
+    const LCOV: &'static str = "\
+SF:/project/src/sum.c
+FN:1,sum
+FN:7,9,main
+FNDA:2,sum
+FNDA:1,main
+FNF:2
+FNH:2
+BRDA:2,0,0,1
+BRDA:2,0,1,1
+BRF:2
+BRH:2
+DA:1,2
+DA:2,2
+DA:3,1
+DA:4,1
+DA:7,1
+DA:8,1
+LF:6
+LH:6
+end_of_record
+";
+
+    #[test]
+    fn merges_gcov_json_of_every_harness() {
+
+        let mut coverage = Coverage::default();
+        coverage.merge_gcov(&GCOV.repeat(2)).unwrap();
+
+        let file = &coverage.files["/project/tests/sum.c"];
+
+        assert_eq!(file.lines.iter().map(|(line, count)| (*line, *count)).collect::<Vec<_>>(), [
+            (1, 2), (2, 2), (3, 2), (4, 0), (7, 2), (8, 2)
+        ]);
+        assert_eq!(file.functions["sum"].count, 2);
+        assert_eq!((file.functions["sum"].start_line, file.functions["sum"].end_line), (1, Some(5)));
+        assert_eq!(file.branches[&(2, 0)], 2);
+        assert_eq!(file.branches[&(2, 1)], 0);
+
+        assert!(coverage.merge_gcov("{\"files\": [").is_err());
+
+    }
+
+    #[test]
+    fn merges_lcov_tracefiles() {
+
+        let mut coverage = Coverage::default();
+        coverage.merge_lcov(LCOV);
+        coverage.merge_lcov(LCOV);
+
+        let file = &coverage.files["/project/src/sum.c"];
+
+        assert_eq!(file.lines[&1], 4);
+        assert_eq!(file.lines[&4], 2);
+        assert_eq!(file.functions["sum"].count, 4);
+        assert_eq!(file.functions["sum"].end_line, None);
+        assert_eq!(file.functions["main"].end_line, Some(9));
+        assert_eq!(file.branches[&(2, 0)], 2);
+        assert_eq!(file.branches[&(2, 1)], 2);
+
+    }
+
+    #[test]
+    fn writes_lcov_that_reads_back() {
+
+        let mut coverage = Coverage::default();
+        coverage.merge_gcov(GCOV).unwrap();
+
+        let lcov = coverage.to_lcov();
+
+        assert!(lcov.starts_with("TN:\nSF:/project/tests/sum.c\n"));
+        for record in ["FN:1,sum\n", "FNDA:1,main\n", "FNF:2\nFNH:2\n", "BRDA:2,0,1,0\n", "BRF:2\nBRH:1\n", "DA:4,0\n", "LF:6\nLH:5\nend_of_record\n"] {
+            assert!(lcov.contains(record), "missing {record:?} in:\n{lcov}");
+        }
+
+        let mut read = Coverage::default();
+        read.merge_lcov(&lcov);
+
+        let (written, read) = (&coverage.files["/project/tests/sum.c"], &read.files["/project/tests/sum.c"]);
+        assert_eq!(written.lines, read.lines);
+        assert_eq!(written.branches, read.branches);
+
+    }
+
+    #[test]
+    fn truncate_drops_the_harness_main() {
+
+        let mut coverage = Coverage::default();
+        coverage.merge_gcov(GCOV).unwrap();
+
+        let file = coverage.files.get_mut("/project/tests/sum.c").unwrap();
+        file.truncate(5);
+
+        assert_eq!(file.lines.keys().copied().collect::<Vec<_>>(), [1, 2, 3, 4]);
+        assert_eq!(file.functions.keys().collect::<Vec<_>>(), ["sum"]);
+        assert_eq!(file.branches.len(), 2);
+
+    }
+
+}
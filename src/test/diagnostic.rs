@@ -0,0 +1,386 @@
+//! Parsing gcc & clang diagnostics into [Alert]s pointing at the
+//! code the user wrote, harnesses are generated with `#line`
+//! directives such that the diagnostics already refer to it.
+//!
+//! Format
+//! ------
+//! Both compilers report diagnostics as:
+//! ```text
+//! <file>:<line>:<column>: <error|warning|note|fatal error>: <message>
+//! ```
+//! Notes belong to the diagnostic before them, notes before the first
+//! diagnostic have nothing to belong to & are dropped. Fix-it hints are read
+//! from `-fdiagnostics-parseable-fixits`, supported by both compilers:
+//! ```text
+//! fix-it:"<file>":{<line>:<column>-<line>:<column>}:"<replacement>"
+//! ```
+//! & belong to the diagnostic or note right before them.
+//! Every other line (source excerpts, carets, `In function ...`) is skipped.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::error::{
+    debuginfo, Alert, AlertCode,
+    AlertCodeFix, AlertExample, AlertInfo
+};
+
+lazy_static! {
+
+    static ref DIAGNOSTIC: Regex = Regex::new(
+        r"^(.+?):(\d+):(?:(\d+):)? (fatal error|error|warning|note): (.*)$"
+    ).unwrap();
+
+    static ref FIXIT: Regex = Regex::new(
+        r#"^fix-it:"(.*)":\{(\d+):(\d+)-(\d+):(\d+)\}:"(.*)"$"#
+    ).unwrap();
+
+}
+
+/// Flags that make the compiler output parseable fix-it hints.
+pub const FLAGS: [&str; 1] = ["-fdiagnostics-parseable-fixits"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Note,
+    Warning,
+    Error
+}
+
+/// Suggested replacement of a range of a file.
+#[derive(Clone, Debug)]
+pub struct FixIt {
+
+    pub file: String,
+
+    pub line: usize,
+
+    pub column: usize,
+
+    /// End of the replaced range, exclusive.
+    pub end: (usize, usize),
+
+    pub replacement: String
+
+}
+
+/// A single diagnostic along with its notes & fix-it hints.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+
+    pub file: String,
+
+    pub line: usize,
+
+    /// `0` if the compiler didn't report a column.
+    pub column: usize,
+
+    pub severity: Severity,
+
+    pub message: String,
+
+    pub notes: Vec<Diagnostic>,
+
+    pub fixits: Vec<FixIt>
+
+}
+
+/// Unescapes the C string escapes of a fix-it.
+fn unescape(text: &str) -> String {
+
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        unescaped.push(match (c, c == '\\') {
+            (_, true) => match chars.next() {
+                Some('n')   => '\n',
+                Some('t')   => '\t',
+                Some(other) => other,
+                None        => '\\'
+            },
+            (c, false) => c
+        });
+    }
+
+    unescaped
+
+}
+
+/// Diagnostics of the compiler output `stderr`, in order.
+pub fn parse(stderr: &str) -> Vec<Diagnostic> {
+
+    let mut diagnostics: Vec<Diagnostic> = vec![];
+
+    for line in stderr.lines() {
+
+        if let Some(captures) = FIXIT.captures(line) {
+            let number = |index: usize| captures[index].parse::<usize>().unwrap_or_default();
+            let fixit = FixIt {
+                file:        unescape(&captures[1]),
+                line:        number(2),
+                column:      number(3),
+                end:         (number(4), number(5)),
+                replacement: unescape(&captures[6])
+            };
+            if let Some(last) = diagnostics.last_mut() {
+                match last.notes.last_mut() {
+                    Some(note) => note.fixits.push(fixit),
+                    None       => last.fixits.push(fixit)
+                }
+            }
+            continue
+        }
+
+        let Some(captures) = DIAGNOSTIC.captures(line) else {
+            continue
+        };
+
+        let diagnostic = Diagnostic {
+            file:     captures[1].to_owned(),
+            line:     captures[2].parse().unwrap_or_default(),
+            column:   captures.get(3).and_then(|column| column.as_str().parse().ok()).unwrap_or_default(),
+            severity: match &captures[4] {
+                "note"    => Severity::Note,
+                "warning" => Severity::Warning,
+                _         => Severity::Error
+            },
+            message:  captures[5].to_owned(),
+            notes:    vec![],
+            fixits:   vec![]
+        };
+
+        match (diagnostics.last_mut(), diagnostic.severity) {
+            (Some(last), Severity::Note) => last.notes.push(diagnostic),
+            (None, Severity::Note) => (),
+            _ => diagnostics.push(diagnostic)
+        }
+
+    }
+
+    diagnostics
+
+}
+
+impl FixIt {
+
+    fn describe(&self) -> String {
+
+        if self.replacement.is_empty() {
+            "fix-it: remove this".to_owned()
+        } else if (self.line, self.column) == self.end {
+            format!("fix-it: insert `{}`", self.replacement)
+        } else {
+            format!("fix-it: replace with `{}`", self.replacement)
+        }
+
+    }
+
+}
+
+impl Diagnostic {
+
+    /// The diagnostic as an [Alert] for `test`, with an excerpt of the
+    /// file it points at marking the diagnostic, the notes & the fix-its
+    /// inside of the same file. Everything else is added as a note.
+    pub fn to_alert(&self, test: &str) -> Alert {
+
+        let description = match self.severity {
+            Severity::Error => format!("test `{test}` failed to compile: {}", self.message),
+            _               => format!("compiling test `{test}` emitted a warning: {}", self.message)
+        };
+
+        let mut fix: Vec<AlertCodeFix> = vec![];
+        let mut note: Vec<String> = vec![];
+
+        let contents = if self.line > 0 {
+            std::fs::read_to_string(&self.file).ok()
+        } else {
+            None
+        };
+
+        let mut mark = |file: &str, line: usize, column: usize, comment: String| {
+            if contents.is_some() && file == self.file && line > 0 {
+                fix.push(AlertCodeFix {
+                    relative_line: line - 1,
+                    column,
+                    comment
+                });
+            } else {
+                note.push(format!("`{file}`:{line}:{column} {comment}"));
+            }
+        };
+
+        mark(&self.file, self.line, self.column, self.message.clone());
+
+        for fixit in self.fixits.iter() {
+            mark(&fixit.file, fixit.line, fixit.column, fixit.describe());
+        }
+
+        for diagnostic_note in self.notes.iter() {
+            mark(&diagnostic_note.file, diagnostic_note.line, diagnostic_note.column,
+                format!("note: {}", diagnostic_note.message));
+            for fixit in diagnostic_note.fixits.iter() {
+                mark(&fixit.file, fixit.line, fixit.column, fixit.describe());
+            }
+        }
+
+        let info = AlertInfo {
+            description,
+            debug: debuginfo!(),
+            example: contents.map(|code| AlertExample::Code(AlertCode {
+                line: self.line,
+                file: self.file.clone(),
+                code,
+                fix
+            })),
+            note
+        };
+
+        match self.severity {
+            Severity::Error => Alert::Error(info),
+            _               => Alert::Warning(info)
+        }
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{parse, Severity};
+
+    #[test]
+    fn parses_gcc_output() {
+
+        // gcc 12 with -fdiagnostics-parseable-fixits -Wall.
+        let diagnostics = parse(concat!(
+            "ds.c:3:5: error: conflicting types for 'f'; have 'int(int)'\n",
+            "    3 | int f(int x) { return x; }\n",
+            "      |     ^\n",
+            "ds.c:2:5: note: previous declaration of 'f' with type 'int(void)'\n",
+            "    2 | int f(void);\n",
+            "      |     ^\n",
+            "ds.c: In function 'cesty_a':\n",
+            "ds.c:7:12: error: 'coutn' undeclared (first use in this function); did you mean 'count'?\n",
+            "    7 |     return coutn;\n",
+            "      |            ^~~~~\n",
+            "      |            count\n",
+            "fix-it:\"ds.c\":{7:12-7:17}:\"count\"\n",
+            "ds.c:7:12: note: each undeclared identifier is reported only once for each function it appears in\n",
+            "ds.c:5:9: warning: unused variable 'unused' [-Wunused-variable]\n",
+            "    5 |     int unused;\n",
+            "      |         ^~~~~~\n"
+        ));
+
+        assert_eq!(diagnostics.len(), 3);
+
+        let conflict = &diagnostics[0];
+        assert_eq!((conflict.file.as_str(), conflict.line, conflict.column), ("ds.c", 3, 5));
+        assert_eq!(conflict.severity, Severity::Error);
+        assert_eq!(conflict.message, "conflicting types for 'f'; have 'int(int)'");
+        assert_eq!(conflict.notes.len(), 1);
+        assert_eq!(conflict.notes[0].severity, Severity::Note);
+        assert_eq!(conflict.notes[0].line, 2);
+        assert!(conflict.fixits.is_empty());
+
+        let undeclared = &diagnostics[1];
+        assert_eq!(undeclared.line, 7);
+        assert_eq!(undeclared.fixits.len(), 1);
+        assert_eq!(undeclared.fixits[0].file, "ds.c");
+        assert_eq!((undeclared.fixits[0].line, undeclared.fixits[0].column), (7, 12));
+        assert_eq!(undeclared.fixits[0].end, (7, 17));
+        assert_eq!(undeclared.fixits[0].replacement, "count");
+        assert_eq!(undeclared.fixits[0].describe(), "fix-it: replace with `count`");
+        assert_eq!(undeclared.notes.len(), 1);
+        assert!(undeclared.notes[0].fixits.is_empty());
+
+        assert_eq!(diagnostics[2].severity, Severity::Warning);
+        assert_eq!(diagnostics[2].message, "unused variable 'unused' [-Wunused-variable]");
+
+    }
+
+    #[test]
+    fn parses_clang_output() {
+
+        // clang 16 with -fdiagnostics-parseable-fixits.
+        let diagnostics = parse(concat!(
+            "ds.c:4:13: error: expected ';' after return statement\n",
+            "    4 |     return 0\n",
+            "      |             ^\n",
+            "      |             ;\n",
+            "fix-it:\"ds.c\":{4:13-4:13}:\";\"\n",
+            "ds.c:9:5: warning: call to undeclared library function 'puts' with type 'int (const char *)'; ISO C99 and later do not support implicit function declarations [-Wimplicit-function-declaration]\n",
+            "    9 |     puts(\"a\\tb\");\n",
+            "      |     ^\n",
+            "ds.c:9:5: note: include the header <stdio.h> or explicitly provide a declaration for 'puts'\n",
+            "fix-it:\"ds.c\":{1:1-1:1}:\"#include <stdio.h>\\n\"\n",
+            "1 warning and 1 error generated.\n"
+        ));
+
+        assert_eq!(diagnostics.len(), 2);
+
+        assert_eq!(diagnostics[0].fixits.len(), 1);
+        assert_eq!(diagnostics[0].fixits[0].describe(), "fix-it: insert `;`");
+
+        // The fix-it after a note belongs to the note.
+        let implicit = &diagnostics[1];
+        assert_eq!(implicit.severity, Severity::Warning);
+        assert!(implicit.fixits.is_empty());
+        assert_eq!(implicit.notes.len(), 1);
+        assert_eq!(implicit.notes[0].fixits.len(), 1);
+        assert_eq!(implicit.notes[0].fixits[0].replacement, "#include <stdio.h>\n");
+        assert_eq!((implicit.notes[0].fixits[0].line, implicit.notes[0].fixits[0].column), (1, 1));
+
+    }
+
+    #[test]
+    fn parses_fatal_errors_and_missing_columns() {
+
+        let diagnostics = parse(concat!(
+            "In file included from ds.c:1:\n",
+            "./ds.h:3:10: fatal error: 'missing.h' file not found\n",
+            "compilation terminated.\n",
+            "ds.c:12: warning: ignoring '#pragma omp parallel' [-Wunknown-pragmas]\n",
+            "/usr/bin/ld: ds.o: in function `cesty_a':\n",
+            "ds.c:(.text+0x1b): undefined reference to `ds_new'\n",
+            "collect2: error: ld returned 1 exit status\n"
+        ));
+
+        assert_eq!(diagnostics.len(), 2);
+
+        assert_eq!(diagnostics[0].file, "./ds.h");
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].message, "'missing.h' file not found");
+
+        assert_eq!((diagnostics[1].line, diagnostics[1].column), (12, 0));
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+
+    }
+
+    #[test]
+    fn fixits_describe_removals() {
+
+        let diagnostics = parse(concat!(
+            "ds.c:2:17: warning: extra ';' outside of a function [-Wextra-semi]\n",
+            "fix-it:\"ds.c\":{2:17-2:18}:\"\"\n"
+        ));
+
+        assert_eq!(diagnostics[0].fixits[0].describe(), "fix-it: remove this");
+
+    }
+
+    #[test]
+    fn skips_fixits_and_notes_without_a_diagnostic() {
+
+        let diagnostics = parse(concat!(
+            "fix-it:\"ds.c\":{1:1-1:1}:\"x\"\n",
+            "ds.c:1:1: note: stray note\n"
+        ));
+
+        assert!(diagnostics.is_empty());
+
+    }
+
+}
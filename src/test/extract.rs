@@ -12,13 +12,27 @@
 
 use std::{ffi::OsString, path::PathBuf};
 
+use regex::Regex;
+
 use crate::{
+    arg_conf::{
+        Recipe, Run
+    },
+    test::{property::Property, Config},
+    defaults::{
+        BENCH_NAME_PREFIX,
+        DEFAULT_FUNCTION_PREFIX,
+        FUZZ_NAME_PREFIX,
+        FILE_CONFIG_MARKER
+    },
     error::{
         debuginfo, debugpush, error, 
-        Alert, AlertInfo
+        warning, Alert, AlertCode, 
+        AlertCodeFix, AlertExample, AlertInfo
     }, 
     rustclang::{
-        Clang, Open
+        Clang, DiagnosticSeverity, 
+        Open, ParseMode
     }
 };
 
@@ -27,8 +41,11 @@ use crate::{
 #[derive(Clone, Debug, Default)]
 pub struct Function {
 
-    /// Return type in string. Might be removed due to
-    /// redundancy.
+    /// Canonical return type in string, typedefs are resolved
+    /// (`bool` is spelled `_Bool`, `my_int_t` as `int`...).
+    /// 
+    /// Without a full parse types from headers can't be resolved,
+    /// see [resolve_declared_types].
     pub returns: String,
 
     /// Full function name (with *cesty_*)
@@ -44,6 +61,48 @@ pub struct Function {
 
 }
 
+/// How the result of a test is decided from its return type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReturnConvention {
+
+    /// Passes when it returns `true`.
+    Bool,
+
+    /// Passes when it returns [super::Settings::pass_value],
+    /// `0` by default. Used for every integer type & enums.
+    Int,
+
+    /// Passes unless an assertion fails or the process dies.
+    Void
+
+}
+
+impl Function {
+
+    /// Return convention of the function, [None] if the return
+    /// type is unsupported (structs, floats, pointers...).
+    pub fn return_convention(&self) -> Option<ReturnConvention> {
+
+        let returns = self.returns
+            .replace("const ", "")
+            .replace("volatile ", "");
+
+        match returns.trim() {
+            "void" => Some(ReturnConvention::Void),
+            "_Bool" | "bool" => Some(ReturnConvention::Bool),
+            "char" | "signed char" | "unsigned char" 
+            | "short" | "unsigned short" 
+            | "int" | "unsigned int" 
+            | "long" | "unsigned long" 
+            | "long long" | "unsigned long long" => Some(ReturnConvention::Int),
+            other if other.starts_with("enum ") => Some(ReturnConvention::Int),
+            _ => None
+        }
+
+    }
+
+}
+
 /// Ranges for certain parts of a function.
 #[derive(Clone, Debug, Default)]
 pub struct Range {
@@ -78,14 +137,41 @@ pub struct Environment {
     /// Full file.
     pub full:      String,
 
-    /// Full file without main().
+    /// Full file with main() blanked out, offsets & lines
+    /// match [Environment::full].
     pub mainless:  String,
 
-    /// File without all the function bodies & main().
+    /// File without all the function bodies & main(), blanked
+    /// out such that lines match [Environment::full].
     pub templated: String
 
 }
 
+/// Fixtures found inside of a file, these are excluded from
+/// the tests & invoked by the harness around them.
+/// 
+/// A fixture is a prefixed function whose name (without the prefix)
+/// is one of [crate::defaults::FIXTURE_SETUP], [crate::defaults::FIXTURE_TEARDOWN],
+/// [crate::defaults::FIXTURE_BEFORE_EACH] or [crate::defaults::FIXTURE_AFTER_EACH].
+#[derive(Clone, Debug, Default)]
+pub struct Fixtures {
+
+    /// Ran once, in its own process, before all the tests of the file.
+    pub setup: Option<ParsedTest>,
+
+    /// Ran once, in its own process, after all the tests of the file.
+    pub teardown: Option<ParsedTest>,
+
+    /// Ran before every test inside of the test process, can
+    /// return or fill in a context pointer passed to the test.
+    pub before_each: Option<ParsedTest>,
+
+    /// Ran after every test inside of the test process, can
+    /// accept the context pointer.
+    pub after_each: Option<ParsedTest>
+
+}
+
 /// Parsed file test data.
 #[derive(Clone, Debug, Default)]
 pub struct ParsedFile {
@@ -99,9 +185,24 @@ pub struct ParsedFile {
     /// List of all tests found inside of the file.
     pub test: Vec<ParsedTest>,
 
+    /// Benchmarks found inside of the file, tests whose name
+    /// (without the prefix) starts with [BENCH_NAME_PREFIX].
+    pub bench: Vec<ParsedTest>,
+
+    /// Fuzz targets found inside of the file, tests whose name
+    /// (without the prefix) starts with [FUZZ_NAME_PREFIX].
+    pub fuzz: Vec<ParsedTest>,
+
+    /// Setup, teardown & per test fixtures.
+    pub fixtures: Fixtures,
+
     /// The files full & clean environment.
     pub environment: Environment,
 
+    /// File wide config found after a [FILE_CONFIG_MARKER], 
+    /// default if the file has none.
+    pub config: super::Config,
+
     /// Main function.
     pub main: Option<ParsedTest>
 
@@ -109,6 +210,22 @@ pub struct ParsedFile {
 
 impl ParsedTest {
 
+    /// Is the test a benchmark, see [BENCH_NAME_PREFIX].
+    pub fn is_benchmark(&self) -> bool {
+
+        self.function.name != self.function.name_slice
+            && self.function.name_slice.starts_with(BENCH_NAME_PREFIX)
+
+    }
+
+    /// Is the test a fuzz target, see [FUZZ_NAME_PREFIX].
+    pub fn is_fuzz_target(&self) -> bool {
+
+        self.function.name != self.function.name_slice
+            && self.function.name_slice.starts_with(FUZZ_NAME_PREFIX)
+
+    }
+
     /// Test file stem used for debuging.
     #[allow(dead_code)]
     pub fn get_test_file_stem(
@@ -125,20 +242,579 @@ impl ParsedTest {
 
 }
 
+/// Replaces `range` of `contents` with whitespace, keeping the newlines
+/// such that offsets & line numbers after it stay the same.
+pub fn blank_range(contents: &mut String, range: (usize, usize)) {
+
+    let (start, end) = (range.0.min(contents.len()), range.1.min(contents.len()));
+
+    let Some(blanked) = contents.get(start..end) else {
+        return
+    };
+
+    let blanked: String = blanked
+        .chars()
+        .flat_map(|c| if c == '\n' { vec!['\n'] } else { vec![' '; c.len_utf8()] })
+        .collect();
+
+    contents.replace_range(start..end, &blanked);
+
+}
+
+/// Compiler flags that change how the preprocessor sees a file,
+/// these are passed to libclang for a [ParseMode::Full] parse.
+/// 
+/// Both the joined (`-Iinclude`) and separated (`-I include`)
+/// forms are recognized.
+fn preprocessor_flags(flags: &[String]) -> Vec<String> {
+
+    const SEPARABLE: [&str; 6] = ["-I", "-D", "-U", "-isystem", "-iquote", "-include"];
+
+    let mut accumulated: Vec<String> = vec![];
+    let mut iter = flags.iter();
+
+    while let Some(flag) = iter.next() {
+
+        if SEPARABLE.contains(&flag.as_str()) {
+            accumulated.push(flag.to_owned());
+            if let Some(value) = iter.next() {
+                accumulated.push(value.to_owned());
+            }
+        } else if SEPARABLE.iter().any(|x| flag.starts_with(x))
+               || flag.starts_with("-std=")
+        {
+            accumulated.push(flag.to_owned());
+        }
+
+    }
+
+    accumulated
+
+}
+
+/// Reads the file wide config from the comment containing the 
+/// [FILE_CONFIG_MARKER] line, every line of the comment after the
+/// marker is part of the config.
+/// 
+/// Example
+/// -------
+/// ```C
+/// /* 
+///  * #?cesty-file
+///  * [settings]
+///  * standalone = false
+///  */
+/// ```
+/// 
+/// Returns [None] if no comment contains the marker.
+fn file_config_from_contents(
+    contents: &str,
+    path:     &str
+) -> Result<Option<super::Config>, Alert> {
+
+    fn content_position(line: &str) -> Option<usize> {
+        line.find(|c: char| !c.is_whitespace() && c != '/' && c != '*')
+    }
+
+    let lines: Vec<&str> = contents.lines().collect();
+
+    let Some(marker) = lines.iter().position(|line| {
+        let trimmed = line.trim();
+        (trimmed.starts_with("//") || trimmed.starts_with("/*") || trimmed.starts_with("*"))
+        && content_position(line).is_some_and(|position| 
+            line[position..].trim_end().trim_end_matches("*/").trim_end() == FILE_CONFIG_MARKER)
+    }) else {
+        return Ok(None)
+    };
+
+    let singleline = lines[marker].trim().starts_with("//");
+    let mut comment_lines: Vec<(String, String, usize, usize)> = vec![];
+
+    if !singleline && lines[marker].contains("*/") {
+        return Config::from_comment_lines(comment_lines, path.to_owned()).map(Some)
+    }
+
+    for (index, line) in lines.iter().enumerate().skip(marker + 1) {
+
+        let trimmed = line.trim();
+
+        if singleline && !trimmed.starts_with("//") {
+            break;
+        }
+
+        let closing = !singleline && trimmed.contains("*/");
+        let line_content = if closing {
+            &line[..line.find("*/").unwrap()]
+        } else {
+            line
+        };
+
+        if let Some(position) = content_position(line_content) {
+            comment_lines.push((
+                line_content[position..].trim_end().to_owned(),
+                line.to_string(),
+                index + 1,
+                position
+            ));
+        }
+
+        if closing {
+            break;
+        }
+
+    }
+
+    Config::from_comment_lines(comment_lines, path.to_owned()).map(Some)
+
+}
+
+/// Keywords that can't be the name of a type or a parameter.
+const TYPE_KEYWORDS: [&str; 11] = [
+    "void", "_Bool", "bool", "char", "short", "int",
+    "long", "float", "double", "signed", "unsigned"
+];
+
+const QUALIFIERS: [&str; 3] = ["const", "volatile", "restrict"];
+
+/// Canonical spelling of a builtin type or a standard typedef, the same
+/// one libclang gives when the headers are resolved (assuming LP64),
+/// [None] for anything else.
+fn standard_type(base: &[&str]) -> Option<String> {
+
+    let typedef = match base {
+        [name] => match *name {
+            "int8_t" => Some("signed char"),
+            "uint8_t" => Some("unsigned char"),
+            "int16_t" => Some("short"),
+            "uint16_t" => Some("unsigned short"),
+            "int32_t" => Some("int"),
+            "uint32_t" => Some("unsigned int"),
+            "int64_t" | "ssize_t" | "ptrdiff_t" | "intptr_t" | "intmax_t" => Some("long"),
+            "uint64_t" | "size_t" | "uintptr_t" | "uintmax_t" => Some("unsigned long"),
+            "bool" | "_Bool" => Some("_Bool"),
+            _ => None
+        },
+        _ => None
+    };
+
+    if let Some(typedef) = typedef {
+        return Some(typedef.to_owned())
+    }
+
+    if base.is_empty() || base.iter().any(|token| !TYPE_KEYWORDS.contains(token)) {
+        return None
+    }
+
+    let count = |keyword: &str| base.iter().filter(|token| **token == keyword).count();
+    let unsigned = count("unsigned") > 0;
+
+    let spelled = if count("void") > 0 {
+        "void"
+    } else if count("float") > 0 {
+        "float"
+    } else if count("double") > 0 {
+        if count("long") > 0 { "long double" } else { "double" }
+    } else if count("char") > 0 {
+        if unsigned { "unsigned char" } else if count("signed") > 0 { "signed char" } else { "char" }
+    } else if count("short") > 0 {
+        if unsigned { "unsigned short" } else { "short" }
+    } else if count("long") > 1 {
+        if unsigned { "unsigned long long" } else { "long long" }
+    } else if count("long") == 1 {
+        if unsigned { "unsigned long" } else { "long" }
+    } else if unsigned {
+        "unsigned int"
+    } else {
+        "int"
+    };
+
+    Some(spelled.to_owned())
+
+}
+
+/// Type written as `declared` (without a parameter name), `resolved`
+/// being the canonical spelling libclang gave it. 
+/// 
+/// libclang spells a type it can't resolve as `int`, so a builtin type or
+/// a standard typedef is spelled from the declaration instead. A typedef
+/// that isn't declared inside of `contents` is kept as written, leaving
+/// it unknown rather than an `int`.
+fn declared_type(declared: &str, resolved: &str, contents: &str) -> String {
+
+    let spaced = declared.replace('*', " * ");
+    let tokens: Vec<&str> = spaced.split_whitespace().collect();
+
+    let pointers = tokens.iter().position(|token| *token == "*").unwrap_or(tokens.len());
+    let (base, rest) = tokens.split_at(pointers);
+
+    let qualifiers: Vec<&str> = base.iter().copied().filter(|token| QUALIFIERS.contains(token)).collect();
+    let base: Vec<&str> = base.iter().copied().filter(|token| !QUALIFIERS.contains(token)).collect();
+
+    // Spelled like libclang does, `char *const *`.
+    let mut suffix = String::new();
+    for token in rest {
+        match (*token, suffix.ends_with('*') || suffix.is_empty()) {
+            ("*", true) if suffix.is_empty() => suffix.push_str(" *"),
+            ("*", true)                      => suffix.push('*'),
+            ("*", false)                     => suffix.push_str(" *"),
+            (qualifier, _)                   => suffix.push_str(qualifier)
+        }
+    }
+
+    let spell = |base: &str| format!("{}{base}{suffix}",
+        qualifiers.iter().map(|qualifier| format!("{qualifier} ")).collect::<String>());
+
+    if let Some(standard) = standard_type(&base) {
+        return spell(&standard)
+    }
+
+    let unresolved = match base.as_slice() {
+        [name] => {
+            let pattern = format!(r"\btypedef\b[^;{{]*\b{0}\b|\}}\s*{0}\s*;", regex::escape(name));
+            !Regex::new(&pattern).is_ok_and(|typedef| typedef.is_match(contents))
+        }
+        _ => false
+    };
+
+    match unresolved {
+        true  => spell(&base.join(" ")),
+        false => resolved.to_owned()
+    }
+
+}
+
+/// Splits a parameter into its type & drops its name, [None] for
+/// parameters that aren't a plain type followed by a name (arrays,
+/// function pointers, varargs).
+fn parameter_type(parameter: &str) -> Option<String> {
+
+    if parameter.contains(['(', '[']) || parameter.contains("...") {
+        return None
+    }
+
+    let spaced = parameter.replace('*', " * ");
+    let tokens: Vec<&str> = spaced.split_whitespace().collect();
+    let named: Vec<&str> = tokens.iter().copied().filter(|token| !QUALIFIERS.contains(token)).collect();
+
+    let has_name = match named.as_slice() {
+        [.., before, last] => !TYPE_KEYWORDS.contains(last)
+            && *last != "*"
+            && !["struct", "enum", "union"].contains(before),
+        _ => false
+    };
+
+    let tokens = match has_name {
+        true  => &tokens[..tokens.iter().rposition(|token| *token == *named.last().unwrap()).unwrap()],
+        false => &tokens[..]
+    };
+
+    Some(tokens.join(" "))
+
+}
+
+/// Replaces the types of `function` a [ParseMode::SingleFile] parse can't
+/// resolve by the ones written inside of its `declaration` (everything up
+/// to its body), see [declared_type]. Functions declared through a macro
+/// keep the types libclang resolved.
+fn resolve_declared_types(function: &mut Function, declaration: &str, contents: &str) {
+
+    let Ok(name) = Regex::new(&format!(r"\b{}\s*\(", regex::escape(&function.name))) else {
+        return
+    };
+
+    let Some(found) = name.find(declaration) else {
+        return
+    };
+
+    let returns: Vec<&str> = declaration[..found.start()]
+        .split_whitespace()
+        .filter(|token| !["static", "inline", "extern", "__inline", "__inline__", "_Noreturn"].contains(token))
+        .collect();
+
+    if !returns.is_empty() && !returns.iter().any(|token| token.contains('(')) {
+        function.returns = declared_type(&returns.join(" "), &function.returns, contents);
+    }
+
+    let mut depth = 0;
+    let mut parameters: Vec<String> = vec![String::new()];
+
+    for character in declaration[found.end()..].chars() {
+        match character {
+            '(' => depth += 1,
+            ')' if depth == 0 => break,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parameters.push(String::new());
+                continue
+            }
+            _ => ()
+        }
+        parameters.last_mut().unwrap().push(character);
+    }
+
+    if let [only] = parameters.as_slice() {
+        if only.trim().is_empty() || only.trim() == "void" {
+            parameters.clear();
+        }
+    }
+
+    if parameters.len() != function.args.len() {
+        return
+    }
+
+    for (arg, parameter) in function.args.iter_mut().zip(parameters.iter()) {
+        if let Some(declared) = parameter_type(parameter) {
+            *arg = declared_type(&declared, arg, contents);
+        }
+    }
+
+}
+
+/// Checks if the harness is able to call `test` & decide its result
+/// from the return value, returns a warning pointing at the signature
+/// if it can't.
+/// 
+/// - The return type must have a [ReturnConvention].
+/// - A test can't accept parameters unless they're configured, the only
+///   input currently available is a single context pointer provided by
+///   the [Fixtures::before_each] fixture.
+/// - A property test accepts arguments generated from its `[property]`
+///   config, see [super::property].
+/// - A fuzz target must accept the data & its size and return an integer.
+fn signature_warning(
+    test:     &ParsedTest,
+    fixtures: &Fixtures,
+    contents: &str,
+    path:     &str
+) -> Option<Alert> {
+
+    let (comment, note) = if test.is_fuzz_target() {
+
+        let args = &test.function.args;
+
+        if test.function.return_convention() == Some(ReturnConvention::Int)
+        && args.len() == 2
+        && args[0].trim_end().ends_with('*')
+        && !args[1].contains('*')
+        {
+            return None
+        }
+
+        (
+            format!("fuzz target returns `{}` & accepts `{}`", test.function.returns, args.join(", ")),
+            vec![
+                "fuzz targets must be declared as `int <name>(const uint8_t *data, size_t size)`.".to_owned()
+            ]
+        )
+
+    } else if test.function.return_convention().is_none() {
+
+        (
+            format!("unsupported return type `{}`", test.function.returns),
+            vec![
+                "tests must return `bool` (pass on `true`), an integer (pass on `pass_value`, `0` by default) or `void`.".to_owned()
+            ]
+        )
+
+    } else if let Some(property) = test.config.property.as_ref() {
+
+        match Property::resolve(property, &test.function, None, &test.function.name) {
+            Ok(_) => return None,
+            Err(err) => (
+                format!("property test accepts `{}`", test.function.args.join(", ")),
+                vec![
+                    err,
+                    "property arguments can be integers, floats, `bool` or `char *` strings.".to_owned()
+                ]
+            )
+        }
+
+    } else if !test.function.args.is_empty() 
+           && (test.function.args.len() != 1 
+            || !test.function.args[0].trim_end().ends_with('*') 
+            || fixtures.before_each.is_none()) 
+    {
+
+        (
+            format!("test accepts `{}` but has no inputs configured", test.function.args.join(", ")),
+            vec![
+                "a test can only accept a single context pointer provided by a `before_each` fixture.".to_owned()
+            ]
+        )
+
+    } else {
+
+        return None
+
+    };
+
+    let start = test.range.template.0.min(contents.len());
+    let offset = contents
+        .get(start..test.range.template.1.min(contents.len()))
+        .and_then(|template| template.find(&test.function.name))
+        .map(|position| start + position)
+        .unwrap_or(start);
+
+    let line = contents[..offset].matches('\n').count() + 1;
+    let column = offset - contents[..offset].rfind('\n').map(|x| x + 1).unwrap_or(0) + 1;
+
+    let mut note = note;
+    note.push(format!("test `{}` will not be ran.", test.function.name));
+
+    Some(warning!{
+        debug: debuginfo!(),
+        description: format!("test `{}` has an unsupported signature", test.function.name),
+        example: Some(AlertExample::Code(AlertCode {
+            line,
+            file: path.to_owned(),
+            code: contents.to_owned(),
+            fix: vec![AlertCodeFix {
+                relative_line: line - 1,
+                column,
+                comment
+            }]
+        })),
+        note: note
+    })
+
+}
+
+/// Extract all tests from the file at `path`.
+/// 
+/// A function is a test if its name starts with one of the prefixes
+/// from [Recipe::prefix] or [Run::prefix] (in that order, defaulting to
+/// [DEFAULT_FUNCTION_PREFIX]), or if it is marked with 
+/// [crate::defaults::TEST_ANNOTATION] or [crate::defaults::TEST_MARKER].
+/// 
+/// Tests declared through a macro invoked inside of the file (like
+/// `TEST(parse_empty) { ... }`) are found as long as the macro can
+/// be expanded, macros defined inside of headers need a full parse. 
+/// Their ranges & comments are taken from the macro invocation.
+/// 
+/// If [Run::full_parse] or [Recipe::full_parse] is set the file is
+/// first parsed with [ParseMode::Full], every diagnostic libclang
+/// returns is passed along as a [Alert] and if the parse had any 
+/// errors the file is reparsed with [ParseMode::SingleFile].
+/// 
+/// Tests the harness can't call are dropped with a warning, 
+/// see [signature_warning].
 pub fn extract(
-    path: PathBuf
+    path:     PathBuf,
+    run_conf: &Run,
+    recipe:   Option<&Recipe>
 ) -> Result<(ParsedFile, Vec<Alert>), Alert> {
 
     let mut warnings: Vec<Alert> = vec![];
 
-    let clang = match Clang::open(&path, "-fparse-all-comments") {
-        Ok(clang) => clang,
-        Err(err) => { return Err(debugpush!(err)) }
+    let mut clang_args: Vec<String> = vec!["-fparse-all-comments".to_owned()];
+
+    let full_parse = run_conf.full_parse 
+        || recipe.is_some_and(|recipe| recipe.full_parse == Some(true));
+
+    let full_clang = if full_parse {
+
+        if let Some(compiler) = run_conf.compiler.as_ref() {
+            clang_args.append(&mut preprocessor_flags(&compiler.flags));
+        }
+
+        match Clang::open(&path, (clang_args.as_slice(), ParseMode::Full)) {
+            Ok(clang) => {
+
+                let diagnostics = clang.diagnostics();
+
+                warnings.append(&mut diagnostics
+                    .iter()
+                    .map(|diagnostic| Alert::from_clang_diagnostic(diagnostic, debuginfo!()))
+                    .collect());
+
+                if diagnostics.iter().any(|diagnostic| diagnostic.severity >= DiagnosticSeverity::Error) {
+                    warnings.push(warning!{
+                        debug: debuginfo!(),
+                        description: format!("full parse of `{}` failed, falling back to a single file parse", path.to_string_lossy()),
+                        example: None,
+                        note: vec![
+                            "check the errors above, usually a header was not found or a macro is undefined.".to_owned(),
+                            "add the missing `-I` / `-D` flags to the compiler flags in the config.".to_owned(),
+                            "types from included headers will not be resolved for this file.".to_owned()
+                        ]
+                    });
+                    None
+                } else {
+                    Some(clang)
+                }
+
+            },
+            Err(Alert::Error(info) | Alert::Warning(info)) => {
+                warnings.push(warning!{
+                    debug: debuginfo!(),
+                    description: format!("full parse of `{}` failed, falling back to a single file parse", path.to_string_lossy()),
+                    example: None,
+                    note: vec![
+                        format!("libclang: {}", info.description),
+                        "types from included headers will not be resolved for this file.".to_owned()
+                    ]
+                });
+                None
+            }
+        }
+
+    } else {
+
+        None
+
+    };
+
+    let fully_parsed = full_clang.is_some();
+
+    let clang = match full_clang {
+        Some(clang) => clang,
+        None => match Clang::open(&path, "-fparse-all-comments") {
+            Ok(clang) => clang,
+            Err(err) => { return Err(debugpush!(err)) }
+        }
+    };
+
+    let prefixes: Vec<String> = if recipe.is_some_and(|recipe| !recipe.prefix.is_empty()) {
+        recipe.unwrap().prefix.clone()
+    } else if !run_conf.prefix.is_empty() {
+        run_conf.prefix.clone()
+    } else {
+        vec![DEFAULT_FUNCTION_PREFIX.to_owned()]
     };
 
-    match visitor::visit(path.as_path(), &clang) {
-        Ok(((tests, main, environment), mut ext_warnings)) => {
+    match visitor::visit(path.as_path(), &clang, &prefixes) {
+        Ok(((mut tests, main, mut fixtures, environment), mut ext_warnings)) => {
             warnings.append(&mut ext_warnings);
+            if !fully_parsed {
+                for test in tests.iter_mut().chain([
+                    fixtures.setup.as_mut(),
+                    fixtures.teardown.as_mut(),
+                    fixtures.before_each.as_mut(),
+                    fixtures.after_each.as_mut()
+                ].into_iter().flatten()) {
+                    if let Some(declaration) = environment.full.get(test.range.template.0..test.range.template.1) {
+                        resolve_declared_types(&mut test.function, declaration, &environment.full);
+                    }
+                }
+            }
+            let config = match file_config_from_contents(
+                &environment.full, 
+                &path.to_string_lossy()
+            ) {
+                Ok(config) => config.unwrap_or_default(),
+                Err(err) => return Err(debugpush!(err))
+            };
+            let mut callable_tests: Vec<ParsedTest> = vec![];
+            let mut benchmarks: Vec<ParsedTest> = vec![];
+            let mut fuzz_targets: Vec<ParsedTest> = vec![];
+            for test in tests {
+                match signature_warning(&test, &fixtures, &environment.full, &path.to_string_lossy()) {
+                    Some(warning) => warnings.push(warning),
+                    None if test.is_benchmark() => benchmarks.push(test),
+                    None if test.is_fuzz_target() => fuzz_targets.push(test),
+                    None => callable_tests.push(test)
+                }
+            }
             return Ok((ParsedFile {
                 path: path.clone(),
                 stem: if path.file_stem().is_some() {
@@ -151,8 +827,12 @@ pub fn extract(
                         note: vec!["path.file_stem() returned `None`".to_owned()]
                     }
                 },
-                test: tests,
+                test: callable_tests,
+                bench: benchmarks,
+                fuzz: fuzz_targets,
+                fixtures,
                 environment,
+                config,
                 main
             }, warnings))
         }
@@ -179,7 +859,11 @@ pub mod visitor {
             cxstring_to_string_consumable, 
             filename_from_cursor 
         },
-        defaults::DEFAULT_FUNCTION_PREFIX, 
+        defaults::{
+            FIXTURE_AFTER_EACH, FIXTURE_BEFORE_EACH,
+            FIXTURE_SETUP, FIXTURE_TEARDOWN,
+            TEST_ANNOTATION, TEST_MARKER
+        }, 
         error::{
             debugappend, debuginfo, 
             debugpush, error, 
@@ -190,7 +874,7 @@ pub mod visitor {
         test::Config
     };
 
-    use super::{Environment, ParsedTest};
+    use super::{Environment, Fixtures, ParsedTest};
 
     #[derive(
         strum_macros::EnumProperty, 
@@ -262,12 +946,10 @@ pub mod visitor {
                 };
             }
             
-            let mut comment = match cxstring_to_string_consumable(
-                unsafe { libclang::clang_Cursor_getRawCommentText(cursor) }
-            ) {
+            let mut comment = match raw_comment_from_cursor(cursor) {
 
-                Ok(comment) => comment,
-                Err(_) => return Ok(None)
+                Some(comment) => comment,
+                None => return Ok(None)
 
             }.trim().to_owned();
 
@@ -470,6 +1152,91 @@ pub mod visitor {
 
     }
 
+    /// Returns the comment written directly above `offset` in `code`,
+    /// empty lines in between are not allowed.
+    fn comment_above_offset(
+        code:   &str,
+        offset: usize
+    ) -> Option<String> {
+
+        let before = match code.get(..offset) {
+            Some(before) => &before[..before.rfind('\n').map(|x| x + 1).unwrap_or(0)],
+            None => return None
+        };
+
+        let mut collected: Vec<&str> = vec![];
+        let mut inside_multiline = false;
+
+        for line in before.lines().rev() {
+
+            let trimmed = line.trim();
+
+            if inside_multiline {
+                collected.push(line);
+                if trimmed.contains("/*") {
+                    inside_multiline = false;
+                }
+            } else if trimmed.starts_with("//") {
+                collected.push(line);
+            } else if trimmed.ends_with("*/") {
+                collected.push(line);
+                if !trimmed.starts_with("/*") {
+                    inside_multiline = true;
+                }
+            } else {
+                break;
+            }
+
+        }
+
+        if collected.is_empty() || inside_multiline {
+            return None
+        }
+
+        Some(collected
+            .into_iter()
+            .rev()
+            .collect::<Vec<&str>>()
+            .join("\n")
+        )
+
+    }
+
+    /// [libclang::clang_Cursor_getRawCommentText] that also finds the 
+    /// comment of functions declared through a macro.
+    /// 
+    /// libclang doesn't attach comments to declarations whose location
+    /// is inside of a macro expansion, so for these the comment is 
+    /// read from above the macro invocation found inside of 
+    /// [MACRO_EXPANSIONS].
+    fn raw_comment_from_cursor(
+        cursor: libclang::CXCursor
+    ) -> Option<String> {
+
+        match cxstring_to_string_consumable(
+            unsafe { libclang::clang_Cursor_getRawCommentText(cursor) }
+        ) {
+            Ok(comment) if !comment.trim().is_empty() => return Some(comment),
+            _ => ()
+        }
+
+        let offset = rustclang::expansion_offset_from_cursor(cursor);
+
+        let start = MACRO_EXPANSIONS.with(|n| n
+            .borrow()
+            .iter()
+            .find(|(start, end)| *start <= offset && offset <= *end)
+            .map(|(start, _)| *start)
+        )?;
+
+        let code = std::fs::read_to_string(
+            rustclang::filename_from_cursor(cursor).ok()?
+        ).ok()?;
+
+        comment_above_offset(&code, start)
+
+    }
+
     /// Cleans up the raw config comment.
     /// 
     /// Example of comments
@@ -810,6 +1577,40 @@ pub mod visitor {
 
     }
 
+    /// Checks if the function is marked as a test with either 
+    /// [TEST_ANNOTATION] or a [TEST_MARKER] line inside of its comment.
+    fn is_marked_as_test(
+        cursor: libclang::CXCursor
+    ) -> bool { unsafe {
+
+        let annotated = rustclang::annotations_from_cursor(cursor)
+            .into_iter()
+            .chain(rustclang::annotations_from_cursor(libclang::clang_getCanonicalCursor(cursor)))
+            .any(|annotation| annotation == TEST_ANNOTATION);
+
+        if annotated {
+            return true
+        }
+
+        match raw_comment_from_cursor(cursor) {
+            Some(comment) => comment
+                .lines()
+                .any(|line| line
+                    .trim()
+                    .trim_start_matches(|c: char| c == '/' || c == '*')
+                    .trim_end_matches(|c: char| c == '/' || c == '*')
+                    .trim() == TEST_MARKER
+                ),
+            None => false
+        }
+
+    }}
+
+    /// Returns the full function name & the name without the prefix
+    /// if the function is a test.
+    /// 
+    /// Tests matched through [is_marked_as_test] without a prefix
+    /// keep their full name as the name without the prefix.
     fn valid_function_name_from_cursor(
         cursor: libclang::CXCursor
     ) -> Result<(Option<(String, String)>, Vec<Alert>), Alert> { unsafe {
@@ -836,11 +1637,26 @@ pub mod visitor {
             Err(err) => return Err(debugpush!(err))
         };
 
-        if function_name.starts_with(DEFAULT_FUNCTION_PREFIX) == true {
+        // Longest prefix wins so that `cesty_` & `cesty_unit_` can coexist.
+        let prefix = PREFIXES.with(|n| n
+            .borrow()
+            .iter()
+            .filter(|prefix| function_name.starts_with(prefix.as_str()))
+            .max_by_key(|prefix| prefix.len())
+            .cloned()
+        );
+
+        let marked = is_marked_as_test(cursor);
+
+        if let Some(prefix) = prefix {
 
-            let test_name_part = function_name[DEFAULT_FUNCTION_PREFIX.len()..].to_owned();
+            let test_name_part = function_name[prefix.len()..].to_owned();
 
-            if test_name_part.is_empty() {
+            if test_name_part.is_empty() && marked {
+
+                return Ok((Some((function_name.clone(), function_name)), warnings));
+
+            } else if test_name_part.is_empty() {
 
                 let (code, relative_line, column) = match rustclang::filename_from_cursor(cursor) {
                     Ok(filename) => {
@@ -879,6 +1695,10 @@ pub mod visitor {
 
             Ok((Some((function_name, test_name_part)), warnings))
 
+        } else if marked {
+
+            Ok((Some((function_name.clone(), function_name)), warnings))
+
         } else {
 
             Ok((None, warnings))
@@ -901,19 +1721,124 @@ pub mod visitor {
         //                              mane info, start, end
         static MAIN:        RefCell<Option<(ParsedTest, usize, usize)>> = RefCell::new(None);
         static ERROR:       RefCell<Option<Alert>>                      = RefCell::new(None);
+        static PREFIXES:    RefCell<Vec<String>>                        = RefCell::new(vec![]);
+        //                              Start, end of macro invocations
+        static MACRO_EXPANSIONS: RefCell<Vec<(usize, usize)>>           = RefCell::new(vec![]);
+        static FIXTURES:    RefCell<Fixtures>                           = RefCell::new(Fixtures::default());
+
+    }
+
+    /// Moves `test` into [FIXTURES] if its name (without the prefix)
+    /// is the name of a fixture, returns `false` if it's a regular test.
+    /// 
+    /// Fixtures with an unusable signature are dropped with a warning,
+    /// setup & teardown run in their own process so they can't accept
+    /// a context while before/after each accept at most a single pointer.
+    fn store_if_fixture(test: &ParsedTest) -> bool {
+
+        if test.function.name == test.function.name_slice {
+            return false
+        }
+
+        let accepts_context = match test.function.name_slice.as_str() {
+            FIXTURE_SETUP | FIXTURE_TEARDOWN => false,
+            FIXTURE_BEFORE_EACH | FIXTURE_AFTER_EACH => true,
+            _ => return false
+        };
+
+        let valid_arguments = match test.function.args.len() {
+            0 => true,
+            1 => accepts_context && test.function.args[0].trim_end().ends_with('*'),
+            _ => false
+        };
+
+        if !valid_arguments {
+            WARNINGS.with(|n| n.borrow_mut().push(warning!{
+                debug: debuginfo!(),
+                description: format!("fixture `{}` has an unsupported signature and will be ignored",
+                    test.function.name),
+                example: None,
+                note: vec![
+                    format!("`{FIXTURE_SETUP}` & `{FIXTURE_TEARDOWN}` fixtures can't accept any arguments."),
+                    format!("`{FIXTURE_BEFORE_EACH}` & `{FIXTURE_AFTER_EACH}` fixtures accept at most a single pointer to the context.")
+                ]
+            }));
+            return true
+        }
+
+        FIXTURES.with(|n| {
+
+            let mut fixtures = n.borrow_mut();
+
+            let slot = match test.function.name_slice.as_str() {
+                FIXTURE_SETUP       => &mut fixtures.setup,
+                FIXTURE_TEARDOWN    => &mut fixtures.teardown,
+                FIXTURE_BEFORE_EACH => &mut fixtures.before_each,
+                _                   => &mut fixtures.after_each
+            };
+
+            if let Some(existing) = slot.as_ref() {
+                WARNINGS.with(|n| n.borrow_mut().push(warning!{
+                    debug: debuginfo!(),
+                    description: format!("fixture `{}` is ignored, `{}` was already found",
+                        test.function.name, existing.function.name),
+                    example: None,
+                    note: vec![
+                        "only a single fixture of every kind is used per file.".to_owned()
+                    ]
+                }));
+            } else {
+                *slot = Some(test.clone());
+            }
+
+        });
+
+        true
 
     }
 
     pub fn visit(
-        path: &Path,
-        clang: &Clang
-    ) -> Result<((Vec<ParsedTest>, Option<ParsedTest>, Environment), Vec<Alert>), Alert> {
+        path:     &Path,
+        clang:    &Clang,
+        prefixes: &[String]
+    ) -> Result<((Vec<ParsedTest>, Option<ParsedTest>, Fixtures, Environment), Vec<Alert>), Alert> {
 
         TEST_STACK .with(|n| n.borrow_mut().clear());
         CLEAN_STACK.with(|n| n.borrow_mut().clear());
         WARNINGS   .with(|n| n.borrow_mut().clear());
         MAIN       .with(|n| (*n.borrow_mut()) = None);
         ERROR      .with(|n| (*n.borrow_mut()) = None);
+        PREFIXES   .with(|n| (*n.borrow_mut()) = prefixes.to_vec());
+        MACRO_EXPANSIONS.with(|n| n.borrow_mut().clear());
+        FIXTURES   .with(|n| (*n.borrow_mut()) = Fixtures::default());
+
+        // Macro invocations from the detailed preprocessing record, used
+        // to map tests declared through macros back to the invocation.
+        extern "C" fn macro_filter(
+
+            cursor: libclang::CXCursor,
+            _p:     libclang::CXCursor,
+            _d:     libclang::CXClientData
+    
+        ) -> libclang::CXChildVisitResult { unsafe {
+
+            if libclang::clang_getCursorKind(cursor) == libclang::CXCursor_MacroExpansion
+            && libclang::clang_Location_isFromMainFile(libclang::clang_getCursorLocation(cursor)) != 0
+            {
+                MACRO_EXPANSIONS.with(|n| n.borrow_mut().push(rustclang::range_from_cursor_extent(cursor)));
+            }
+
+            libclang::CXChildVisit_Continue
+
+        }}
+
+        unsafe { 
+            libclang::clang_visitChildren(
+                clang.cursor,
+                macro_filter,
+                null_mut()
+            );
+        }
         
         extern "C" fn filter(
 
@@ -924,9 +1849,7 @@ pub mod visitor {
         ) -> libclang::CXChildVisitResult { unsafe {
 
 
-            if libclang::clang_Location_isFromMainFile(
-                libclang::clang_getCursorLocation(cursor)
-                ) == 0
+            if !rustclang::is_from_main_file(cursor)
             {
                 return libclang::CXChildVisit_Continue;
             }
@@ -943,6 +1866,12 @@ pub mod visitor {
                  && libclang::clang_getCursorKind(cursor) == libclang::CXCursor_CompoundStmt
             {
 
+                // Bodies written inside of a macro definition have no
+                // usable range inside of the main file.
+                if libclang::clang_Location_isFromMainFile(libclang::clang_getCursorLocation(cursor)) == 0 {
+                    return libclang::CXChildVisit_Continue;
+                }
+
                 let body_range = rustclang::range_from_cursor_extent(cursor);
 
                 let template_range = (
@@ -1002,9 +1931,11 @@ pub mod visitor {
                 let returns = 
                 match rustclang::cxstring_to_string_consumable(
                     libclang::clang_getTypeSpelling(
-                        libclang::clang_getResultType(
-                            libclang::clang_getCursorType(
-                                libclang::clang_getCursorSemanticParent(cursor)
+                        libclang::clang_getCanonicalType(
+                            libclang::clang_getResultType(
+                                libclang::clang_getCursorType(
+                                    libclang::clang_getCursorSemanticParent(cursor)
+                                )
                             )
                         )
                     )
@@ -1144,7 +2075,7 @@ pub mod visitor {
                         return libclang::CXChildVisit_Break;
                     } 
                     MAIN.with(|n| (*n.borrow_mut()) = Some((test, main_line, main_column)))
-                } else {
+                } else if !store_if_fixture(&test) {
                     TEST_STACK.with(|n| n.borrow_mut().push(test));
                 }
 
@@ -1176,9 +2107,10 @@ pub mod visitor {
         let warnings: Vec<Alert> = debugappend!(WARNINGS.with(|n| n.borrow_mut().to_owned()));
         let tests:    Vec<ParsedTest> = TEST_STACK.with(|n| n.borrow_mut().to_owned());
         let main:     Option<(ParsedTest, usize, usize)> = MAIN.with(|n| (*n.borrow_mut()).to_owned());
+        let fixtures: Fixtures = FIXTURES.with(|n| n.borrow_mut().to_owned());
 
         if tests.is_empty() {
-            return Ok(((vec![], None, Environment::default()), warnings))
+            return Ok(((vec![], None, Fixtures::default(), Environment::default()), warnings))
         }
 
         let file_contents = match std::fs::read_to_string(path) {
@@ -1196,14 +2128,17 @@ pub mod visitor {
             mainless: {
                 let mut cleaned_up = file_contents.clone();
                 if main.is_some() {
-                    cleaned_up.replace_range(main.as_ref().unwrap().0.range.template.0..main.as_ref().unwrap().0.range.body.1, "");
+                    super::blank_range(&mut cleaned_up, (main.as_ref().unwrap().0.range.template.0, main.as_ref().unwrap().0.range.body.1));
                 }
                 cleaned_up
             },
             templated: {
                 let mut cleaned_up = file_contents.clone();
                 for cleanup in clean.iter().rev() {
-                    cleaned_up.replace_range(cleanup.0..cleanup.1, if cleanup.2 == true {""} else {";"} );
+                    super::blank_range(&mut cleaned_up, (cleanup.0, cleanup.1));
+                    if !cleanup.2 && cleanup.0 < cleanup.1 {
+                        cleaned_up.replace_range(cleanup.0..cleanup.0 + 1, ";");
+                    }
                 }
                 cleaned_up
             }
@@ -1211,14 +2146,112 @@ pub mod visitor {
 
         if main.is_some() {
 
-            Ok(((tests, Some(main.unwrap().0), environment), warnings))
+            Ok(((tests, Some(main.unwrap().0), fixtures, environment), warnings))
 
         } else {
 
-            Ok(((tests, None, environment), warnings))
+            Ok(((tests, None, fixtures, environment), warnings))
 
         }
 
     }
 
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+
+    use super::{resolve_declared_types, Function};
+
+    /// `function` as a single file parse sees it, with every
+    /// type from a header spelled as `int`.
+    fn resolve(declaration: &str, returns: &str, args: &[&str], contents: &str) -> Function {
+
+        let name = declaration.split('(').next().unwrap().split_whitespace().last().unwrap();
+
+        let mut function = Function {
+            returns: returns.to_owned(),
+            name: name.to_owned(),
+            name_slice: name.trim_start_matches("cesty_").to_owned(),
+            args: args.iter().map(|arg| arg.to_string()).collect()
+        };
+
+        resolve_declared_types(&mut function, declaration, contents);
+
+        function
+
+    }
+
+    #[test]
+    fn bool_from_a_skipped_header() {
+
+        // `#include <stdbool.h>` isn't followed, libclang resolves `bool` as `int`.
+        let function = resolve("bool cesty_x(void) ", "int", &[], "");
+        assert_eq!(function.returns, "_Bool");
+        assert_eq!(function.return_convention(), Some(super::ReturnConvention::Bool));
+
+        let function = resolve("static inline bool\ncesty_y(bool flag) ", "int", &["int"], "");
+        assert_eq!(function.returns, "_Bool");
+        assert_eq!(function.args, ["_Bool"]);
+
+    }
+
+    #[test]
+    fn standard_typedefs_and_builtins() {
+
+        let function = resolve(
+            "int cesty_fuzz_parse(const uint8_t *data, size_t size) ",
+            "int",
+            &["const int *", "int"],
+            ""
+        );
+        assert_eq!(function.args, ["const unsigned char *", "unsigned long"]);
+
+        let function = resolve(
+            "unsigned cesty_a(long int a, char *const *argv, unsigned long long) ",
+            "unsigned int",
+            &["long", "char *const *", "unsigned long long"],
+            ""
+        );
+        assert_eq!(function.returns, "unsigned int");
+        assert_eq!(function.args, ["long", "char *const *", "unsigned long long"]);
+
+    }
+
+    #[test]
+    fn unknown_typedefs_are_not_trusted() {
+
+        let function = resolve("status_t cesty_a(void) ", "int", &[], "#include \"status.h\"\n");
+        assert_eq!(function.returns, "status_t");
+        assert_eq!(function.return_convention(), None);
+
+    }
+
+    #[test]
+    fn typedefs_of_the_file_keep_the_resolved_type() {
+
+        let contents = "typedef long my_int;\ntypedef struct { int x; } point;\n";
+
+        let function = resolve("my_int cesty_a(point *p, struct node *n) ", "long", &["point *", "struct node *"], contents);
+        assert_eq!(function.returns, "long");
+        assert_eq!(function.args, ["point *", "struct node *"]);
+
+    }
+
+    #[test]
+    fn macro_declarations_are_left_alone() {
+
+        let function = resolve("TEST(cesty_parse_empty) ", "int", &[], "");
+        // Found the name but nothing in front of it, the return type stays.
+        assert_eq!(function.returns, "int");
+
+        let mut function = Function {
+            returns: "int".to_owned(),
+            name: "cesty_other".to_owned(),
+            ..Default::default()
+        };
+        resolve_declared_types(&mut function, "CESTY(bool, other) ", "");
+        assert_eq!(function.returns, "int");
+
+    }
+
+}
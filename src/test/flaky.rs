@@ -0,0 +1,549 @@
+//! Tests whose outcome changed between runs of the exact same binary,
+//! found from the runs stored by [super::history].
+//!
+//! Flip rate
+//! ---------
+//! Every result is stored along with the fingerprint of its harness, see
+//! [super::cache::key], covering the source, the flags & the linked
+//! binaries. Consecutive runs of a test with the same fingerprint are
+//! compared & a pair where the test passed in one but not in the other
+//! is a flip. A test that only passed on a retry ([Outcome::Flaky]) is
+//! a flip on its own. The flip rate is the flips over all comparisons.
+//!
+//! Quarantine
+//! ----------
+//! `cesty flaky --quarantine` adds the flaky tests to the `quarantine`
+//! list of the config, `cesty run` reports them separately & they never
+//! fail the run. Remove a test from the list once it's fixed.
+//!
+//! The config is edited in place such that its comments are kept, a YAML
+//! config only if the list is missing or a block list, otherwise the
+//! tests to add by hand are printed instead.
+
+use std::path::Path;
+
+use colored::Colorize;
+
+use crate::{
+    arg_conf::{ConfigLanguage, Flaky},
+    defaults::DEFAULT_FLAKY_RUNS,
+    error::{
+        debuginfo, debugpush, error, function_message,
+        Alert, AlertInfo
+    }
+};
+
+use super::{
+    history::RunRecord,
+    run::Outcome
+};
+
+/// A test whose outcome changed between runs of the same binary.
+#[derive(Clone, Debug)]
+pub struct FlakyTest {
+
+    /// See [super::run::identity].
+    pub identity: String,
+
+    /// Times the outcome changed.
+    pub flips: usize,
+
+    /// Times the outcome could have changed.
+    pub comparisons: usize,
+
+    /// Fingerprint & whether the test passed in the
+    /// newest run looked at so far.
+    previous: Option<(String, bool)>
+
+}
+
+impl FlakyTest {
+
+    /// Flips over comparisons.
+    pub fn rate(&self) -> f64 {
+
+        match self.comparisons {
+            0 => 0.0,
+            _ => self.flips as f64 / self.comparisons as f64
+        }
+
+    }
+
+}
+
+/// The flaky tests of the newest `runs` of `records` (newest
+/// first), ranked by their flip rate.
+pub fn detect(records: &[RunRecord], runs: usize) -> Vec<FlakyTest> {
+
+    let mut tests: Vec<FlakyTest> = vec![];
+
+    for record in records.iter().take(runs).rev() {
+
+        for test in record.tests.iter() {
+
+            let Some(fingerprint) = test.fingerprint.as_ref() else {
+                continue
+            };
+
+            if test.outcome == Outcome::Skipped {
+                continue
+            }
+
+            let index = match tests.iter().position(|known| known.identity == test.identity) {
+                Some(index) => index,
+                None => {
+                    tests.push(FlakyTest {
+                        identity: test.identity.clone(),
+                        flips: 0,
+                        comparisons: 0,
+                        previous: None
+                    });
+                    tests.len() - 1
+                }
+            };
+
+            let flaky = &mut tests[index];
+            let passed = !test.outcome.unsuccessful();
+
+            if test.outcome == Outcome::Flaky {
+                flaky.flips += 1;
+                flaky.comparisons += 1;
+            }
+
+            if let Some((previous, previously_passed)) = flaky.previous.as_ref() {
+                if previous == fingerprint {
+                    flaky.comparisons += 1;
+                    if *previously_passed != passed {
+                        flaky.flips += 1;
+                    }
+                }
+            }
+
+            flaky.previous = Some((fingerprint.clone(), passed));
+
+        }
+
+    }
+
+    tests.retain(|test| test.flips > 0);
+    tests.sort_by(|a, b| b.rate()
+        .total_cmp(&a.rate())
+        .then_with(|| b.flips.cmp(&a.flips))
+        .then_with(|| a.identity.cmp(&b.identity))
+    );
+
+    tests
+
+}
+
+/// Appends `identities` to the top level `quarantine` block list of the
+/// YAML `contents`, adding the list if there is none. [None] if the list
+/// is written in any other way, like `quarantine: [...]`.
+fn quarantine_yaml(contents: &str, identities: &[String]) -> Option<String> {
+
+    let items = identities
+        .iter()
+        .map(|identity| serde_yaml::to_string(identity).ok().map(|item| item.trim_end().to_owned()))
+        .collect::<Option<Vec<String>>>()?;
+
+    let mut lines: Vec<&str> = contents.lines().collect();
+
+    let Some(key) = lines.iter().position(|line| line.starts_with("quarantine:")) else {
+        let mut updated = contents.to_owned();
+        if !updated.is_empty() && !updated.ends_with('\n') {
+            updated.push('\n');
+        }
+        updated.push_str("quarantine:\n");
+        for item in items {
+            updated.push_str(&format!("  - {item}\n"));
+        }
+        return Some(updated)
+    };
+
+    let value = lines[key]["quarantine:".len()..].trim();
+    if !value.is_empty() && !value.starts_with('#') {
+        return None
+    }
+
+    // The list ends at the next line that isn't indented, blank,
+    // a comment or an item of a list that isn't indented.
+    let mut last = key;
+    let mut indent: Option<String> = None;
+
+    for (index, line) in lines.iter().enumerate().skip(key + 1) {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue
+        }
+        if !line.starts_with(char::is_whitespace) && !line.starts_with('-') {
+            break
+        }
+        if trimmed.starts_with('-') && indent.is_none() {
+            indent = Some(line[..line.len() - trimmed.len()].to_owned());
+        }
+        last = index;
+    }
+
+    let indent = indent.unwrap_or("  ".to_owned());
+    let new: Vec<String> = items.iter().map(|item| format!("{indent}- {item}")).collect();
+
+    lines.splice(last + 1..last + 1, new.iter().map(String::as_str));
+
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+
+    Some(updated)
+
+}
+
+/// Adds `identities` to the `quarantine` list of the config at `path`,
+/// keeping the tests already inside of it. Returns the amount added.
+fn quarantine(path: &Path, identities: &[String]) -> Result<usize, Alert> {
+
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) => return error!{
+            debug: debuginfo!(),
+            description: format!("failed to open `{}`", path.to_string_lossy()),
+            example: None,
+            note: function_message!("std::fs::read_to_string()", err.to_string())
+        }
+    };
+
+    let language = match ConfigLanguage::try_from(path.extension().unwrap_or_default()) {
+        Ok(language) => language,
+        Err(err) => return Err(debugpush!(err))
+    };
+
+    let mut added: usize = 0;
+
+    let updated = match language {
+
+        // Edited in place such that comments & formatting are kept.
+        ConfigLanguage::TOML(_) => {
+
+            let mut document = match contents.parse::<toml_edit::DocumentMut>() {
+                Ok(document) => document,
+                Err(err) => return error!{
+                    debug: debuginfo!(),
+                    description: format!("failed to parse `{}`", path.to_string_lossy()),
+                    example: None,
+                    note: function_message!("toml_edit::DocumentMut::from_str()", err.to_string())
+                }
+            };
+
+            let mut list = document
+                .get("quarantine")
+                .and_then(|item| item.as_array())
+                .cloned()
+                .unwrap_or_default();
+
+            for identity in identities {
+                if !list.iter().any(|value| value.as_str() == Some(identity)) {
+                    list.push(identity.as_str());
+                    added += 1;
+                }
+            }
+
+            document["quarantine"] = toml_edit::value(list);
+            document.to_string()
+
+        }
+
+        // serde_yaml drops comments, the block list is edited as text instead.
+        ConfigLanguage::YAML(_) => {
+
+            let list: Vec<String> = match serde_yaml::from_str::<serde_yaml::Mapping>(&contents) {
+                Ok(document) => document
+                    .get("quarantine")
+                    .and_then(|value| serde_yaml::from_value(value.clone()).ok())
+                    .unwrap_or_default(),
+                Err(err) => return error!{
+                    debug: debuginfo!(),
+                    description: format!("failed to parse `{}`", path.to_string_lossy()),
+                    example: None,
+                    note: function_message!("serde_yaml::from_str()", err.to_string())
+                }
+            };
+
+            let missing: Vec<String> = identities
+                .iter()
+                .filter(|identity| !list.contains(identity))
+                .cloned()
+                .collect();
+
+            added = missing.len();
+
+            if missing.is_empty() {
+                contents
+            } else {
+                let expected: Vec<String> = list.into_iter().chain(missing.iter().cloned()).collect();
+                match quarantine_yaml(&contents, &missing)
+                    .filter(|updated| serde_yaml::from_str::<serde_yaml::Mapping>(updated)
+                        .ok()
+                        .and_then(|document| document.get("quarantine").cloned())
+                        .and_then(|value| serde_yaml::from_value::<Vec<String>>(value).ok())
+                        == Some(expected.clone()))
+                {
+                    Some(updated) => updated,
+                    None => return error!{
+                        debug: debuginfo!(),
+                        description: format!("failed to add the flaky tests to `quarantine` inside of `{}`", path.to_string_lossy()),
+                        example: None,
+                        note: std::iter::once(
+                            "only a block list (`quarantine:` followed by `- <test>` lines) is edited in place, add these by hand:".to_owned()
+                        )
+                        .chain(missing.iter().map(|identity| format!("- {identity}")))
+                        .collect()
+                    }
+                }
+            }
+
+        }
+
+    };
+
+    match std::fs::write(path, updated) {
+        Ok(_) => Ok(added),
+        Err(err) => error!{
+            debug: debuginfo!(),
+            description: format!("failed to write `{}`", path.to_string_lossy()),
+            example: None,
+            note: function_message!("std::fs::write()", err.to_string())
+        }
+    }
+
+}
+
+/// Runs "cesty flaky ...".
+pub fn flaky(flaky_conf: &Flaky) -> Result<Vec<Alert>, Alert> {
+
+    let runs = flaky_conf.runs.unwrap_or(DEFAULT_FLAKY_RUNS);
+    let (records, warnings) = super::history::records(&super::history::history_path(&flaky_conf.run));
+    let looked_at = records.len().min(runs);
+
+    let flaky = detect(&records, runs);
+
+    if flaky.is_empty() {
+        println!("no flaky tests over the last {looked_at} runs");
+        return Ok(warnings)
+    }
+
+    println!("{}", format!("flaky tests over the last {looked_at} runs:").bold());
+
+    for test in flaky.iter() {
+        println!("  {:>6}  {} of {} flipped  {}{}",
+            format!("{:.1}%", test.rate() * 100.0).yellow().bold(),
+            test.flips,
+            test.comparisons,
+            test.identity,
+            if flaky_conf.run.quarantine.contains(&test.identity) {
+                " (quarantined)".dimmed()
+            } else {
+                "".normal()
+            }
+        );
+    }
+
+    if !flaky_conf.quarantine {
+        return Ok(warnings)
+    }
+
+    let Some(config_path) = flaky_conf.run.config_path.as_ref() else {
+        return error!{
+            debug: debuginfo!(),
+            description: "no config to quarantine the flaky tests in".to_owned(),
+            example: None,
+            note: vec![
+                "the tests are quarantined through the `quarantine` list of the config.".to_owned(),
+                "create a config with `cesty init <toml/yaml>` or pass one with -C / --config.".to_owned()
+            ]
+        }
+    };
+
+    let identities: Vec<String> = flaky.into_iter().map(|test| test.identity).collect();
+    let added = quarantine(config_path, &identities)?;
+
+    println!("\nquarantined {added} tests in `{}`", config_path.to_string_lossy());
+
+    Ok(warnings)
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use indoc::indoc;
+
+    use crate::test::{
+        history::{RunRecord, TestRecord},
+        run::Outcome
+    };
+
+    use super::{detect, quarantine_yaml};
+
+    /// A run of `tests`, identity, outcome & fingerprint.
+    fn run(tests: &[(&str, Outcome, &str)]) -> RunRecord {
+        RunRecord {
+            id: String::new(),
+            finished: 0,
+            commit: None,
+            seed: None,
+            tests: tests
+                .iter()
+                .map(|(identity, outcome, fingerprint)| TestRecord {
+                    identity: identity.to_string(),
+                    outcome: *outcome,
+                    duration: 0.0,
+                    fingerprint: Some(fingerprint.to_string())
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn ranks_by_flip_rate() {
+
+        use Outcome::*;
+
+        // Newest first.
+        let records = [
+            run(&[("ds.c::cesty_a", Passed, "1"), ("ds.c::cesty_b", Passed, "1"), ("ds.c::cesty_c", Passed, "1")]),
+            run(&[("ds.c::cesty_a", Failed, "1"), ("ds.c::cesty_b", Passed, "1"), ("ds.c::cesty_c", Passed, "1")]),
+            run(&[("ds.c::cesty_a", Passed, "1"), ("ds.c::cesty_b", Passed, "1"), ("ds.c::cesty_c", Passed, "1")]),
+            run(&[("ds.c::cesty_a", Passed, "1"), ("ds.c::cesty_b", Failed, "1"), ("ds.c::cesty_c", Passed, "1")])
+        ];
+
+        let flaky = detect(&records, 10);
+
+        assert_eq!(flaky.len(), 2);
+
+        assert_eq!(flaky[0].identity, "ds.c::cesty_a");
+        assert_eq!((flaky[0].flips, flaky[0].comparisons), (2, 3));
+
+        assert_eq!(flaky[1].identity, "ds.c::cesty_b");
+        assert_eq!((flaky[1].flips, flaky[1].comparisons), (1, 3));
+        assert!((flaky[1].rate() - 1.0 / 3.0).abs() < 1e-9);
+
+    }
+
+    #[test]
+    fn only_compares_the_same_binary() {
+
+        use Outcome::*;
+
+        // Failed on an old binary & was fixed, not flaky.
+        let records = [
+            run(&[("ds.c::cesty_a", Passed, "2")]),
+            run(&[("ds.c::cesty_a", Passed, "2")]),
+            run(&[("ds.c::cesty_a", Failed, "1")]),
+            run(&[("ds.c::cesty_a", Failed, "1")])
+        ];
+
+        assert!(detect(&records, 10).is_empty());
+
+    }
+
+    #[test]
+    fn retries_and_skips() {
+
+        use Outcome::*;
+
+        let records = [
+            run(&[("ds.c::cesty_a", Passed, "1"), ("ds.c::cesty_b", Passed, "1")]),
+            run(&[("ds.c::cesty_a", Flaky, "1"), ("ds.c::cesty_b", Skipped, "1")]),
+            run(&[("ds.c::cesty_a", Passed, "1"), ("ds.c::cesty_b", Failed, "1")])
+        ];
+
+        let flaky = detect(&records, 10);
+
+        // Passing on a retry is a flip of its own, skipped runs are left out.
+        assert_eq!(flaky.len(), 2);
+        assert_eq!(flaky[0].identity, "ds.c::cesty_b");
+        assert_eq!((flaky[0].flips, flaky[0].comparisons), (1, 1));
+        assert_eq!(flaky[1].identity, "ds.c::cesty_a");
+        assert_eq!((flaky[1].flips, flaky[1].comparisons), (1, 3));
+
+    }
+
+    #[test]
+    fn looks_at_the_newest_runs_only() {
+
+        use Outcome::*;
+
+        let records = [
+            run(&[("ds.c::cesty_a", Passed, "1")]),
+            run(&[("ds.c::cesty_a", Passed, "1")]),
+            run(&[("ds.c::cesty_a", Failed, "1")])
+        ];
+
+        assert!(detect(&records, 2).is_empty());
+        assert_eq!(detect(&records, 3).len(), 1);
+
+    }
+
+    #[test]
+    fn records_without_fingerprints_are_skipped() {
+
+        let mut records = [
+            run(&[("ds.c::cesty_a", Outcome::Passed, "1")]),
+            run(&[("ds.c::cesty_a", Outcome::Failed, "1")])
+        ];
+        records[0].tests[0].fingerprint = None;
+
+        assert!(detect(&records, 10).is_empty());
+
+    }
+
+    #[test]
+    fn appends_to_a_yaml_block_list() {
+
+        let contents = indoc!{"
+            # Tests that are known to be flaky.
+            quarantine:
+                - ds.c::cesty_a # races the timer
+                # - ds.c::cesty_old
+
+            # Compiler settings.
+            compiler:
+              name: gcc
+        "};
+
+        let updated = quarantine_yaml(contents, &["ds.c::cesty_b".to_owned()]).unwrap();
+
+        assert_eq!(updated, indoc!{"
+            # Tests that are known to be flaky.
+            quarantine:
+                - ds.c::cesty_a # races the timer
+                - ds.c::cesty_b
+                # - ds.c::cesty_old
+
+            # Compiler settings.
+            compiler:
+              name: gcc
+        "});
+
+    }
+
+    #[test]
+    fn adds_a_missing_yaml_list() {
+
+        let updated = quarantine_yaml("valgrind: false # for now", &["ds.c::cesty_a".to_owned()]).unwrap();
+
+        assert_eq!(updated, "valgrind: false # for now\nquarantine:\n  - ds.c::cesty_a\n");
+
+        let updated = quarantine_yaml("quarantine: # none yet\nvalgrind: false\n", &["ds.c::cesty_a".to_owned()]).unwrap();
+
+        assert_eq!(updated, "quarantine: # none yet\n  - ds.c::cesty_a\nvalgrind: false\n");
+
+    }
+
+    #[test]
+    fn refuses_other_yaml_lists() {
+
+        assert!(quarantine_yaml("quarantine: [ds.c::cesty_a]\n", &["ds.c::cesty_b".to_owned()]).is_none());
+        assert!(quarantine_yaml("quarantine: ~\n", &["ds.c::cesty_b".to_owned()]).is_none());
+
+    }
+
+}
@@ -0,0 +1,295 @@
+//! Fuzz targets, tests named `<prefix>fuzz_*` accepting
+//! `(const uint8_t *data, size_t size)`, ran with libFuzzer
+//! through `cesty fuzz <target>`.
+//!
+//! Every target keeps its data inside of
+//! `.cesty/fuzz/<target>/`:
+//! - [FUZZ_CORPUS_DIRECTORY] - The corpus libFuzzer grows between runs.
+//! - [FUZZ_CRASH_DIRECTORY] - Inputs that crashed the target, `cesty run`
+//!   replays every one of them as a regression test under ASan. They're
+//!   never removed by cesty, delete an input once it's no longer needed.
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio}
+};
+
+use colored::Colorize;
+use indoc::formatdoc;
+
+use crate::{
+    arg_conf::Fuzz,
+    defaults::{
+        ASSERTION_HEADER_NAME,
+        DEFAULT_FUZZ_COMPILER,
+        FUZZ_CORPUS_DIRECTORY,
+        FUZZ_CRASH_DIRECTORY,
+        FUZZ_DIRECTORY,
+        FUZZ_NAME_PREFIX
+    },
+    error::{
+        debuginfo, debugpush, error, function_message,
+        warning, Alert, AlertInfo
+    }
+};
+
+use super::{
+    batch::TestBatchFolder,
+    compilable::ResolvedCompiler,
+    coverage::Toolchain,
+    extract::{ParsedFile, ParsedTest}
+};
+
+/// Directory holding the corpus & crashes of `target`.
+pub fn target_directory(private_path: &Path, target: &str) -> PathBuf {
+
+    private_path.join(FUZZ_DIRECTORY).join(target)
+
+}
+
+/// Inputs that crashed `target`, sorted by name.
+pub fn crash_inputs(private_path: &Path, target: &str) -> Vec<PathBuf> {
+
+    let Ok(entries) = std::fs::read_dir(target_directory(private_path, target).join(FUZZ_CRASH_DIRECTORY)) else {
+        return vec![]
+    };
+
+    let mut inputs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+
+    inputs.sort();
+    inputs
+
+}
+
+/// Does `name` select the fuzz target `test`, either its
+/// full name or the name without the prefix.
+fn selects(test: &ParsedTest, name: &str) -> bool {
+
+    test.function.name == name
+        || test.function.name_slice == name
+        || test.function.name_slice.strip_prefix(FUZZ_NAME_PREFIX) == Some(name)
+
+}
+
+/// Source of the libFuzzer harness, `-1` returned by the target
+/// rejects the input from the corpus, anything else keeps it.
+fn create_harness(target: &ParsedTest, parsed_file: &ParsedFile) -> String {
+
+    formatdoc!{
+        "
+            #include \"{ASSERTION_HEADER_NAME}\"
+            #line 1 \"{origin}\"
+            {env}
+
+            int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size) {{
+                return {name}(data, size) == -1 ? -1 : 0;
+            }}
+        ",
+        origin = parsed_file.path.to_string_lossy().replace('\\', "\\\\"),
+        env    = parsed_file.environment.mainless,
+        name   = target.function.name
+    }
+
+}
+
+/// Finds the target selected by `fuzz_conf`, compiles it with libFuzzer
+/// & the address sanitizer and runs it on its corpus.
+///
+/// Returns an error if the target isn't found, is ambiguous, fails to
+/// compile or crashes.
+pub fn fuzz(fuzz_conf: &Fuzz) -> Result<Vec<Alert>, Alert> {
+
+    let run_conf = &fuzz_conf.run;
+
+    let ((list, recipe), mut warnings) = match crate::lister::list(run_conf) {
+        Ok(listed) => listed,
+        Err(err) => return Err(debugpush!(err))
+    };
+
+    let mut available: Vec<String> = vec![];
+    let mut found: Vec<(ParsedFile, ParsedTest)> = vec![];
+
+    for path in list {
+
+        let parsed_file = match super::extract::extract(path, run_conf, recipe) {
+            Ok((parsed_file, mut extract_warnings)) => {
+                warnings.append(&mut extract_warnings);
+                parsed_file
+            }
+            Err(err) => return Err(debugpush!(err))
+        };
+
+        for target in parsed_file.fuzz.iter() {
+            available.push(target.function.name.clone());
+            if selects(target, &fuzz_conf.target) {
+                found.push((parsed_file.clone(), target.clone()));
+            }
+        }
+
+    }
+
+    let (parsed_file, target) = match found.len() {
+        1 => found.remove(0),
+        0 => return error!{
+            debug: debuginfo!(),
+            description: format!("fuzz target `{}` wasn't found", fuzz_conf.target),
+            example: None,
+            note: if available.is_empty() {
+                vec![format!("no fuzz targets were found, name a function `<prefix>{FUZZ_NAME_PREFIX}<name>` to make it one.")]
+            } else {
+                vec![format!("available fuzz targets: {}", available.join(", "))]
+            }
+        },
+        _ => return error!{
+            debug: debuginfo!(),
+            description: format!("fuzz target `{}` is ambiguous", fuzz_conf.target),
+            example: None,
+            note: found
+                .iter()
+                .map(|(file, target)| format!("`{}` inside of `{}`", target.function.name, file.path.to_string_lossy()))
+                .collect()
+        }
+    };
+
+    let batch_folder = TestBatchFolder::new(run_conf)?;
+
+    let directory = target_directory(batch_folder.private_path(), &target.function.name);
+    let (corpus, crashes) = (directory.join(FUZZ_CORPUS_DIRECTORY), directory.join(FUZZ_CRASH_DIRECTORY));
+
+    for path in [&corpus, &crashes] {
+        if let Err(err) = std::fs::create_dir_all(path) {
+            return error!{
+                debug: debuginfo!(),
+                description: format!("failed to create directory `{}` for fuzz target `{}`",
+                    path.to_string_lossy(), target.function.name),
+                example: None,
+                note: function_message!("std::fs::create_dir_all()", err.to_string())
+            }
+        }
+    }
+
+    let source = batch_folder.path().join(format!("{}.c", target.function.name));
+    let binary = source.with_extension("out");
+
+    if let Err(err) = std::fs::write(&source, create_harness(&target, &parsed_file)) {
+        return error!{
+            debug: debuginfo!(),
+            description: format!("failed to write fuzz harness `{}`", source.to_string_lossy()),
+            example: None,
+            note: function_message!("std::fs::write()", err.to_string())
+        }
+    }
+
+    let compiler = ResolvedCompiler::resolve(run_conf, recipe, &parsed_file.config, &target.config, true);
+
+    let compiler_name = if Toolchain::from_compiler(&compiler.name) == Toolchain::Llvm {
+        compiler.name.clone()
+    } else {
+        warnings.push(warning!{
+            debug: debuginfo!(),
+            description: format!("compiling fuzz target `{}` with `{DEFAULT_FUZZ_COMPILER}` instead of `{}`",
+                target.function.name, compiler.name),
+            example: None,
+            note: vec![
+                "libFuzzer is only available in clang.".to_owned()
+            ]
+        });
+        DEFAULT_FUZZ_COMPILER.to_owned()
+    };
+
+    let output = match Command::new(&compiler_name)
+        .args(&compiler.flags)
+        .args(["-g", "-O1", "-fsanitize=fuzzer,address"])
+        .arg(format!("-I{}", batch_folder.include_path().to_string_lossy()))
+        .arg(&source)
+        .arg("-o")
+        .arg(&binary)
+        .args(&compiler.libraries)
+        .output()
+    {
+        Ok(output) => output,
+        Err(err) => return error!{
+            debug: debuginfo!(),
+            description: format!("failed to run compiler `{compiler_name}` for fuzz target `{}`", target.function.name),
+            example: None,
+            note: function_message!("std::process::Command::output()", err.to_string())
+        }
+    };
+
+    if !output.status.success() {
+        let mut note = vec![
+            format!("compiled `{}` from `{}`", source.to_string_lossy(), parsed_file.path.to_string_lossy()),
+            "the compiler returned the following:".to_owned()
+        ];
+        note.extend(String::from_utf8_lossy(&output.stderr).lines().map(String::from));
+        return error!{
+            debug: debuginfo!(),
+            description: format!("failed to compile fuzz target `{}`", target.function.name),
+            example: None,
+            note: note
+        }
+    }
+
+    for warning in warnings.drain(..) {
+        eprintln!("{warning}");
+    }
+
+    let known_crashes = crash_inputs(batch_folder.private_path(), &target.function.name);
+
+    println!("{} {} ... corpus `{}`", "fuzzing".bold(), target.function.name, corpus.to_string_lossy());
+
+    let status = match Command::new(&binary)
+        .arg(&corpus)
+        .arg(format!("-artifact_prefix={}/", crashes.to_string_lossy()))
+        .args(fuzz_conf.max_time.map(|seconds| format!("-max_total_time={seconds}")))
+        .stdin(Stdio::null())
+        .status()
+    {
+        Ok(status) => status,
+        Err(err) => return error!{
+            debug: debuginfo!(),
+            description: format!("failed to run fuzz target `{}`", target.function.name),
+            example: None,
+            note: function_message!("std::process::Command::status()", err.to_string())
+        }
+    };
+
+    if status.success() {
+        println!("fuzz target {} ... {}", target.function.name, "ok".green().bold());
+        return Ok(warnings)
+    }
+
+    let new_crashes: Vec<PathBuf> = crash_inputs(batch_folder.private_path(), &target.function.name)
+        .into_iter()
+        .filter(|input| !known_crashes.contains(input))
+        .collect();
+
+    let mut note: Vec<String> = new_crashes
+        .iter()
+        .map(|input| format!("input saved to `{}`", input.to_string_lossy()))
+        .collect();
+
+    if note.is_empty() {
+        note.push(match status.code() {
+            Some(code) => format!("libFuzzer exited with code {code} without saving an input."),
+            None       => "libFuzzer was killed by a signal without saving an input.".to_owned()
+        });
+    } else {
+        note.extend([
+            "`cesty run` replays every saved input as a regression test on each run.".to_owned(),
+            format!("the inputs are kept until removed from `{}`.", crashes.to_string_lossy())
+        ]);
+    }
+
+    error!{
+        debug: debuginfo!(),
+        description: format!("fuzz target `{}` crashed", target.function.name),
+        example: None,
+        note: note
+    }
+
+}
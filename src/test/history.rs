@@ -0,0 +1,749 @@
+//! Results of past runs stored inside of [HISTORY_DIRECTORY], one
+//! [RunRecord] per run named after its batch.
+//!
+//! Comparing
+//! ---------
+//! `cesty history` lists the stored runs newest first, numbered from 1.
+//! `cesty diff [run-a] [run-b]` compares two of them, selected by their
+//! number, id or a unique start of the id, by default the previous run
+//! against the latest one.
+//!
+//! Every `cesty run` compares itself against the run before it & lists
+//! the tests that stopped passing below the summary.
+//!
+//! Re-running
+//! ----------
+//! `--failed` only runs the tests that did not pass in the latest run
+//! & `--failed-first` runs them before the rest, see [Rerun]. Replayed
+//! fuzz crashes are looked up by their target.
+//!
+//! Only the newest [MAX_HISTORY_RUNS] runs are kept.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    time::SystemTime
+};
+
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arg_conf::{Diff, History, Run},
+    defaults::{
+        HISTORY_DIRECTORY,
+        MAX_HISTORY_RUNS
+    },
+    error::{
+        debuginfo, debugpush, error, function_message,
+        warning, Alert, AlertInfo
+    }
+};
+
+use super::{
+    extract::ParsedFile,
+    run::{Outcome, TestResult}
+};
+
+/// Stored result of a single test.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TestRecord {
+
+    /// See [TestResult::identity].
+    pub identity: String,
+
+    pub outcome: Outcome,
+
+    /// Seconds the test took.
+    pub duration: f64,
+
+    /// See [TestResult::fingerprint].
+    #[serde(default)]
+    pub fingerprint: Option<String>
+
+}
+
+/// Stored results of a single run.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RunRecord {
+
+    /// Name of the batch of the run.
+    pub id: String,
+
+    /// Unix time in milliseconds the run finished at.
+    pub finished: u64,
+
+    /// `HEAD` of the git repository the run was started in.
+    #[serde(default)]
+    pub commit: Option<String>,
+
+    /// See [Run::seed].
+    #[serde(default)]
+    pub seed: Option<u64>,
+
+    #[serde(default)]
+    pub tests: Vec<TestRecord>
+
+}
+
+/// Differences between an older & a newer [RunRecord].
+#[derive(Clone, Debug, Default)]
+pub struct RunDiff {
+
+    /// Tests that passed before but not anymore,
+    /// along with their outcome before & after.
+    pub newly_failing: Vec<(String, Outcome, Outcome)>,
+
+    /// Tests that didn't pass before but do now,
+    /// along with their outcome before & after.
+    pub newly_passing: Vec<(String, Outcome, Outcome)>,
+
+    /// Tests only inside of the newer run.
+    pub added: Vec<(String, Outcome)>,
+
+    /// Tests only inside of the older run.
+    pub removed: Vec<(String, Outcome)>
+
+}
+
+/// Tests that did not pass in the latest recorded run,
+/// see [Run::failed] & [Run::failed_first].
+#[derive(Clone, Debug)]
+pub struct Rerun {
+
+    /// Id of the run the tests failed in.
+    pub id: String,
+
+    /// Identities of the failed tests & whether they
+    /// were found inside of any parsed file.
+    failed: Vec<(String, bool)>
+
+}
+
+/// The [HISTORY_DIRECTORY] of `config`.
+pub fn history_path(config: &Run) -> PathBuf {
+
+    super::batch::private_path(config).join(HISTORY_DIRECTORY)
+
+}
+
+fn record_path(history_path: &Path, id: &str) -> PathBuf {
+
+    history_path.join(format!("{id}.toml"))
+
+}
+
+/// `HEAD` of the git repository inside of the current pwd, [None]
+/// outside of a repository or without git.
+fn git_commit() -> Option<String> {
+
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+
+}
+
+/// Local time of the unix time in milliseconds `finished`.
+fn format_time(finished: u64) -> String {
+
+    let utc = time::OffsetDateTime::UNIX_EPOCH + time::Duration::milliseconds(finished as i64);
+    let local = utc.to_offset(time::UtcOffset::local_offset_at(utc).unwrap_or(time::UtcOffset::UTC));
+
+    format!("{}-{:0>2}-{:0>2} {:0>2}:{:0>2}:{:0>2}",
+        local.year(),
+        local.month() as u8,
+        local.day(),
+        local.hour(),
+        local.minute(),
+        local.second()
+    )
+
+}
+
+/// Every run stored inside of `history_path`, newest first.
+/// Records that can't be read are returned as warnings.
+pub fn records(history_path: &Path) -> (Vec<RunRecord>, Vec<Alert>) {
+
+    let mut warnings: Vec<Alert> = vec![];
+
+    let Ok(read_dir) = std::fs::read_dir(history_path) else {
+        return (vec![], warnings)
+    };
+
+    let mut records: Vec<RunRecord> = read_dir
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "toml"))
+        .filter_map(|path| {
+            match std::fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|contents| toml::from_str::<RunRecord>(&contents).map_err(|err| err.message().to_owned()))
+            {
+                Ok(record) => Some(record),
+                Err(err) => {
+                    warnings.push(warning!{
+                        debug: debuginfo!(),
+                        description: format!("failed to read the run record `{}`", path.to_string_lossy()),
+                        example: None,
+                        note: vec![err, "it's left out of the history, remove it if it's corrupted.".to_owned()]
+                    });
+                    None
+                }
+            }
+        })
+        .collect();
+
+    records.sort_by(|a, b| b.finished.cmp(&a.finished).then_with(|| b.id.cmp(&a.id)));
+
+    (records, warnings)
+
+}
+
+fn save(history_path: &Path, record: &RunRecord) -> Result<(), Alert> {
+
+    let contents = match toml::to_string(record) {
+        Ok(contents) => contents,
+        Err(err) => return error!{
+            debug: debuginfo!(),
+            description: format!("failed to serialize the run record `{}`", record.id),
+            example: None,
+            note: function_message!("toml::to_string()", err.to_string())
+        }
+    };
+
+    let path = record_path(history_path, &record.id);
+
+    match std::fs::write(&path, contents) {
+        Ok(_) => Ok(()),
+        Err(err) => error!{
+            debug: debuginfo!(),
+            description: format!("failed to write the run record `{}`", path.to_string_lossy()),
+            example: None,
+            note: function_message!("std::fs::write()", err.to_string())
+        }
+    }
+
+}
+
+/// The run of `records` (newest first) selected by `selector`,
+/// its number inside of `cesty history`, its id or a unique start of it.
+fn select<'a>(records: &'a [RunRecord], selector: &str) -> Result<&'a RunRecord, Alert> {
+
+    if let Ok(number) = selector.parse::<usize>() {
+        if let Some(record) = number.checked_sub(1).and_then(|index| records.get(index)) {
+            return Ok(record)
+        }
+    }
+
+    if let Some(record) = records.iter().find(|record| record.id == selector) {
+        return Ok(record)
+    }
+
+    let matching: Vec<&RunRecord> = records
+        .iter()
+        .filter(|record| record.id.starts_with(selector))
+        .collect();
+
+    match matching.as_slice() {
+        [record] => Ok(record),
+        [] => error!{
+            debug: debuginfo!(),
+            description: format!("no run matches `{selector}`"),
+            example: None,
+            note: vec![
+                format!("there are {} stored runs, list them with `cesty history`.", records.len())
+            ]
+        },
+        _ => error!{
+            debug: debuginfo!(),
+            description: format!("`{selector}` matches {} runs", matching.len()),
+            example: None,
+            note: matching
+                .iter()
+                .map(|record| format!("`{}` matches.", record.id))
+                .collect()
+        }
+    }
+
+}
+
+impl RunRecord {
+
+    /// Record of the run of the batch `id` with `results`.
+    pub fn new(id: &str, results: &[TestResult], config: &Run) -> Self {
+
+        RunRecord {
+            id: id.to_owned(),
+            finished: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or(0),
+            commit: git_commit(),
+            seed: config.seed,
+            tests: results
+                .iter()
+                .map(|result| TestRecord {
+                    identity: result.identity(),
+                    outcome:  result.outcome,
+                    duration: result.duration.as_secs_f64(),
+                    fingerprint: result.fingerprint.clone()
+                })
+                .collect()
+        }
+
+    }
+
+    fn find(&self, identity: &str) -> Option<&TestRecord> {
+
+        self.tests.iter().find(|test| test.identity == identity)
+
+    }
+
+}
+
+impl Rerun {
+
+    /// The failed tests of the latest run recorded for `config`.
+    pub fn latest(config: &Run) -> Result<(Self, Vec<Alert>), Alert> {
+
+        let history_path = history_path(config);
+        let (records, warnings) = records(&history_path);
+
+        let Some(latest) = records.first() else {
+            return error!{
+                debug: debuginfo!(),
+                description: "no run to re-run the failed tests of".to_owned(),
+                example: None,
+                note: vec![
+                    format!("no runs are recorded inside of `{}` yet.", history_path.to_string_lossy()),
+                    "run the tests once without `--failed` or `--failed-first`.".to_owned()
+                ]
+            }
+        };
+
+        let mut failed: Vec<(String, bool)> = vec![];
+
+        for test in latest.tests.iter().filter(|test| test.outcome.unsuccessful()) {
+            // Replayed fuzz crashes are named `<target>[<input>]`.
+            let identity = match test.identity.rsplit_once("::") {
+                Some((file, name)) => format!("{file}::{}", name.split('[').next().unwrap_or(name)),
+                None => test.identity.clone()
+            };
+            if !failed.iter().any(|(known, _)| *known == identity) {
+                failed.push((identity, false));
+            }
+        }
+
+        Ok((Rerun {
+            id: latest.id.clone(),
+            failed
+        }, warnings))
+
+    }
+
+    pub fn is_empty(&self) -> bool {
+
+        self.failed.is_empty()
+
+    }
+
+    /// Did the test `identity` fail, see [super::run::identity].
+    pub fn contains(&self, identity: &str) -> bool {
+
+        self.failed.iter().any(|(failed, _)| failed == identity)
+
+    }
+
+    /// Did any test inside of `file` fail.
+    pub fn contains_file(&self, file: &Path) -> bool {
+
+        let prefix = super::run::identity(file, "");
+
+        self.failed.iter().any(|(failed, _)| failed.starts_with(&prefix))
+
+    }
+
+    /// Marks the failed tests of `parsed_file` as found & removes
+    /// all the other tests from it if `only` is set. The setup &
+    /// teardown fixtures are found as well, they run with any test.
+    pub fn select(&mut self, parsed_file: &mut ParsedFile, only: bool) {
+
+        let path = parsed_file.path.clone();
+
+        for fixture in [&parsed_file.fixtures.setup, &parsed_file.fixtures.teardown].into_iter().flatten() {
+            let identity = super::run::identity(&path, &fixture.function.name);
+            for (failed, found) in self.failed.iter_mut() {
+                *found |= *failed == identity;
+            }
+        }
+
+        for tests in [&mut parsed_file.test, &mut parsed_file.bench, &mut parsed_file.fuzz] {
+            tests.retain(|test| {
+                let identity = super::run::identity(&path, &test.function.name);
+                let mut failed = false;
+                for (known, found) in self.failed.iter_mut() {
+                    if *known == identity {
+                        *found = true;
+                        failed = true;
+                    }
+                }
+                failed || !only
+            });
+        }
+
+    }
+
+    /// Warnings for the failed tests that weren't found by [Rerun::select].
+    pub fn missing(&self) -> Vec<Alert> {
+
+        self.failed
+            .iter()
+            .filter(|(_, found)| !found)
+            .map(|(identity, _)| warning!{
+                debug: debuginfo!(),
+                description: format!("test `{identity}` failed in `{}` but no longer exists", self.id),
+                example: None,
+                note: vec![
+                    "it was renamed, removed or its file isn't parsed by this recipe anymore.".to_owned()
+                ]
+            })
+            .collect()
+
+    }
+
+}
+
+impl RunDiff {
+
+    /// Compares the `before` run against the `after` run, skipped
+    /// tests are neither failing nor passing.
+    pub fn between(before: &RunRecord, after: &RunRecord) -> Self {
+
+        let mut diff = RunDiff::default();
+
+        for test in after.tests.iter() {
+
+            let Some(old) = before.find(&test.identity) else {
+                diff.added.push((test.identity.clone(), test.outcome));
+                continue
+            };
+
+            let entry = (test.identity.clone(), old.outcome, test.outcome);
+
+            if test.outcome.unsuccessful() && !old.outcome.unsuccessful() && old.outcome != Outcome::Skipped {
+                diff.newly_failing.push(entry);
+            } else if old.outcome.unsuccessful() && !test.outcome.unsuccessful() && test.outcome != Outcome::Skipped {
+                diff.newly_passing.push(entry);
+            }
+
+        }
+
+        for test in before.tests.iter() {
+            if after.find(&test.identity).is_none() {
+                diff.removed.push((test.identity.clone(), test.outcome));
+            }
+        }
+
+        diff
+
+    }
+
+    pub fn is_empty(&self) -> bool {
+
+        self.newly_failing.is_empty()
+        && self.newly_passing.is_empty()
+        && self.added.is_empty()
+        && self.removed.is_empty()
+
+    }
+
+}
+
+/// Records the run of the batch `batch` with `results` & lists the
+/// tests that stopped passing since the previous run. The history never
+/// fails a run, anything going wrong is returned as a warning.
+pub fn finish(results: &[TestResult], config: &Run, batch: &Path) -> Vec<Alert> {
+
+    let history_path = history_path(config);
+    let id = batch.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+    if let Err(err) = std::fs::create_dir_all(&history_path) {
+        return vec![warning!{
+            debug: debuginfo!(),
+            description: format!("failed to create the history folder `{}`", history_path.to_string_lossy()),
+            example: None,
+            note: function_message!("std::fs::create_dir_all()", err.to_string())
+        }]
+    }
+
+    let _lock = match super::lock::history(&history_path, config.wait) {
+        Ok(lock) => lock,
+        Err(err) => return vec![debugpush!(err)]
+    };
+
+    let (mut records, mut warnings) = records(&history_path);
+    let record = RunRecord::new(&id, results, config);
+
+    if let Err(err) = save(&history_path, &record) {
+        warnings.push(err);
+        return warnings
+    }
+
+    if records.len() >= MAX_HISTORY_RUNS {
+        for old in records.drain(MAX_HISTORY_RUNS - 1..) {
+            _ = std::fs::remove_file(record_path(&history_path, &old.id));
+        }
+    }
+
+    let Some(previous) = records.first() else {
+        return warnings
+    };
+
+    let mut diff = RunDiff::between(previous, &record);
+    diff.newly_failing.retain(|(identity, _, _)| !config.quarantine.contains(identity));
+
+    if !diff.newly_failing.is_empty() {
+        println!("\n{}", format!("regressions since `{}`:", previous.id).red().bold());
+        for (identity, before, after) in diff.newly_failing.iter() {
+            println!("    {identity} ({before} -> {after})");
+        }
+    }
+
+    warnings
+
+}
+
+impl std::fmt::Display for RunRecord {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        let unsuccessful = self.tests.iter().filter(|test| test.outcome.unsuccessful()).count();
+
+        write!(f, "{}  {}  {}  {} tests, ",
+            self.id,
+            format_time(self.finished),
+            self.commit
+                .as_deref()
+                .map(|commit| &commit[..commit.len().min(10)])
+                .unwrap_or("-")
+                .dimmed(),
+            self.tests.len()
+        )?;
+
+        match unsuccessful {
+            0 => write!(f, "{}", "ok".green().bold()),
+            _ => write!(f, "{}", format!("{unsuccessful} did not pass").red().bold())
+        }
+
+    }
+
+}
+
+impl std::fmt::Display for RunDiff {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        if self.is_empty() {
+            return write!(f, "no differences")
+        }
+
+        let mut first = true;
+
+        for (title, changes) in [
+            ("newly failing".red().bold(), &self.newly_failing),
+            ("newly passing".green().bold(), &self.newly_passing)
+        ] {
+            if changes.is_empty() {
+                continue
+            }
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+            write!(f, "{title}:")?;
+            for (identity, before, after) in changes.iter() {
+                write!(f, "\n    {identity} ({before} -> {after})")?;
+            }
+        }
+
+        for (title, tests) in [
+            ("new tests".bold(), &self.added),
+            ("removed tests".bold(), &self.removed)
+        ] {
+            if tests.is_empty() {
+                continue
+            }
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+            write!(f, "{title}:")?;
+            for (identity, outcome) in tests.iter() {
+                write!(f, "\n    {identity} ({outcome})")?;
+            }
+        }
+
+        Ok(())
+
+    }
+
+}
+
+/// Runs "cesty history".
+pub fn history(history_conf: &History) -> Result<Vec<Alert>, Alert> {
+
+    let (records, warnings) = records(&history_path(&history_conf.run));
+
+    if records.is_empty() {
+        println!("no runs recorded yet inside of `{}`", history_path(&history_conf.run).to_string_lossy());
+        return Ok(warnings)
+    }
+
+    for (number, record) in records.iter().enumerate() {
+        println!("{:>3}  {record}", number + 1);
+    }
+
+    Ok(warnings)
+
+}
+
+/// Runs "cesty diff ...".
+pub fn diff(diff_conf: &Diff) -> Result<Vec<Alert>, Alert> {
+
+    let (records, warnings) = records(&history_path(&diff_conf.run));
+
+    if diff_conf.run_b.is_none() && records.len() < 2 {
+        return error!{
+            debug: debuginfo!(),
+            description: format!("nothing to compare, {} runs are recorded", records.len()),
+            example: None,
+            note: vec![
+                "`cesty diff` compares the latest run against the one before it by default.".to_owned()
+            ]
+        }
+    }
+
+    let (before, after) = match (diff_conf.run_a.as_deref(), diff_conf.run_b.as_deref()) {
+        (Some(a), Some(b)) => (select(&records, a)?, select(&records, b)?),
+        (Some(a), None)    => (select(&records, a)?, &records[0]),
+        (None, _)          => (&records[1], &records[0])
+    };
+
+    println!("{}\n{}\n", before, after);
+    println!("{}", RunDiff::between(before, after));
+
+    Ok(warnings)
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::test::run::Outcome;
+
+    use super::{RunDiff, RunRecord, TestRecord};
+
+    fn run(id: &str, tests: &[(&str, Outcome)]) -> RunRecord {
+        RunRecord {
+            id: id.to_owned(),
+            finished: 0,
+            commit: None,
+            seed: None,
+            tests: tests
+                .iter()
+                .map(|(identity, outcome)| TestRecord {
+                    identity: identity.to_string(),
+                    outcome: *outcome,
+                    duration: 0.0,
+                    fingerprint: None
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn finds_regressions_and_fixes() {
+
+        let before = run("a", &[
+            ("ds.c::cesty_a", Outcome::Passed),
+            ("ds.c::cesty_b", Outcome::Crashed),
+            ("ds.c::cesty_c", Outcome::Flaky),
+            ("ds.c::cesty_d", Outcome::ExpectedFailure),
+            ("ds.c::cesty_e", Outcome::Failed)
+        ]);
+        let after = run("b", &[
+            ("ds.c::cesty_a", Outcome::Failed),
+            ("ds.c::cesty_b", Outcome::Passed),
+            ("ds.c::cesty_c", Outcome::CompileError),
+            ("ds.c::cesty_d", Outcome::UnexpectedPass),
+            ("ds.c::cesty_e", Outcome::Failed)
+        ]);
+
+        let diff = RunDiff::between(&before, &after);
+
+        assert_eq!(diff.newly_failing, vec![
+            ("ds.c::cesty_a".to_owned(), Outcome::Passed, Outcome::Failed),
+            ("ds.c::cesty_c".to_owned(), Outcome::Flaky, Outcome::CompileError),
+            ("ds.c::cesty_d".to_owned(), Outcome::ExpectedFailure, Outcome::UnexpectedPass)
+        ]);
+        assert_eq!(diff.newly_passing, vec![
+            ("ds.c::cesty_b".to_owned(), Outcome::Crashed, Outcome::Passed)
+        ]);
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+
+    }
+
+    #[test]
+    fn skipped_tests_neither_fail_nor_pass() {
+
+        let before = run("a", &[
+            ("ds.c::cesty_a", Outcome::Skipped),
+            ("ds.c::cesty_b", Outcome::Failed)
+        ]);
+        let after = run("b", &[
+            ("ds.c::cesty_a", Outcome::Failed),
+            ("ds.c::cesty_b", Outcome::Skipped)
+        ]);
+
+        assert!(RunDiff::between(&before, &after).is_empty());
+
+    }
+
+    #[test]
+    fn finds_added_and_removed_tests() {
+
+        let before = run("a", &[
+            ("ds.c::cesty_a", Outcome::Passed),
+            ("ds.c::cesty_old", Outcome::Failed)
+        ]);
+        let after = run("b", &[
+            ("ds.c::cesty_new", Outcome::Crashed),
+            ("ds.c::cesty_a", Outcome::Passed)
+        ]);
+
+        let diff = RunDiff::between(&before, &after);
+
+        assert!(diff.newly_failing.is_empty());
+        assert!(diff.newly_passing.is_empty());
+        assert_eq!(diff.added, vec![("ds.c::cesty_new".to_owned(), Outcome::Crashed)]);
+        assert_eq!(diff.removed, vec![("ds.c::cesty_old".to_owned(), Outcome::Failed)]);
+        assert!(!diff.is_empty());
+
+    }
+
+    #[test]
+    fn identical_runs_have_no_differences() {
+
+        let tests = [("ds.c::cesty_a", Outcome::Passed), ("ds.c::cesty_b", Outcome::Failed)];
+
+        assert!(RunDiff::between(&run("a", &tests), &run("b", &tests)).is_empty());
+        assert!(RunDiff::between(&run("a", &[]), &run("b", &[])).is_empty());
+
+    }
+
+}
@@ -0,0 +1,177 @@
+//! Advisory locks on files inside of `.cesty`, several cesty runs (two
+//! terminals, a watch process & a manual run...) can share the same
+//! directory.
+//!
+//! Locks
+//! -----
+//! - [BATCHES_LOCK_NAME] next to the batches, held while a batch is
+//!   created & while old batches are removed.
+//! - [BATCH_RUNNING_LOCK_NAME] inside of every batch, held by the run
+//!   for as long as it uses the batch, such that it isn't removed
+//!   from under it.
+//! - [CACHE_LOCK_NAME] inside of the cache, held while it's written to.
+//! - [HISTORY_LOCK_NAME] inside of the history, held while a run is
+//!   recorded.
+//!
+//! A lock held by another run is waited on for [LOCK_GRACE_PERIOD]
+//! milliseconds, after that it's an error unless `--wait` is set, in
+//! which case the run blocks until the lock is released.
+//!
+//! The process holding a lock writes its id into the locked file, the
+//! lock itself is released by the OS once the file is closed, even if
+//! the process is killed.
+
+use std::{
+    fs::{File, TryLockError},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, Instant}
+};
+
+use crate::{
+    defaults::{
+        BATCHES_LOCK_NAME,
+        BATCH_RUNNING_LOCK_NAME,
+        CACHE_LOCK_NAME,
+        HISTORY_LOCK_NAME,
+        LOCK_GRACE_PERIOD
+    },
+    error::{
+        debuginfo, error, function_message,
+        warning, Alert, AlertInfo
+    }
+};
+
+/// An exclusive lock on a file, released once dropped.
+#[derive(Debug)]
+pub struct FileLock {
+
+    /// The lock lives as long as the file is open.
+    _file: File
+
+}
+
+/// Who holds the lock on `path`, as written by [FileLock::acquire].
+fn holder(path: &Path) -> String {
+
+    match std::fs::read_to_string(path) {
+        Ok(contents) if !contents.trim().is_empty() => contents.trim().to_owned(),
+        _ => "an unknown process".to_owned()
+    }
+
+}
+
+impl FileLock {
+
+    /// Locks `path` (creating it) without waiting,
+    /// [None] if another process holds it.
+    pub fn try_acquire(path: &Path) -> Result<Option<Self>, std::io::Error> {
+
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        match file.try_lock() {
+            Ok(_) => (),
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Error(err)) => return Err(err)
+        }
+
+        // Only informs the other runs, nothing to be done if it fails.
+        _ = file.set_len(0);
+        _ = write!(file, "process {}", std::process::id());
+
+        Ok(Some(Self {
+            _file: file
+        }))
+
+    }
+
+    /// Locks `path` (creating it), waits for another run holding it for
+    /// [LOCK_GRACE_PERIOD] milliseconds or until it's released if `wait`.
+    pub fn acquire(path: &Path, wait: bool) -> Result<Self, Alert> {
+
+        let start = Instant::now();
+        let mut warned = false;
+
+        loop {
+
+            match Self::try_acquire(path) {
+                Ok(Some(lock)) => return Ok(lock),
+                Ok(None) => (),
+                Err(err) => return error!{
+                    debug: debuginfo!(),
+                    description: format!("failed to lock `{}`", path.to_string_lossy()),
+                    example: None,
+                    note: function_message!("std::fs::File::try_lock()", err.to_string())
+                }
+            }
+
+            let waited = start.elapsed();
+
+            if waited >= Duration::from_millis(LOCK_GRACE_PERIOD) {
+
+                if !wait {
+                    return error!{
+                        debug: debuginfo!(),
+                        description: format!("another cesty run is using `{}`", path.to_string_lossy()),
+                        example: None,
+                        note: vec![
+                            format!("the lock is held by {}.", holder(path)),
+                            "pass `--wait` to wait for it to finish instead.".to_owned()
+                        ]
+                    }
+                }
+
+                if !warned {
+                    warned = true;
+                    eprintln!("{}", warning!{
+                        debug: debuginfo!(),
+                        description: format!("waiting for another cesty run using `{}`", path.to_string_lossy()),
+                        example: None,
+                        note: vec![
+                            format!("the lock is held by {}.", holder(path))
+                        ]
+                    });
+                }
+
+            }
+
+            std::thread::sleep(Duration::from_millis(50));
+
+        }
+
+    }
+
+}
+
+/// The [BATCHES_LOCK_NAME] of the batches inside of `root`.
+pub fn batches(root: &Path, wait: bool) -> Result<FileLock, Alert> {
+
+    FileLock::acquire(&root.join(BATCHES_LOCK_NAME), wait)
+
+}
+
+/// The [BATCH_RUNNING_LOCK_NAME] of `batch`.
+pub fn running(batch: &Path) -> PathBuf {
+
+    batch.join(BATCH_RUNNING_LOCK_NAME)
+
+}
+
+/// The [CACHE_LOCK_NAME] of the cache at `cache`.
+pub fn cache(cache: &Path, wait: bool) -> Result<FileLock, Alert> {
+
+    FileLock::acquire(&cache.join(CACHE_LOCK_NAME), wait)
+
+}
+
+/// The [HISTORY_LOCK_NAME] of the history at `history`.
+pub fn history(history: &Path, wait: bool) -> Result<FileLock, Alert> {
+
+    FileLock::acquire(&history.join(HISTORY_LOCK_NAME), wait)
+
+}
@@ -4,9 +4,38 @@
 //! # Use 
 //! * [`extract`] - Used to extract tests, environments for tests
 //!                 and 
+//! * [`compilable`] - Creates & compiles a test harness for every test.
+//! * [`batch`] - The folder the harnesses of a single run are placed in.
+//! * [`run`] - Runs compiled tests & reports their results.
+//! * [`assert`] - The bundled `cesty.h` header & its assertion reports.
+//! * [`sanitize`] - Sanitizer flags, options & report parsing.
+//! * [`valgrind`] - Running tests under memcheck & parsing its XML.
+//! * [`coverage`] - Coverage instrumentation, collection & reports.
+//! * [`bench`] - Benchmark statistics, baselines & comparisons.
+//! * [`fuzz`] - libFuzzer harnesses, corpora & crash replays.
+//! * [`property`] - Generated inputs, property runs & shrinking.
+//! * [`diagnostic`] - Compiler diagnostics mapped back to the parsed files.
+//! * [`cache`] - Compiled harnesses reused between runs.
+//! * [`lock`] - Locks shared by the runs using the same `.cesty`.
+//! * [`history`] - Results of past runs & comparisons between them.
+//! * [`flaky`] - Flaky tests found from the history & their quarantine.
 
 pub mod extract;
 pub mod compilable;
+pub mod batch;
+pub mod run;
+pub mod assert;
+pub mod sanitize;
+pub mod valgrind;
+pub mod coverage;
+pub mod bench;
+pub mod fuzz;
+pub mod property;
+pub mod diagnostic;
+pub mod cache;
+pub mod lock;
+pub mod history;
+pub mod flaky;
 
 use serde::Deserialize;
 use crate::{
@@ -80,6 +109,12 @@ pub struct Compiler {
     #[serde(deserialize_with = "serde_tokenize_strings_and_vec")]
     #[serde(default = "Vec::new")]
     pub libraries: Vec<String>,
+
+    /// Object files/static libraries to link the test against when the
+    /// test isn't [Settings::standalone], appended to [crate::arg_conf::Recipe::objects].
+    #[serde(deserialize_with = "serde_tokenize_strings_and_vec")]
+    #[serde(default = "Vec::new")]
+    pub objects: Vec<String>,
     
     /// Append flags/libraries to the existing set of flags/libraries.
     pub append: Option<CompilerAppend>,
@@ -89,34 +124,129 @@ pub struct Compiler {
 
 }
 
+/// Marks a test as skipped or expected to fail.
+/// 
+/// Example
+/// -------
+/// ```toml
+/// [settings]
+/// skip  = "needs a network connection"
+/// xfail = { reason = "#42, wrong rounding", compiler = "clang" }
+/// ```
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Marker {
+
+    /// Always applies.
+    Reason(String),
+
+    /// Applies only if all of the set conditions hold.
+    Conditional {
+
+        reason: String,
+
+        /// Name of the compiler, `clang` also matches `/usr/bin/clang-15`.
+        compiler: Option<String>,
+
+        /// Macro defined through a `-D` flag or a `#define` inside of the file.
+        defined: Option<String>
+
+    }
+
+}
+
 /// Used for default values.
 fn settings_bool_init() -> bool {false}
 
+/// Used for default values.
+fn settings_true_init() -> bool {true}
+
 /// Settings available to the test comment markup.
 #[derive(Deserialize, Clone, Debug)]
 pub struct Settings {
 
-    // /// Simply put, if the code inside your test is contained 
-    // /// within the file / any included files and does not require
-    // /// any external code dependencies, set this to true. 
-    // /// 
-    // /// Otherwise if you need to include the compiled code
-    // /// then "standalone" is false.
-    // #[serde(default = "settings_bool_init")]
-    // pub standalone: bool,
+    /// Simply put, if the code inside your test is contained 
+    /// within the file / any included files and does not require
+    /// any external code dependencies, set this to true. 
+    /// 
+    /// Otherwise if you need to include the compiled code
+    /// then "standalone" is false, the test is compiled from
+    /// [extract::Environment::templated] and linked against 
+    /// [Compiler::objects].
+    /// 
+    /// Falls back to the file config, then [crate::arg_conf::Recipe::standalone]
+    /// and finally `true`.
+    pub standalone: Option<bool>,
 
     /// Run the test or do not run the test, overwritten
-    /// with the -f / --force flag. 
-    #[serde(default = "settings_bool_init")]
+    /// with the -f / --force flag & `--include-ignored`.
+    /// A test that isn't ran is reported as skipped.
+    #[serde(default = "settings_true_init")]
     pub run: bool,
 
+    /// The test passes only if its harness fails to compile, for checking
+    /// that misuse of an API is rejected (`_Static_assert`, `-Werror`...).
+    /// 
+    /// Such tests are always compiled as [Settings::standalone] & are
+    /// left out of the harnesses of the other tests inside of the file.
+    #[serde(default = "settings_bool_init")]
+    pub compile_fail: bool,
+
+    /// Regex the compiler output of a [Settings::compile_fail] test has to
+    /// match, any failed compilation passes if not set.
+    pub expect_diagnostic: Option<String>,
+
+    /// Skips the test with a reason, overwritten with `--include-ignored`.
+    /// 
+    /// Falls back to the file config.
+    pub skip: Option<Marker>,
+
+    /// The test is expected to fail, a failure is reported as `XFAIL`
+    /// & a pass as `XPASS` which fails the run.
+    /// 
+    /// Falls back to the file config.
+    pub xfail: Option<Marker>,
+
     /// Let stdout be displayed while the test is running.
     #[serde(default = "settings_bool_init")]
     pub stdout: bool,
     
     /// Allow user input through stdin while the test is running.
     #[serde(default = "settings_bool_init")]
-    pub stdin: bool
+    pub stdin: bool,
+
+    /// Value a test returning an integer returns when it passes.
+    /// 
+    /// Falls back to the file config and finally 
+    /// [crate::defaults::DEFAULT_INT_PASS_VALUE].
+    pub pass_value: Option<i64>,
+
+    /// Sanitizers to compile & run the test with, replaces
+    /// [crate::arg_conf::Recipe::sanitize] & [crate::arg_conf::Run::sanitize].
+    /// 
+    /// Falls back to the file config.
+    pub sanitize: Option<Vec<crate::arg_conf::Sanitizer>>,
+
+    /// Run the test under valgrind's memcheck, falls back to
+    /// the file config and finally [crate::arg_conf::Run::valgrind].
+    pub valgrind: Option<bool>,
+
+    /// Amount of samples a benchmark takes, falls back to the
+    /// file config and finally [crate::defaults::DEFAULT_BENCH_SAMPLES].
+    pub bench_samples: Option<u64>,
+
+    /// Milliseconds a benchmark is measured for, falls back to the
+    /// file config and finally [crate::defaults::DEFAULT_BENCH_TIME].
+    pub bench_time: Option<u64>,
+
+    /// Build the test into the binary of its file or into its own,
+    /// falls back to the file config, [crate::arg_conf::Run::harness] &
+    /// finally [crate::arg_conf::HarnessMode::File].
+    pub harness: Option<crate::arg_conf::HarnessMode>,
+
+    /// Times the test is retried after failing, falls back to the
+    /// file config and finally [crate::arg_conf::Run::retries].
+    pub retries: Option<u32>
 
 }
 
@@ -124,9 +254,21 @@ impl Default for Settings {
 
     fn default() -> Self {
         Settings {
+            standalone: None,
             run:    true,
+            compile_fail: false,
+            expect_diagnostic: None,
+            skip:   None,
+            xfail:  None,
             stdout: false,
-            stdin:  false
+            stdin:  false,
+            pass_value: None,
+            sanitize:   None,
+            valgrind:   None,
+            bench_samples: None,
+            bench_time:    None,
+            harness:       None,
+            retries:       None
         }
     }
 
@@ -147,7 +289,10 @@ pub struct Config {
 
     /// Commands to run before the test.
     #[serde(default = "Vec::new")]
-    pub commands: Vec<String>
+    pub commands: Vec<String>,
+
+    /// Turns the test into a property test, see [property].
+    pub property: Option<property::PropertyConfig>
 
 }
 
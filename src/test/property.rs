@@ -0,0 +1,889 @@
+//! Property tests, tests accepting arguments that are called with
+//! randomly generated rows of inputs, configured inside of the
+//! tests comment with:
+//! ```toml
+//! [property]
+//! runs = 1000
+//! args = ["int in -100..100", "double", "string(len<=64)", { one_of = [1, 2, 3] }]
+//! ```
+//!
+//! Every entry of `args` generates the argument at the same position,
+//! arguments without an entry use the default generator of their type.
+//! The test itself is the predicate, it passes a row the same way a
+//! regular test passes.
+//!
+//! Input rows
+//! ----------
+//! Rows are written into the file named by [PROPERTY_INPUT_ENVIRONMENT],
+//! one per line with the arguments tab separated (tabs, newlines &
+//! backslashes inside of strings are escaped). The harness keeps the
+//! index of the row it is running inside of [PROPERTY_PROGRESS_ENVIRONMENT].
+//!
+//! Shrinking
+//! ---------
+//! A failing row is shrunk by replacing one argument at a time with a
+//! simpler value (closer to `0`, shorter strings, earlier `one_of`
+//! values) for as long as the row keeps failing.
+
+use std::path::{Path, PathBuf};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    defaults::{
+        DEFAULT_PROPERTY_RUNS,
+        PROPERTY_INPUT_ENVIRONMENT,
+        PROPERTY_PROGRESS_ENVIRONMENT
+    },
+    error::{debuginfo, Alert, AlertInfo}
+};
+
+use super::extract::Function;
+
+/// `[property]` table of a tests config.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct PropertyConfig {
+
+    /// Amount of generated rows, [DEFAULT_PROPERTY_RUNS] if not set.
+    pub runs: Option<u64>,
+
+    /// Seed of the generated rows, derived from [crate::arg_conf::Run::seed]
+    /// if not set, random without either.
+    pub seed: Option<u64>,
+
+    /// Generators of the arguments, in order.
+    #[serde(default = "Vec::new")]
+    pub args: Vec<GeneratorSpec>
+
+}
+
+/// A generator as written inside of the config.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum GeneratorSpec {
+
+    /// `int in -100..100`, `double`, `string(len<=64)`, `bool`...
+    Spec(String),
+
+    /// One of the listed values.
+    OneOf {
+        one_of: Vec<toml::Value>
+    }
+
+}
+
+/// What an argument is passed to the test as.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArgKind {
+    Int,
+    Float,
+    String,
+    Bool
+}
+
+/// A generated argument.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool)
+}
+
+/// Generates the values of a single argument.
+#[derive(Clone, Debug)]
+pub enum Generator {
+
+    /// Inclusive range.
+    Int { min: i64, max: i64 },
+
+    /// Half open range.
+    Float { min: f64, max: f64 },
+
+    /// Printable ASCII strings of at most `max_len` characters.
+    String { max_len: usize },
+
+    Bool,
+
+    OneOf(Vec<Value>)
+
+}
+
+/// A single argument of a property test.
+#[derive(Clone, Debug)]
+pub struct PropertyArg {
+
+    /// Canonical C type of the argument.
+    pub ty: String,
+
+    pub kind: ArgKind,
+
+    pub generator: Generator
+
+}
+
+/// Resolved property of a test.
+#[derive(Clone, Debug)]
+pub struct Property {
+
+    pub runs: u64,
+
+    pub seed: u64,
+
+    pub args: Vec<PropertyArg>
+
+}
+
+/// A property that didn't hold.
+#[derive(Clone, Debug)]
+pub struct PropertyFailure {
+
+    /// Seed the rows were generated with.
+    pub seed: u64,
+
+    /// Rows ran until the failing one, including it.
+    pub runs: usize,
+
+    /// Shrinking steps that kept failing.
+    pub shrinks: usize,
+
+    /// The shrunk failing row.
+    pub counterexample: Vec<Value>,
+
+    /// Types of the arguments, for displaying the counterexample.
+    pub types: Vec<String>
+
+}
+
+impl ArgKind {
+
+    /// Kind of a canonical C type, [None] if the
+    /// type can't be generated.
+    pub fn from_type(ty: &str) -> Option<Self> {
+
+        let ty = ty.replace("const ", "").replace("volatile ", "");
+        let ty = ty.trim();
+
+        match ty {
+            "_Bool" | "bool" => Some(ArgKind::Bool),
+            "float" | "double" | "long double" => Some(ArgKind::Float),
+            "char *" | "signed char *" | "unsigned char *" => Some(ArgKind::String),
+            "char" | "signed char" | "unsigned char"
+            | "short" | "unsigned short"
+            | "int" | "unsigned int"
+            | "long" | "unsigned long"
+            | "long long" | "unsigned long long" => Some(ArgKind::Int),
+            other if other.starts_with("enum ") && !other.contains('*') => Some(ArgKind::Int),
+            _ => None
+        }
+
+    }
+
+}
+
+/// Range of an integer type, assuming LP64.
+fn integer_range(ty: &str) -> (i64, i64) {
+
+    let unsigned = ty.contains("unsigned");
+
+    let bits = if ty.contains("char") {
+        8
+    } else if ty.contains("short") {
+        16
+    } else if ty.contains("long") {
+        64
+    } else {
+        32
+    };
+
+    match (unsigned, bits) {
+        (true, 64)  => (0, i64::MAX),
+        (true, _)   => (0, (1i64 << bits) - 1),
+        (false, 64) => (i64::MIN, i64::MAX),
+        (false, _)  => (-(1i64 << (bits - 1)), (1i64 << (bits - 1)) - 1)
+    }
+
+}
+
+impl Generator {
+
+    /// Default generator of an argument of type `ty`.
+    fn default_for(ty: &str, kind: ArgKind) -> Self {
+
+        match kind {
+            ArgKind::Int    => {
+                let (min, max) = integer_range(ty);
+                Generator::Int { min, max }
+            }
+            ArgKind::Float  => Generator::Float { min: -1e6, max: 1e6 },
+            ArgKind::String => Generator::String { max_len: 32 },
+            ArgKind::Bool   => Generator::Bool
+        }
+
+    }
+
+    /// Parses `spec` for an argument of type `ty`.
+    fn parse(spec: &GeneratorSpec, ty: &str, kind: ArgKind) -> Result<Self, String> {
+
+        let spec = match spec {
+            GeneratorSpec::Spec(spec) => spec.trim(),
+            GeneratorSpec::OneOf { one_of } => {
+                if one_of.is_empty() {
+                    return Err("`one_of` needs at least a single value".to_owned())
+                }
+                return one_of
+                    .iter()
+                    .map(|value| Value::from_toml(value, kind))
+                    .collect::<Result<Vec<Value>, String>>()
+                    .map(Generator::OneOf)
+            }
+        };
+
+        let (name, range) = match spec.split_once(" in ") {
+            Some((name, range)) => (name.trim(), Some(range.trim())),
+            None => (spec, None)
+        };
+
+        let generator = match (name, range) {
+            ("int", range) => {
+                let (type_min, type_max) = integer_range(ty);
+                let (min, max) = match range {
+                    Some(range) => parse_range::<i64>(range, true)?,
+                    None => (type_min, type_max)
+                };
+                Generator::Int { min: min.max(type_min), max: max.min(type_max) }
+            }
+            ("double" | "float", range) => {
+                let (min, max) = match range {
+                    Some(range) => parse_range::<f64>(range, false)?,
+                    None => (-1e6, 1e6)
+                };
+                Generator::Float { min, max }
+            }
+            ("string", None) => Generator::String { max_len: 32 },
+            (name, None) if name.starts_with("string(") && name.ends_with(')') => {
+                let inner = name["string(".len()..name.len() - 1].replace(' ', "");
+                match inner.strip_prefix("len<=").map(|max| max.parse::<usize>()) {
+                    Some(Ok(max_len)) => Generator::String { max_len },
+                    _ => return Err(format!("expected `string(len<=<max>)`, got `{spec}`"))
+                }
+            }
+            ("bool", None) => Generator::Bool,
+            _ => return Err(format!("unknown generator `{spec}`"))
+        };
+
+        let produces = match generator {
+            Generator::Int { .. }    => ArgKind::Int,
+            Generator::Float { .. }  => ArgKind::Float,
+            Generator::String { .. } => ArgKind::String,
+            Generator::Bool          => ArgKind::Bool,
+            Generator::OneOf(_)      => kind
+        };
+
+        match generator {
+            Generator::Int { min, max } if min > max => Err(format!("generator `{spec}` has an empty range for `{ty}`")),
+            Generator::Float { min, max } if min >= max => Err(format!("generator `{spec}` has an empty range")),
+            _ if produces != kind => Err(format!("generator `{spec}` can't produce `{ty}`")),
+            generator => Ok(generator)
+        }
+
+    }
+
+    fn generate(&self, rng: &mut StdRng) -> Value {
+
+        match self {
+            Generator::Int { min, max } => {
+                // Edges & zero are where bugs hide, prefer them now & then.
+                let special = [*min, *max, 0i64.clamp(*min, *max)];
+                if rng.gen_bool(0.1) {
+                    Value::Int(special[rng.gen_range(0..special.len())])
+                } else {
+                    Value::Int(rng.gen_range(*min..=*max))
+                }
+            }
+            Generator::Float { min, max } => Value::Float(rng.gen_range(*min..*max)),
+            Generator::String { max_len } => {
+                let length = rng.gen_range(0..=*max_len);
+                Value::Str((0..length).map(|_| rng.gen_range(0x20u8..0x7f) as char).collect())
+            }
+            Generator::Bool => Value::Bool(rng.gen_bool(0.5)),
+            Generator::OneOf(values) => values[rng.gen_range(0..values.len())].clone()
+        }
+
+    }
+
+    /// Simpler values than `value`, simplest first.
+    fn shrink(&self, value: &Value) -> Vec<Value> {
+
+        let mut candidates: Vec<Value> = match (self, value) {
+            (Generator::OneOf(values), value) => values
+                .iter()
+                .take_while(|candidate| *candidate != value)
+                .cloned()
+                .collect(),
+            (Generator::Int { min, max }, Value::Int(x)) => {
+                let target = 0i64.clamp(*min, *max);
+                if *x == target {
+                    return vec![]
+                }
+                let half = x - (x - target) / 2;
+                let step = if *x > target { x - 1 } else { x + 1 };
+                vec![Value::Int(target), Value::Int(half), Value::Int(step)]
+            }
+            (Generator::Float { min, max }, Value::Float(x)) => {
+                let target = 0f64.clamp(*min, *max);
+                vec![Value::Float(target), Value::Float(x.trunc()), Value::Float(target + (x - target) / 2.0)]
+                    .into_iter()
+                    .filter(|candidate| matches!(candidate, Value::Float(c) if c >= min && c < max))
+                    .collect()
+            }
+            (Generator::String { .. }, Value::Str(s)) => {
+                let chars: Vec<char> = s.chars().collect();
+                let mut candidates = vec![
+                    Value::Str(String::new()),
+                    Value::Str(chars[..chars.len() / 2].iter().collect())
+                ];
+                for index in 0..chars.len().min(16) {
+                    let mut removed = chars.clone();
+                    removed.remove(index);
+                    candidates.push(Value::Str(removed.into_iter().collect()));
+                }
+                for index in 0..chars.len().min(16) {
+                    if chars[index] != 'a' {
+                        let mut replaced = chars.clone();
+                        replaced[index] = 'a';
+                        candidates.push(Value::Str(replaced.into_iter().collect()));
+                    }
+                }
+                candidates
+            }
+            (Generator::Bool, Value::Bool(true)) => vec![Value::Bool(false)],
+            _ => vec![]
+        };
+
+        let mut unique: Vec<Value> = vec![];
+        candidates.retain(|candidate| {
+            if candidate == value || unique.contains(candidate) {
+                return false
+            }
+            unique.push(candidate.clone());
+            true
+        });
+
+        candidates
+
+    }
+
+}
+
+/// Parses `min..max` (or `min..=max` if `inclusive` is allowed).
+fn parse_range<T>(range: &str, inclusive: bool) -> Result<(T, T), String>
+where T: std::str::FromStr + Copy + std::ops::Sub<Output = T> + From<u8>
+{
+
+    let error = || format!("expected a range like `-100..100`, got `{range}`");
+
+    let (min, max, closed) = match range.split_once("..=") {
+        Some((min, max)) if inclusive => (min, max, true),
+        Some(_) => return Err(error()),
+        None => match range.split_once("..") {
+            Some((min, max)) => (min, max, false),
+            None => return Err(error())
+        }
+    };
+
+    let min: T = min.trim().parse().map_err(|_| error())?;
+    let max: T = max.trim().parse().map_err(|_| error())?;
+
+    // Integer ranges are inclusive internally.
+    if inclusive && !closed {
+        return Ok((min, max - T::from(1)))
+    }
+
+    Ok((min, max))
+
+}
+
+impl Value {
+
+    fn from_toml(value: &toml::Value, kind: ArgKind) -> Result<Self, String> {
+
+        match (value, kind) {
+            (toml::Value::Integer(x), ArgKind::Int)    => Ok(Value::Int(*x)),
+            (toml::Value::Integer(x), ArgKind::Float)  => Ok(Value::Float(*x as f64)),
+            (toml::Value::Float(x), ArgKind::Float)    => Ok(Value::Float(*x)),
+            (toml::Value::String(x), ArgKind::String)  => Ok(Value::Str(x.clone())),
+            (toml::Value::Boolean(x), ArgKind::Bool)   => Ok(Value::Bool(*x)),
+            (value, _) => Err(format!("`one_of` value `{value}` doesn't match the argument"))
+        }
+
+    }
+
+    /// The value as a field of an input row.
+    fn field(&self) -> String {
+
+        match self {
+            Value::Int(x)   => x.to_string(),
+            Value::Float(x) => format!("{x:?}"),
+            Value::Bool(x)  => if *x { "1".to_owned() } else { "0".to_owned() },
+            Value::Str(x)   => x
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r")
+        }
+
+    }
+
+}
+
+impl std::fmt::Display for Value {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        match self {
+            Value::Int(x)   => write!(f, "{x}"),
+            Value::Float(x) => write!(f, "{x:?}"),
+            Value::Str(x)   => write!(f, "{x:?}"),
+            Value::Bool(x)  => write!(f, "{x}")
+        }
+
+    }
+
+}
+
+/// Seed of the property test `identity` inside of a run with `seed`, such
+/// that every test of the run generates different rows & the same
+/// `--seed` reproduces them.
+fn derive_seed(seed: u64, identity: &str) -> u64 {
+
+    let digest = Sha256::new()
+        .chain_update(seed.to_le_bytes())
+        .chain_update(identity.as_bytes())
+        .finalize();
+
+    u64::from_le_bytes(digest[..8].try_into().unwrap())
+
+}
+
+impl Property {
+
+    /// Resolves the generators of every argument of `function`, the test
+    /// `identity` (see [super::run::identity]) of a run with `run_seed`.
+    pub fn resolve(
+        config:   &PropertyConfig,
+        function: &Function,
+        run_seed: Option<u64>,
+        identity: &str
+    ) -> Result<Self, String> {
+
+        if config.args.len() > function.args.len() {
+            return Err(format!("`args` has {} generators but `{}` only accepts {} arguments",
+                config.args.len(), function.name, function.args.len()))
+        }
+
+        let mut args: Vec<PropertyArg> = vec![];
+
+        for (index, ty) in function.args.iter().enumerate() {
+
+            let Some(kind) = ArgKind::from_type(ty) else {
+                return Err(format!("argument {} of type `{ty}` can't be generated", index + 1))
+            };
+
+            let generator = match config.args.get(index) {
+                Some(spec) => Generator::parse(spec, ty, kind)
+                    .map_err(|err| format!("argument {}: {err}", index + 1))?,
+                None => Generator::default_for(ty, kind)
+            };
+
+            args.push(PropertyArg {
+                ty: ty.clone(),
+                kind,
+                generator
+            });
+
+        }
+
+        Ok(Property {
+            runs: config.runs.unwrap_or(DEFAULT_PROPERTY_RUNS),
+            seed: config.seed
+                .or(run_seed.map(|seed| derive_seed(seed, identity)))
+                .unwrap_or_else(rand::random),
+            args
+        })
+
+    }
+
+    /// Generates [Property::runs] rows from [Property::seed].
+    pub fn generate(&self) -> Vec<Vec<Value>> {
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+
+        (0..self.runs)
+            .map(|_| self.args.iter().map(|arg| arg.generator.generate(&mut rng)).collect())
+            .collect()
+
+    }
+
+    /// Rows that differ from `row` by a single simpler argument.
+    pub fn shrink(&self, row: &[Value]) -> Vec<Vec<Value>> {
+
+        let mut rows: Vec<Vec<Value>> = vec![];
+
+        for (index, arg) in self.args.iter().enumerate() {
+            for candidate in arg.generator.shrink(&row[index]) {
+                let mut shrunk = row.to_vec();
+                shrunk[index] = candidate;
+                rows.push(shrunk);
+            }
+        }
+
+        rows
+
+    }
+
+    /// C expression converting field `index` of `cesty_fields`
+    /// into the argument at `index`.
+    pub fn conversion(&self, index: usize) -> String {
+
+        let arg = &self.args[index];
+
+        match arg.kind {
+            ArgKind::Int    => format!("({}) strtoll(cesty_fields[{index}], 0, 10)", arg.ty),
+            ArgKind::Float  => format!("({}) strtod(cesty_fields[{index}], 0)", arg.ty),
+            ArgKind::String => format!("({}) cesty_fields[{index}]", arg.ty),
+            ArgKind::Bool   => format!("strtol(cesty_fields[{index}], 0, 10) != 0")
+        }
+
+    }
+
+}
+
+/// Input rows file of the harness `binary`.
+fn input_path(binary: &Path) -> PathBuf {
+
+    binary.with_extension("rows")
+
+}
+
+/// Progress file of the harness `binary`.
+fn progress_path(binary: &Path) -> PathBuf {
+
+    binary.with_extension("progress")
+
+}
+
+/// Writes `rows` as the input of `binary`.
+pub fn write_rows(binary: &Path, rows: &[Vec<Value>]) -> std::io::Result<()> {
+
+    let mut contents = String::new();
+
+    for row in rows {
+        contents += &row.iter().map(Value::field).collect::<Vec<String>>().join("\t");
+        contents.push('\n');
+    }
+
+    std::fs::write(input_path(binary), contents)
+
+}
+
+/// Sets the input & progress files of `binary` on `command`.
+pub fn prepare(command: &mut std::process::Command, binary: &Path) {
+
+    let progress = progress_path(binary);
+    _ = std::fs::remove_file(&progress);
+
+    command
+        .env(PROPERTY_INPUT_ENVIRONMENT, input_path(binary))
+        .env(PROPERTY_PROGRESS_ENVIRONMENT, progress);
+
+}
+
+/// Index of the row the last run of `binary` stopped at.
+pub fn read_progress(binary: &Path) -> Option<usize> {
+
+    std::fs::read_to_string(progress_path(binary))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+
+}
+
+impl PropertyFailure {
+
+    pub fn to_alert(&self, test: &str) -> Alert {
+
+        let arguments = self.counterexample
+            .iter()
+            .zip(self.types.iter())
+            .map(|(value, ty)| format!("({ty}) {value}"))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        Alert::Error(AlertInfo {
+            description: format!("property `{test}` failed after {} runs", self.runs),
+            debug: debuginfo!(),
+            example: None,
+            note: vec![
+                format!("counterexample: {test}({arguments})"),
+                format!("shrunk in {} steps, generated with seed {}.", self.shrinks, self.seed),
+                format!("reproduce it by adding `seed = {}` to the tests `[property]`.", self.seed)
+            ]
+        })
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::test::extract::Function;
+
+    use super::{
+        parse_range, Generator, GeneratorSpec,
+        ArgKind, Property, PropertyConfig, Value
+    };
+
+    fn spec(spec: &str) -> GeneratorSpec {
+        GeneratorSpec::Spec(spec.to_owned())
+    }
+
+    fn one_of(values: &str) -> GeneratorSpec {
+        toml::from_str::<PropertyConfig>(&format!("args = [{{ one_of = {values} }}]"))
+            .unwrap()
+            .args
+            .remove(0)
+    }
+
+    fn function(args: &[&str]) -> Function {
+        Function {
+            name: "cesty_property".to_owned(),
+            name_slice: "property".to_owned(),
+            returns: "int".to_owned(),
+            args: args.iter().map(|arg| arg.to_string()).collect()
+        }
+    }
+
+    #[test]
+    fn ranges() {
+
+        assert_eq!(parse_range::<i64>("-100..100", true), Ok((-100, 99)));
+        assert_eq!(parse_range::<i64>("-100..=100", true), Ok((-100, 100)));
+        assert_eq!(parse_range::<i64>(" 1 .. 5 ", true), Ok((1, 4)));
+        assert_eq!(parse_range::<f64>("-0.5..0.5", false), Ok((-0.5, 0.5)));
+
+        assert!(parse_range::<f64>("0..=1", false).is_err());
+        assert!(parse_range::<i64>("..5", true).is_err());
+        assert!(parse_range::<i64>("1-5", true).is_err());
+        assert!(parse_range::<i64>("a..b", true).is_err());
+
+    }
+
+    #[test]
+    fn int_generators() {
+
+        assert!(matches!(Generator::parse(&spec("int in -100..100"), "int", ArgKind::Int),
+            Ok(Generator::Int { min: -100, max: 99 })));
+        assert!(matches!(Generator::parse(&spec("int"), "unsigned short", ArgKind::Int),
+            Ok(Generator::Int { min: 0, max: 65535 })));
+
+        // Clamped to the range of the arguments type.
+        assert!(matches!(Generator::parse(&spec("int in -1000..1000"), "signed char", ArgKind::Int),
+            Ok(Generator::Int { min: -128, max: 127 })));
+        assert!(Generator::parse(&spec("int in 300..400"), "unsigned char", ArgKind::Int).is_err());
+        assert!(Generator::parse(&spec("int in 5..5"), "int", ArgKind::Int).is_err());
+
+    }
+
+    #[test]
+    fn other_generators() {
+
+        assert!(matches!(Generator::parse(&spec("string(len<=64)"), "char *", ArgKind::String),
+            Ok(Generator::String { max_len: 64 })));
+        assert!(matches!(Generator::parse(&spec("string( len <= 8 )"), "const char *", ArgKind::String),
+            Ok(Generator::String { max_len: 8 })));
+        assert!(matches!(Generator::parse(&spec("string"), "char *", ArgKind::String),
+            Ok(Generator::String { max_len: 32 })));
+        assert!(Generator::parse(&spec("string(len<64)"), "char *", ArgKind::String).is_err());
+
+        assert!(matches!(Generator::parse(&spec("double in 0..1"), "double", ArgKind::Float),
+            Ok(Generator::Float { min, max }) if min == 0.0 && max == 1.0));
+        assert!(Generator::parse(&spec("double in 1..1"), "double", ArgKind::Float).is_err());
+
+        assert!(matches!(Generator::parse(&spec("bool"), "_Bool", ArgKind::Bool), Ok(Generator::Bool)));
+
+        assert!(Generator::parse(&spec("int"), "double", ArgKind::Float).is_err());
+        assert!(Generator::parse(&spec("uuid"), "char *", ArgKind::String).is_err());
+
+    }
+
+    #[test]
+    fn one_of_generators() {
+
+        match Generator::parse(&one_of("[1, 2, 3]"), "int", ArgKind::Int) {
+            Ok(Generator::OneOf(values)) => assert_eq!(values, vec![Value::Int(1), Value::Int(2), Value::Int(3)]),
+            other => panic!("expected `one_of`, got {other:?}")
+        }
+
+        match Generator::parse(&one_of("[1, 2.5]"), "double", ArgKind::Float) {
+            Ok(Generator::OneOf(values)) => assert_eq!(values, vec![Value::Float(1.0), Value::Float(2.5)]),
+            other => panic!("expected `one_of`, got {other:?}")
+        }
+
+        assert!(Generator::parse(&one_of("[\"a\", 1]"), "char *", ArgKind::String).is_err());
+        assert!(Generator::parse(&one_of("[]"), "int", ArgKind::Int).is_err());
+
+    }
+
+    #[test]
+    fn int_shrinks_towards_zero() {
+
+        let generator = Generator::Int { min: -100, max: 99 };
+
+        assert_eq!(generator.shrink(&Value::Int(37)), vec![Value::Int(0), Value::Int(19), Value::Int(36)]);
+        assert_eq!(generator.shrink(&Value::Int(-37)), vec![Value::Int(0), Value::Int(-19), Value::Int(-36)]);
+        assert_eq!(generator.shrink(&Value::Int(1)), vec![Value::Int(0)]);
+        assert!(generator.shrink(&Value::Int(0)).is_empty());
+
+    }
+
+    #[test]
+    fn int_shrinks_towards_the_range() {
+
+        // Zero is outside of the range, the closest edge is the simplest value.
+        let generator = Generator::Int { min: 10, max: 20 };
+
+        assert_eq!(generator.shrink(&Value::Int(17)), vec![Value::Int(10), Value::Int(14), Value::Int(16)]);
+        assert!(generator.shrink(&Value::Int(10)).is_empty());
+
+        let generator = Generator::Int { min: -20, max: -10 };
+
+        assert_eq!(generator.shrink(&Value::Int(-17)), vec![Value::Int(-10), Value::Int(-14), Value::Int(-16)]);
+
+    }
+
+    #[test]
+    fn float_shrinks() {
+
+        let generator = Generator::Float { min: -10.0, max: 10.0 };
+
+        assert_eq!(generator.shrink(&Value::Float(-3.5)),
+            vec![Value::Float(0.0), Value::Float(-3.0), Value::Float(-1.75)]);
+        assert!(generator.shrink(&Value::Float(0.0)).is_empty());
+
+        // Candidates outside of the half open range are dropped.
+        let generator = Generator::Float { min: 5.0, max: 10.0 };
+
+        assert_eq!(generator.shrink(&Value::Float(7.5)),
+            vec![Value::Float(5.0), Value::Float(7.0), Value::Float(6.25)]);
+        assert_eq!(generator.shrink(&Value::Float(5.5)), vec![Value::Float(5.0), Value::Float(5.25)]);
+
+    }
+
+    #[test]
+    fn other_shrinks() {
+
+        let string = Generator::String { max_len: 8 };
+
+        assert_eq!(string.shrink(&Value::Str("abc".to_owned())), ["", "a", "bc", "ac", "ab", "aac", "aba"]
+            .map(|s| Value::Str(s.to_owned()))
+            .to_vec());
+        assert!(string.shrink(&Value::Str(String::new())).is_empty());
+
+        let one_of = Generator::OneOf(vec![Value::Int(5), Value::Int(1), Value::Int(3)]);
+
+        assert_eq!(one_of.shrink(&Value::Int(3)), vec![Value::Int(5), Value::Int(1)]);
+        assert!(one_of.shrink(&Value::Int(5)).is_empty());
+
+        assert_eq!(Generator::Bool.shrink(&Value::Bool(true)), vec![Value::Bool(false)]);
+        assert!(Generator::Bool.shrink(&Value::Bool(false)).is_empty());
+
+    }
+
+    #[test]
+    fn shrinks_one_argument_at_a_time() {
+
+        let config = PropertyConfig {
+            runs: Some(1),
+            seed: Some(1),
+            args: vec![spec("int in -100..100"), spec("bool")]
+        };
+        let property = Property::resolve(&config, &function(&["int", "_Bool"]), None, "ds.c::cesty_property").unwrap();
+
+        assert_eq!(property.shrink(&[Value::Int(2), Value::Bool(true)]), vec![
+            vec![Value::Int(0), Value::Bool(true)],
+            vec![Value::Int(1), Value::Bool(true)],
+            vec![Value::Int(2), Value::Bool(false)]
+        ]);
+
+    }
+
+    #[test]
+    fn resolves_and_generates_in_range() {
+
+        let config = PropertyConfig {
+            runs: Some(200),
+            seed: Some(42),
+            args: vec![spec("int in -1000..1000"), one_of("[\"x\", \"y\"]")]
+        };
+        let property = Property::resolve(&config, &function(&["signed char", "char *", "double"]), None, "ds.c::cesty_property").unwrap();
+
+        assert_eq!(property.args.len(), 3);
+        assert_eq!(property.args[2].kind, ArgKind::Float);
+
+        let rows = property.generate();
+
+        assert_eq!(rows.len(), 200);
+        assert_eq!(rows, property.generate());
+
+        for row in rows.iter() {
+            assert!(matches!(row[0], Value::Int(x) if (-128..=127).contains(&x)));
+            assert!(matches!(&row[1], Value::Str(s) if s == "x" || s == "y"));
+            assert!(matches!(row[2], Value::Float(x) if (-1e6..1e6).contains(&x)));
+        }
+
+    }
+
+    #[test]
+    fn seeds() {
+
+        let resolve = |config: &PropertyConfig, run_seed: Option<u64>, identity: &str| {
+            Property::resolve(config, &function(&["int"]), run_seed, identity).unwrap().seed
+        };
+
+        let unseeded = PropertyConfig::default();
+
+        // Derived from the run seed, different for every test.
+        assert_eq!(resolve(&unseeded, Some(7), "ds.c::cesty_a"), resolve(&unseeded, Some(7), "ds.c::cesty_a"));
+        assert_ne!(resolve(&unseeded, Some(7), "ds.c::cesty_a"), resolve(&unseeded, Some(7), "ds.c::cesty_b"));
+        assert_ne!(resolve(&unseeded, Some(7), "ds.c::cesty_a"), resolve(&unseeded, Some(8), "ds.c::cesty_a"));
+
+        // The tests own seed takes precedence.
+        let seeded = PropertyConfig {
+            seed: Some(3),
+            ..Default::default()
+        };
+
+        assert_eq!(resolve(&seeded, Some(7), "ds.c::cesty_a"), 3);
+        assert_eq!(resolve(&seeded, None, "ds.c::cesty_a"), 3);
+
+    }
+
+    #[test]
+    fn rejects_unusable_arguments() {
+
+        let config = PropertyConfig {
+            args: vec![spec("int"), spec("int")],
+            ..Default::default()
+        };
+
+        assert!(Property::resolve(&config, &function(&["int"]), None, "ds.c::cesty_property").is_err());
+        assert!(Property::resolve(&PropertyConfig::default(), &function(&["struct node *"]), None, "ds.c::cesty_property").is_err());
+
+    }
+
+}
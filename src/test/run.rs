@@ -0,0 +1,944 @@
+//! Running compiled tests & reporting their results.
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, Instant}
+};
+
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    defaults::{
+        HARNESS_FIXTURE_FAILURE_CODE,
+        HARNESS_SETUP_ARGUMENT,
+        HARNESS_TEARDOWN_ARGUMENT,
+        MAX_PROPERTY_SHRINKS,
+        SEED_ENVIRONMENT
+    },
+    error::{debuginfo, Alert, AlertInfo}
+};
+
+use super::{
+    assert::AssertionFailure,
+    bench::BenchStats,
+    property::{Property, PropertyFailure},
+    sanitize::SanitizerReport,
+    valgrind::ValgrindFinding,
+    compilable::{CompilableTest, CompiledTest},
+    extract::{Fixtures, ParsedTest}
+};
+
+/// How a test finished.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+
+    /// The test returned a passing value.
+    Passed,
+
+    /// The test returned a failing value.
+    Failed,
+
+    /// The test was killed by a signal.
+    Crashed,
+
+    /// The test harness failed to compile.
+    CompileError,
+
+    /// A fixture surrounding the test failed, the
+    /// test itself either didn't run or passed.
+    FixtureFailed,
+
+    /// The test failed & then passed on a retry.
+    Flaky,
+
+    /// The test wasn't ran, see [super::Settings::skip].
+    Skipped,
+
+    /// The test failed as expected, see [super::Settings::xfail].
+    ExpectedFailure,
+
+    /// The test was expected to fail but passed.
+    UnexpectedPass
+
+}
+
+/// Result of a single test.
+#[derive(Clone, Debug)]
+pub struct TestResult {
+
+    /// File the test was extracted from.
+    pub file: PathBuf,
+
+    /// Full function name of the test.
+    pub name: String,
+
+    pub outcome: Outcome,
+
+    /// Exit code of the test, [None] if it was killed by a signal
+    /// or never ran.
+    pub code: Option<i32>,
+
+    pub duration: Duration,
+
+    /// Captured stdout, empty if it was displayed while running.
+    pub stdout: String,
+
+    /// Captured stderr.
+    pub stderr: String,
+
+    /// Assertions from `cesty.h` that failed while running.
+    pub assertions: Vec<AssertionFailure>,
+
+    /// Reports parsed from stderr when ran with sanitizers.
+    pub sanitizer_reports: Vec<SanitizerReport>,
+
+    /// Findings parsed from the XML report when ran under valgrind.
+    pub valgrind_findings: Vec<ValgrindFinding>,
+
+    /// Statistics of a benchmark, [None] for regular tests.
+    pub bench: Option<BenchStats>,
+
+    /// Shrunk counterexample of a failed property test.
+    pub property: Option<PropertyFailure>,
+
+    /// Times the test was retried, see [CompilableTest::retries].
+    pub retries: u32,
+
+    /// Reason the test was skipped or expected to fail.
+    pub reason: Option<String>,
+
+    /// See [CompiledTest::fingerprint], [None] if the test never ran.
+    pub fingerprint: Option<String>
+
+}
+
+/// Amount of tests per [Outcome].
+#[derive(Clone, Debug, Default)]
+pub struct Summary {
+
+    pub passed:        usize,
+    pub failed:        usize,
+    pub crashed:       usize,
+    pub compile_error: usize,
+    pub fixture_failed: usize,
+    pub flaky:         usize,
+    pub skipped:       usize,
+    pub expected_failure: usize,
+    pub unexpected_pass:  usize,
+
+    /// Identity, outcome & reason of every skipped test
+    /// & every test that was expected to fail.
+    pub reasons: Vec<(String, Outcome, String)>,
+
+    /// Identity & outcome of every quarantined test, see
+    /// [crate::arg_conf::Run::quarantine], not counted by any other field.
+    pub quarantined: Vec<(String, Outcome)>,
+
+    pub duration: Duration,
+
+    /// Seed the tests were shuffled with, see [crate::arg_conf::Run::seed].
+    pub seed: Option<u64>
+
+}
+
+impl Outcome {
+
+    /// Did the test not pass, see [Summary::unsuccessful].
+    pub fn unsuccessful(&self) -> bool {
+        matches!(self,
+            Outcome::Failed
+            | Outcome::Crashed
+            | Outcome::CompileError
+            | Outcome::FixtureFailed
+            | Outcome::UnexpectedPass
+        )
+    }
+
+}
+
+impl TestResult {
+
+    /// Result of a test that was skipped because of `reason`.
+    pub fn skipped(test: &CompilableTest, reason: &str) -> Self {
+        TestResult {
+            outcome: Outcome::Skipped,
+            reason:  Some(reason.to_owned()),
+            ..Self::compile_error(test)
+        }
+    }
+
+    /// Result of a test whose harness failed to compile.
+    pub fn compile_error(test: &CompilableTest) -> Self {
+        TestResult {
+            file:     test.file.clone(),
+            name:     test.name.clone(),
+            outcome:  Outcome::CompileError,
+            code:     None,
+            duration: Duration::ZERO,
+            stdout:   String::new(),
+            stderr:   String::new(),
+            assertions: vec![],
+            sanitizer_reports: vec![],
+            valgrind_findings: vec![],
+            bench: None,
+            property: None,
+            retries: 0,
+            reason: None,
+            fingerprint: None
+        }
+    }
+
+    /// Identity of the test, see [identity].
+    pub fn identity(&self) -> String {
+        identity(&self.file, &self.name)
+    }
+
+}
+
+/// Identity of the test `name` inside of `file`, `file::name`,
+/// with the file relative to the current pwd.
+pub fn identity(file: &Path, name: &str) -> String {
+
+    let file = match std::env::current_dir() {
+        Ok(pwd) => file.strip_prefix(pwd).unwrap_or(file).to_path_buf(),
+        Err(_)  => file.to_path_buf()
+    };
+
+    format!("{}::{}", file.to_string_lossy(), name)
+
+}
+
+/// Runs a compiled test, stdout is captured unless
+/// [super::Settings::stdout] is set and stdin is only
+/// available with [super::Settings::stdin].
+pub fn run(compiled: &CompiledTest) -> TestResult {
+
+    execute(compiled, None, None, &compiled.test.name)
+
+}
+
+/// Replays every input that crashed the fuzz target `compiled`,
+/// each as its own test named `<target>[<input>]`.
+fn replay(compiled: &CompiledTest) -> Vec<TestResult> {
+
+    compiled.test.fuzz_inputs
+        .iter()
+        .map(|input| execute(
+            compiled,
+            None,
+            Some(&input.to_string_lossy()),
+            &format!("{}[{}]", compiled.test.name, input.file_name().unwrap_or_default().to_string_lossy())
+        ))
+        .collect()
+
+}
+
+/// Runs the property test `compiled` over its generated rows, a failing
+/// row is shrunk by running all of its simpler variants at once & keeping
+/// the first one that still fails, until none of them fail.
+/// 
+/// The returned result is the run of the shrunk counterexample.
+fn run_property(compiled: &CompiledTest, property: &Property) -> TestResult {
+
+    let rows = property.generate();
+    let write_error = super::property::write_rows(&compiled.test.artifacts, &rows).err();
+
+    let mut result = execute(compiled, None, None, &compiled.test.name);
+
+    if let Some(err) = write_error {
+        result.stderr += &format!("failed to write the property rows: {err}");
+        return result
+    }
+
+    let failed = |result: &TestResult| matches!(result.outcome, Outcome::Failed | Outcome::Crashed);
+
+    let Some(index) = super::property::read_progress(&compiled.test.artifacts).filter(|_| failed(&result)) else {
+        return result
+    };
+
+    let Some(mut counterexample) = rows.get(index).cloned() else {
+        return result
+    };
+
+    let mut shrinks: usize = 0;
+
+    while shrinks < MAX_PROPERTY_SHRINKS {
+
+        let candidates = property.shrink(&counterexample);
+
+        if candidates.is_empty() || super::property::write_rows(&compiled.test.artifacts, &candidates).is_err() {
+            break
+        }
+
+        let attempt = execute(compiled, None, None, &compiled.test.name);
+
+        let Some(shrunk) = super::property::read_progress(&compiled.test.artifacts)
+            .filter(|_| failed(&attempt))
+            .and_then(|index| candidates.get(index))
+        else {
+            break
+        };
+
+        counterexample = shrunk.clone();
+        result = attempt;
+        shrinks += 1;
+
+    }
+
+    result.property = Some(PropertyFailure {
+        seed:  property.seed,
+        runs:  index + 1,
+        shrinks,
+        counterexample,
+        types: property.args.iter().map(|arg| arg.ty.clone()).collect()
+    });
+
+    result
+
+}
+
+/// Compiles the [CompilableTest::compile_fail] test `test`, passing if
+/// the compiler fails with output matching [CompilableTest::expect_diagnostic].
+/// The compiler output is kept as the stderr of the result.
+fn compile_fail(test: &CompilableTest) -> TestResult {
+
+    let mut result = TestResult {
+        outcome: Outcome::Failed,
+        ..TestResult::compile_error(test)
+    };
+
+    let start = Instant::now();
+    let output = test.compile_output(&test.path.with_extension("out"));
+    result.duration = start.elapsed();
+
+    let output = match output {
+        Ok(output) => output,
+        Err(err) => {
+            eprintln!("{err}");
+            result.outcome = Outcome::CompileError;
+            return result
+        }
+    };
+
+    result.code = output.status.code();
+    result.stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if output.status.success() {
+        result.stderr.insert_str(0, "compiled successfully but was expected to fail\n");
+        return result
+    }
+
+    match test.expect_diagnostic.as_ref() {
+        Some(regex) if !regex.is_match(&result.stderr) => {
+            result.stderr.insert_str(0, &format!("compilation failed but no diagnostic matched `{regex}`\n"));
+        }
+        _ => result.outcome = Outcome::Passed
+    }
+
+    result
+
+}
+
+/// Log of the failed `attempt` of `compiled`, next to its binary.
+fn attempt_path(compiled: &CompiledTest, attempt: u32) -> PathBuf {
+
+    compiled.test.artifacts.with_extension(format!("attempt-{attempt}.log"))
+
+}
+
+/// Writes the outcome & output of `attempt` into its [attempt_path],
+/// nothing to be done if it fails.
+fn record_attempt(compiled: &CompiledTest, attempt: u32, result: &TestResult) {
+
+    let mut log = format!("attempt {attempt} of {}: {:?}\n", compiled.test.retries + 1, result.outcome);
+
+    if let Some(code) = result.code {
+        log += &format!("exit code: {code}\n");
+    }
+
+    for (name, captured) in [("stdout", &result.stdout), ("stderr", &result.stderr)] {
+        log += &format!("---- {name} ----\n{captured}\n");
+    }
+
+    _ = std::fs::write(attempt_path(compiled, attempt), log);
+
+}
+
+/// Runs `run` until it passes or [CompilableTest::retries] runs out,
+/// a test that passes on a retry is [Outcome::Flaky]. Every attempt of
+/// a test that had to be retried is recorded, see [record_attempt].
+fn retry(compiled: &CompiledTest, run: impl Fn() -> TestResult) -> TestResult {
+
+    let mut retries: u32 = 0;
+
+    loop {
+
+        let mut result = run();
+        result.retries = retries;
+
+        let failed = matches!(result.outcome, Outcome::Failed | Outcome::Crashed);
+
+        if failed || retries > 0 {
+            record_attempt(compiled, retries + 1, &result);
+        }
+
+        if !failed || retries == compiled.test.retries {
+            if result.outcome == Outcome::Passed && retries > 0 {
+                result.outcome = Outcome::Flaky;
+            }
+            return result
+        }
+
+        retries += 1;
+
+    }
+
+}
+
+/// Warning for a test that passed only after being retried.
+fn flaky_alert(compiled: &CompiledTest, result: &TestResult) -> Alert {
+
+    Alert::Warning(AlertInfo {
+        description: format!("test `{}` is flaky", result.name),
+        debug: debuginfo!(),
+        example: None,
+        note: vec![
+            format!("it passed on attempt {} of {}.", result.retries + 1, compiled.test.retries + 1),
+            format!("the output of every attempt is inside of `{}`.",
+                compiled.test.artifacts.with_extension("attempt-*.log").to_string_lossy()),
+            "`--fail-on-flaky` fails the run on flaky tests.".to_owned()
+        ]
+    })
+
+}
+
+/// Runs the harness of `compiled` with `argument`, used for
+/// running the file wide setup & teardown fixtures.
+fn run_fixture(compiled: &CompiledTest, fixture: &ParsedTest, argument: &str) -> TestResult {
+
+    execute(compiled, Some(argument), None, &fixture.function.name)
+
+}
+
+/// Runs the harness of `compiled`, either the file wide `fixture`
+/// or the test itself selected by its name, followed by `input`.
+fn execute(compiled: &CompiledTest, fixture: Option<&str>, input: Option<&str>, name: &str) -> TestResult {
+
+    let settings = &compiled.test.config.settings;
+    let artifacts = &compiled.test.artifacts;
+
+    let mut command = if compiled.test.valgrind {
+        super::valgrind::command(&compiled.binary, artifacts, &compiled.test.suppressions)
+    } else {
+        Command::new(&compiled.binary)
+    };
+    super::assert::prepare_report(&mut command, artifacts);
+    command.envs(super::sanitize::environment(&compiled.test.sanitizers));
+    if let Some(seed) = compiled.test.seed {
+        command.env(SEED_ENVIRONMENT, seed.to_string());
+    }
+    if let Some(toolchain) = compiled.test.coverage {
+        command.envs(toolchain.environment(&compiled.binary));
+    }
+    if let (Some(bench), None) = (compiled.test.bench.as_ref(), fixture) {
+        super::bench::prepare(&mut command, artifacts, bench);
+    }
+    if let (Some(_), None) = (compiled.test.property.as_ref(), fixture) {
+        super::property::prepare(&mut command, artifacts);
+    }
+    match fixture {
+        Some(argument) => command.arg(argument),
+        None => command.arg(&compiled.test.name).args(input)
+    };
+
+    let start = Instant::now();
+
+    let output = command
+        .stdin(if settings.stdin { Stdio::inherit() } else { Stdio::null() })
+        .stdout(if settings.stdout { Stdio::inherit() } else { Stdio::piped() })
+        .stderr(Stdio::piped())
+        .output();
+
+    let duration = start.elapsed();
+
+    let mut result = TestResult {
+        file:     compiled.test.file.clone(),
+        name:     name.to_owned(),
+        outcome:  Outcome::Crashed,
+        code:     None,
+        duration,
+        stdout:   String::new(),
+        stderr:   String::new(),
+        assertions: super::assert::read_report(artifacts),
+        sanitizer_reports: vec![],
+        valgrind_findings: vec![],
+        bench: None,
+        property: None,
+        retries: 0,
+        reason: None,
+        fingerprint: None
+    };
+
+    match output {
+        Ok(output) => {
+            result.code   = output.status.code();
+            result.stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            result.stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            result.outcome = match output.status.code() {
+                Some(0) => Outcome::Passed,
+                Some(HARNESS_FIXTURE_FAILURE_CODE) if fixture.is_none() => Outcome::FixtureFailed,
+                Some(_) => Outcome::Failed,
+                None    => Outcome::Crashed
+            };
+        }
+        Err(err) if compiled.test.valgrind => {
+            result.stderr = format!("failed to run `{}` under valgrind, is valgrind installed?: {}", 
+                compiled.binary.to_string_lossy(), err);
+        }
+        Err(err) => {
+            result.stderr = format!("failed to run `{}`: {}", compiled.binary.to_string_lossy(), err);
+        }
+    }
+
+    if compiled.test.bench.is_some() && fixture.is_none() {
+        result.bench = super::bench::read_report(artifacts);
+        if result.bench.is_none() && result.outcome == Outcome::Passed {
+            result.outcome = Outcome::Failed;
+            result.stderr += "the benchmark finished without writing any samples";
+        }
+    }
+
+    if compiled.test.valgrind {
+        result.valgrind_findings = super::valgrind::read_report(artifacts);
+        if !result.valgrind_findings.is_empty() && result.outcome == Outcome::Passed {
+            result.outcome = Outcome::Failed;
+        }
+    }
+
+    if !compiled.test.sanitizers.is_empty() {
+        result.sanitizer_reports = super::sanitize::parse_reports(&result.stderr);
+        if !result.sanitizer_reports.is_empty() && result.outcome == Outcome::Passed {
+            result.outcome = Outcome::Failed;
+        }
+    }
+
+    result
+
+}
+
+/// Alert for a failed setup/teardown fixture containing its
+/// exit code, failed assertions & captured output.
+fn fixture_alert(result: &TestResult, description: String) -> Alert {
+
+    let mut note = vec![match result.code {
+        Some(code) => format!("the fixture returned with exit code {code}"),
+        None       => "the fixture was killed by a signal".to_owned()
+    }];
+
+    for assertion in result.assertions.iter() {
+        note.push(format!("`{}`:{} {}({}) failed, expected {}, got {}",
+            assertion.file,
+            assertion.line,
+            assertion.assertion,
+            assertion.expression,
+            assertion.expected,
+            assertion.actual
+        ));
+    }
+
+    for (name, captured) in [("stdout", &result.stdout), ("stderr", &result.stderr)] {
+        if captured.trim().is_empty() {
+            continue;
+        }
+        note.push(format!("---- {name} ----"));
+        note.extend(captured.lines().map(String::from));
+    }
+
+    Alert::Error(AlertInfo {
+        description,
+        debug: debuginfo!(),
+        example: None,
+        note
+    })
+
+}
+
+/// Compiles & runs every test of a single file, printing the
+/// results as they finish. Every harness is compiled once, its
+/// binary is shared by all of the tests inside of it.
+/// 
+/// Fixtures
+/// --------
+/// The [Fixtures::setup] fixture runs once through the first
+/// compiled harness before any test, if it fails none of the tests
+/// run and they're all marked as [Outcome::FixtureFailed].
+/// 
+/// The [Fixtures::teardown] fixture runs once after all the tests,
+/// a failing teardown is added to the results as its own entry.
+/// 
+/// Tests with a [CompilableTest::skip] reason aren't compiled & are
+/// reported as [Outcome::Skipped], tests with a [CompilableTest::xfail]
+/// reason are reported as [Outcome::ExpectedFailure] if they fail.
+pub fn run_file(
+    compilable_tests: Vec<CompilableTest>, 
+    fixtures:         &Fixtures
+) -> Vec<TestResult> {
+
+    let mut results: Vec<TestResult> = vec![];
+    let mut compiled_tests: Vec<CompiledTest> = vec![];
+
+    // Binaries & fingerprints of the harnesses compiled so far,
+    // [None] if they failed to compile.
+    let mut binaries: Vec<(PathBuf, Option<(PathBuf, Option<String>)>)> = vec![];
+
+    for compilable_test in compilable_tests {
+
+        if let Some(reason) = compilable_test.skip.as_ref() {
+            let result = TestResult::skipped(&compilable_test, reason);
+            println!("{result}");
+            results.push(result);
+            continue
+        }
+
+        if compilable_test.compile_fail {
+            let result = compile_fail(&compilable_test);
+            println!("{result}");
+            results.push(result);
+            continue
+        }
+
+        let binary = match binaries.iter().find(|(path, _)| *path == compilable_test.path) {
+            Some((_, binary)) => binary.clone(),
+            None => {
+                let binary = match compilable_test.compile() {
+                    Ok(compiled_test) => Some((compiled_test.binary, compiled_test.fingerprint)),
+                    Err(alerts) => {
+                        for alert in alerts {
+                            eprintln!("{alert}");
+                        }
+                        None
+                    }
+                };
+                binaries.push((compilable_test.path.clone(), binary.clone()));
+                binary
+            }
+        };
+
+        match binary {
+            Some((binary, fingerprint)) => compiled_tests.push(CompiledTest {
+                test: compilable_test,
+                binary,
+                fingerprint
+            }),
+            None => {
+                let result = TestResult::compile_error(&compilable_test);
+                println!("{result}");
+                results.push(result);
+            }
+        }
+
+    }
+
+    let Some(first) = compiled_tests.first() else {
+        return results
+    };
+
+    if let Some(setup) = fixtures.setup.as_ref() {
+
+        let result = run_fixture(first, setup, HARNESS_SETUP_ARGUMENT);
+
+        if result.outcome != Outcome::Passed {
+
+            eprintln!("{}", fixture_alert(&result, format!(
+                "setup fixture `{}` of `{}` failed, skipping {} tests",
+                setup.function.name,
+                first.test.file.to_string_lossy(),
+                compiled_tests.len()
+            )));
+
+            for compiled_test in compiled_tests.iter() {
+                let result = TestResult {
+                    file:     compiled_test.test.file.clone(),
+                    name:     compiled_test.test.name.clone(),
+                    outcome:  Outcome::FixtureFailed,
+                    code:     None,
+                    duration: Duration::ZERO,
+                    stdout:   String::new(),
+                    stderr:   format!("not ran, setup fixture `{}` failed", setup.function.name),
+                    assertions: vec![],
+                    sanitizer_reports: vec![],
+                    valgrind_findings: vec![],
+                    bench: None,
+                    property: None,
+                    retries: 0,
+                    reason: None,
+                    fingerprint: None
+                };
+                println!("{result}");
+                results.push(result);
+            }
+
+            return results
+
+        }
+
+    }
+
+    for compiled_test in compiled_tests.iter() {
+
+        let test_results = if let Some(property) = compiled_test.test.property.as_ref() {
+            vec![retry(compiled_test, || run_property(compiled_test, property))]
+        } else if compiled_test.test.fuzz_inputs.is_empty() {
+            vec![retry(compiled_test, || run(compiled_test))]
+        } else {
+            replay(compiled_test)
+        };
+
+        for mut result in test_results {
+            result.fingerprint = compiled_test.fingerprint.clone();
+            if let Some(reason) = compiled_test.test.xfail.as_ref() {
+                result.outcome = match result.outcome {
+                    Outcome::Failed | Outcome::Crashed => Outcome::ExpectedFailure,
+                    Outcome::Passed | Outcome::Flaky   => Outcome::UnexpectedPass,
+                    outcome => outcome
+                };
+                result.reason = Some(reason.clone());
+            }
+            println!("{result}");
+            for assertion in result.assertions.iter() {
+                eprintln!("{}", assertion.to_alert(&result.name));
+            }
+            for report in result.sanitizer_reports.iter() {
+                eprintln!("{}", report.to_alert(&result.name));
+            }
+            if !result.valgrind_findings.is_empty() {
+                eprintln!("{}", super::valgrind::summary_alert(&result.valgrind_findings, &result.name));
+            }
+            if let Some(failure) = result.property.as_ref() {
+                eprintln!("{}", failure.to_alert(&result.name));
+            }
+            if result.outcome == Outcome::Flaky {
+                eprintln!("{}", flaky_alert(compiled_test, &result));
+            }
+            results.push(result);
+        }
+
+    }
+
+    if let Some(teardown) = fixtures.teardown.as_ref() {
+
+        let mut result = run_fixture(first, teardown, HARNESS_TEARDOWN_ARGUMENT);
+
+        if result.outcome != Outcome::Passed {
+
+            eprintln!("{}", fixture_alert(&result, format!(
+                "teardown fixture `{}` of `{}` failed",
+                teardown.function.name,
+                first.test.file.to_string_lossy()
+            )));
+
+            result.outcome = Outcome::FixtureFailed;
+            results.push(result);
+
+        }
+
+    }
+
+    results
+
+}
+
+impl std::fmt::Display for Outcome {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        write!(f, "{}", match self {
+            Outcome::Passed          => "ok",
+            Outcome::Failed          => "failed",
+            Outcome::Crashed         => "crashed",
+            Outcome::CompileError    => "compile error",
+            Outcome::FixtureFailed   => "fixture failed",
+            Outcome::Flaky           => "flaky",
+            Outcome::Skipped         => "skipped",
+            Outcome::ExpectedFailure => "xfail",
+            Outcome::UnexpectedPass  => "xpass"
+        })
+
+    }
+
+}
+
+impl std::fmt::Display for TestResult {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        let status = match self.outcome {
+            Outcome::Passed       => "ok".green().bold(),
+            Outcome::Failed       => "FAILED".red().bold(),
+            Outcome::Crashed      => "CRASHED".red().bold(),
+            Outcome::CompileError  => "COMPILE ERROR".red().bold(),
+            Outcome::FixtureFailed => "FIXTURE FAILED".red().bold(),
+            Outcome::Flaky         => "FLAKY".yellow().bold(),
+            Outcome::Skipped       => "SKIPPED".yellow().bold(),
+            Outcome::ExpectedFailure => "XFAIL".yellow().bold(),
+            Outcome::UnexpectedPass  => "XPASS".red().bold()
+        };
+
+        write!(f, "{} {} ... {}",
+            if self.bench.is_some() { "bench" } else { "test" },
+            self.identity(),
+            status
+        )?;
+
+        if let Some(bench) = self.bench.as_ref() {
+            write!(f, "\n    {bench}")?;
+        }
+
+        if let Some(reason) = self.reason.as_ref() {
+            write!(f, " {}", format!("({reason})").dimmed())?;
+        }
+
+        if matches!(self.outcome, Outcome::Skipped | Outcome::ExpectedFailure) {
+            return Ok(())
+        }
+
+        if self.outcome == Outcome::UnexpectedPass {
+            return write!(f, "\n{}", "    expected to fail but passed, remove `xfail` if it was fixed".dimmed())
+        }
+
+        if self.outcome == Outcome::Flaky {
+            return write!(f, "\n{}", format!("    passed on attempt {}", self.retries + 1).dimmed())
+        }
+
+        if self.outcome == Outcome::Passed {
+            return Ok(())
+        }
+
+        if self.retries > 0 {
+            write!(f, "\n{}", format!("    failed all {} attempts", self.retries + 1).dimmed())?;
+        }
+
+        if let Some(code) = self.code {
+            write!(f, "\n{}", format!("    exit code: {code}").dimmed())?;
+        }
+
+        for (name, captured) in [("stdout", &self.stdout), ("stderr", &self.stderr)] {
+            if captured.trim().is_empty() {
+                continue;
+            }
+            write!(f, "\n{}", format!("    ---- {name} ----").dimmed())?;
+            for line in captured.lines() {
+                write!(f, "\n    {line}")?;
+            }
+        }
+
+        Ok(())
+
+    }
+
+}
+
+impl Summary {
+
+    /// Counts `results`, the tests inside of `quarantine`
+    /// are only listed inside of [Summary::quarantined].
+    pub fn from_results(results: &[TestResult], quarantine: &[String]) -> Self {
+
+        let mut summary = Summary::default();
+
+        for result in results.iter() {
+            summary.duration += result.duration;
+            if quarantine.contains(&result.identity()) {
+                summary.quarantined.push((result.identity(), result.outcome));
+                continue
+            }
+            match result.outcome {
+                Outcome::Passed       => summary.passed += 1,
+                Outcome::Failed       => summary.failed += 1,
+                Outcome::Crashed      => summary.crashed += 1,
+                Outcome::CompileError  => summary.compile_error += 1,
+                Outcome::FixtureFailed => summary.fixture_failed += 1,
+                Outcome::Flaky         => summary.flaky += 1,
+                Outcome::Skipped       => summary.skipped += 1,
+                Outcome::ExpectedFailure => summary.expected_failure += 1,
+                Outcome::UnexpectedPass  => summary.unexpected_pass += 1
+            }
+            if let Some(reason) = result.reason.as_ref() {
+                summary.reasons.push((result.identity(), result.outcome, reason.clone()));
+            }
+        }
+
+        summary
+
+    }
+
+    /// Amount of tests that did not pass.
+    pub fn unsuccessful(&self) -> usize {
+        self.failed + self.crashed + self.compile_error + self.fixture_failed + self.unexpected_pass
+    }
+
+}
+
+impl std::fmt::Display for Summary {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        let result = if self.unsuccessful() == 0 {
+            "ok".green().bold()
+        } else {
+            "FAILED".red().bold()
+        };
+
+        for (title, outcome) in [
+            ("skipped", Outcome::Skipped),
+            ("expected failures", Outcome::ExpectedFailure),
+            ("unexpected passes", Outcome::UnexpectedPass)
+        ] {
+            let mut reasons = self.reasons.iter().filter(|(_, reason_outcome, _)| *reason_outcome == outcome).peekable();
+            if reasons.peek().is_none() {
+                continue
+            }
+            write!(f, "\n{}:", title.bold())?;
+            for (identity, _, reason) in reasons {
+                write!(f, "\n    {identity} - {reason}")?;
+            }
+        }
+
+        if !self.quarantined.is_empty() {
+            write!(f, "\n{}:", "quarantined".bold())?;
+            for (identity, outcome) in self.quarantined.iter() {
+                write!(f, "\n    {identity} ({outcome})")?;
+            }
+        }
+
+        if !self.reasons.is_empty() || !self.quarantined.is_empty() {
+            writeln!(f)?;
+        }
+
+        write!(f, "\ntest result: {}. {} passed; {} flaky; {} failed; {} crashed; {} failed to compile; {} fixtures failed; {} skipped; {} xfail; {} xpass; {} quarantined; finished in {:.2}s",
+            result,
+            self.passed,
+            self.flaky,
+            self.failed,
+            self.crashed,
+            self.compile_error,
+            self.fixture_failed,
+            self.skipped,
+            self.expected_failure,
+            self.unexpected_pass,
+            self.quarantined.len(),
+            self.duration.as_secs_f64()
+        )?;
+
+        if let Some(seed) = self.seed {
+            write!(f, "\nshuffled with seed {seed}, rerun in the same order with `--seed {seed}`")?;
+        }
+
+        Ok(())
+
+    }
+
+}
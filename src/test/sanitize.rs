@@ -0,0 +1,521 @@
+//! Compiling & running tests with sanitizers and parsing
+//! the reports they write into stderr.
+//!
+//! Supported reports
+//! -----------------
+//! - AddressSanitizer & LeakSanitizer: `==PID==ERROR: AddressSanitizer: <kind> ...`
+//! - ThreadSanitizer: `WARNING: ThreadSanitizer: <kind> (pid=...)`
+//! - UndefinedBehaviorSanitizer: `<file>:<line>:<column>: runtime error: <message>`
+//!
+//! Each followed by stack frames like `#0 0x... in <function> <file>:<line>:<column>`.
+
+use std::path::{Component, Path};
+
+use crate::{
+    arg_conf::{Recipe, Run, Sanitizer},
+    defaults::DEFAULT_PRIVATE_DIRECTORY,
+    error::{
+        debuginfo, warning, Alert, AlertCode,
+        AlertCodeFix, AlertExample, AlertInfo
+    }
+};
+
+use super::Config;
+
+/// A stack frame of a sanitizer report.
+#[derive(Clone, Debug)]
+pub struct Frame {
+
+    /// Function the frame is inside of.
+    pub function: String,
+
+    pub file: String,
+
+    pub line: usize,
+
+    /// `0` if the sanitizer didn't report a column.
+    pub column: usize
+
+}
+
+/// A single error reported by a sanitizer.
+#[derive(Clone, Debug)]
+pub struct SanitizerReport {
+
+    pub sanitizer: Sanitizer,
+
+    /// Error kind, like `heap-buffer-overflow` or `data race`.
+    pub kind: String,
+
+    /// What happened, the kind with the access or leak
+    /// description that follows the first line.
+    pub message: String,
+
+    /// Top stack frame inside of the project, [None]
+    /// if no frame was inside of the project.
+    pub frame: Option<Frame>
+
+}
+
+/// Sanitizers used for a test, the test config takes precedence over the
+/// file config, which takes precedence over [Recipe::sanitize] & [Run::sanitize].
+///
+/// [Sanitizer::Thread] can't be combined with [Sanitizer::Address] or
+/// [Sanitizer::Leak], if it is it's dropped with a warning.
+pub fn resolve(
+    run_conf:    &Run,
+    recipe:      Option<&Recipe>,
+    file_config: &Config,
+    config:      &Config
+) -> (Vec<Sanitizer>, Option<Alert>) {
+
+    let mut sanitizers = config.settings.sanitize.clone()
+        .or(file_config.settings.sanitize.clone())
+        .or(recipe.filter(|recipe| !recipe.sanitize.is_empty()).map(|recipe| recipe.sanitize.clone()))
+        .unwrap_or(run_conf.sanitize.clone());
+
+    let mut unique: Vec<Sanitizer> = vec![];
+    sanitizers.retain(|sanitizer| {
+        if unique.contains(sanitizer) {
+            return false
+        }
+        unique.push(*sanitizer);
+        true
+    });
+
+    if sanitizers.contains(&Sanitizer::Thread)
+    && (sanitizers.contains(&Sanitizer::Address) || sanitizers.contains(&Sanitizer::Leak))
+    {
+        sanitizers.retain(|sanitizer| *sanitizer != Sanitizer::Thread);
+        return (sanitizers, Some(warning!{
+            debug: debuginfo!(),
+            description: "the `thread` sanitizer can't be combined with `address` or `leak`".to_owned(),
+            example: None,
+            note: vec![
+                "the `thread` sanitizer is ignored, run it in a separate recipe or with `--sanitize thread`.".to_owned()
+            ]
+        }))
+    }
+
+    (sanitizers, None)
+
+}
+
+/// Compile & link flags for `sanitizers`, the same for gcc & clang.
+pub fn flags(sanitizers: &[Sanitizer]) -> Vec<String> {
+
+    if sanitizers.is_empty() {
+        return vec![]
+    }
+
+    vec![
+        format!("-fsanitize={}", sanitizers
+            .iter()
+            .map(|sanitizer| sanitizer.to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+        ),
+        "-fno-omit-frame-pointer".to_owned(),
+        "-g".to_owned()
+    ]
+
+}
+
+/// `*SAN_OPTIONS` environment variables for running a test with
+/// `sanitizers`, options already set in the environment are kept
+/// & take precedence.
+pub fn environment(sanitizers: &[Sanitizer]) -> Vec<(String, String)> {
+
+    let mut variables: Vec<(&str, String)> = vec![];
+
+    let leaks = if sanitizers.contains(&Sanitizer::Leak) { 1 } else { 0 };
+
+    if sanitizers.contains(&Sanitizer::Address) {
+        variables.push(("ASAN_OPTIONS", format!("detect_leaks={leaks}:halt_on_error=1:abort_on_error=0:symbolize=1")));
+    }
+
+    if sanitizers.contains(&Sanitizer::Leak) {
+        variables.push(("LSAN_OPTIONS", "symbolize=1".to_owned()));
+    }
+
+    if sanitizers.contains(&Sanitizer::Undefined) {
+        variables.push(("UBSAN_OPTIONS", "print_stacktrace=1:halt_on_error=1".to_owned()));
+    }
+
+    if sanitizers.contains(&Sanitizer::Thread) {
+        variables.push(("TSAN_OPTIONS", "halt_on_error=1:symbolize=1".to_owned()));
+    }
+
+    variables
+        .into_iter()
+        .map(|(name, options)| match std::env::var(name) {
+            Ok(existing) if !existing.is_empty() => (name.to_owned(), format!("{options}:{existing}")),
+            _ => (name.to_owned(), options)
+        })
+        .collect()
+
+}
+
+/// Is `file` part of the project, inside of the current pwd
+/// & not inside of a [DEFAULT_PRIVATE_DIRECTORY].
+pub(super) fn in_project(file: &str) -> bool {
+
+    let path = Path::new(file);
+
+    let inside = match std::env::current_dir() {
+        Ok(pwd) if path.is_relative() => pwd.join(path).exists(),
+        Ok(pwd) => path.starts_with(pwd),
+        Err(_)  => true
+    };
+
+    inside && !path.components().any(|component| 
+        component == Component::ParentDir 
+        || component.as_os_str() == DEFAULT_PRIVATE_DIRECTORY
+    )
+
+}
+
+/// Splits `file:line[:column]` into its parts.
+fn location(text: &str) -> Option<(String, usize, usize)> {
+
+    let mut parts = text.rsplitn(3, ':');
+    let last   = parts.next()?;
+    let middle = parts.next()?;
+
+    match (middle.parse::<usize>(), last.parse::<usize>(), parts.next()) {
+        (Ok(line), Ok(column), Some(file)) => Some((file.to_owned(), line, column)),
+        (_, Ok(line), _) => {
+            let file = text[..text.len() - last.len() - 1].to_owned();
+            Some((file, line, 0))
+        }
+        _ => None
+    }
+
+}
+
+/// Parses a frame line, `#0 0x... in <function> <file>:<line>:<column>`
+/// from ASan or `#0 <function> <file>:<line>:<column> (<module>+0x...)`
+/// from TSan.
+fn frame(line: &str) -> Option<Frame> {
+
+    let line = line.trim_start();
+
+    if !line.starts_with('#') {
+        return None
+    }
+
+    let (_, rest) = line.split_once(' ')?;
+    let rest = match rest.split_once(" in ") {
+        Some((address, rest)) if address.starts_with("0x") => rest,
+        _ => rest
+    };
+    let (function, place) = rest.trim_start().split_once(' ')?;
+
+    if place.starts_with('(') {
+        return None
+    }
+
+    let place = match place.rsplit_once(" (") {
+        Some((place, _)) => place,
+        None => place
+    };
+
+    let (file, line, column) = location(place.trim())?;
+
+    Some(Frame {
+        function: function.to_owned(),
+        file,
+        line,
+        column
+    })
+
+}
+
+/// Start of a report, the sanitizer & kind.
+fn header(line: &str) -> Option<(Sanitizer, String)> {
+
+    for (marker, sanitizer) in [
+        ("AddressSanitizer: ", Sanitizer::Address),
+        ("LeakSanitizer: ", Sanitizer::Leak),
+        ("ThreadSanitizer: ", Sanitizer::Thread)
+    ] {
+        if (line.contains("ERROR: ") || line.contains("WARNING: ")) && line.contains(marker) {
+            let kind = line.split_once(marker).unwrap().1;
+            let kind = kind
+                .split(" on ")
+                .next()
+                .unwrap_or(kind)
+                .split(" (")
+                .next()
+                .unwrap_or(kind)
+                .trim();
+            return Some((sanitizer, kind.to_owned()))
+        }
+    }
+
+    None
+
+}
+
+/// Parses every sanitizer report inside of `stderr`.
+pub fn parse_reports(stderr: &str) -> Vec<SanitizerReport> {
+
+    let mut reports: Vec<SanitizerReport> = vec![];
+
+    for line in stderr.lines() {
+
+        if let Some((sanitizer, kind)) = header(line) {
+            reports.push(SanitizerReport {
+                sanitizer,
+                message: kind.clone(),
+                kind,
+                frame: None
+            });
+            continue;
+        }
+
+        let trimmed = line.trim();
+
+        if ["READ of", "WRITE of", "Read of", "Write of", "Atomic", "Direct leak", "Indirect leak"]
+            .iter()
+            .any(|start| trimmed.starts_with(start))
+        {
+            if let Some(report) = reports.last_mut().filter(|report| report.message == report.kind) {
+                let detail = trimmed
+                    .split(" at 0x")
+                    .next()
+                    .unwrap_or(trimmed)
+                    .trim_end_matches(" allocated from:")
+                    .trim_end_matches(':');
+                report.message = format!("{}, {}", report.kind, detail);
+            }
+            continue;
+        }
+
+        if let Some((place, message)) = line.split_once(": runtime error: ") {
+            let frame = location(place).map(|(file, line, column)| Frame {
+                function: String::new(),
+                file,
+                line,
+                column
+            });
+            reports.push(SanitizerReport {
+                sanitizer: Sanitizer::Undefined,
+                kind: message.split(':').next().unwrap_or(message).trim().to_owned(),
+                message: format!("runtime error: {message}"),
+                frame: frame.filter(|frame| in_project(&frame.file))
+            });
+            continue;
+        }
+
+        if let Some(frame) = frame(line) {
+            if let Some(report) = reports.last_mut() {
+                if report.frame.is_none() && in_project(&frame.file) {
+                    report.frame = Some(frame);
+                }
+            }
+        }
+
+    }
+
+    reports
+
+}
+
+impl Frame {
+
+    /// Excerpt of the frames file pointing at the frames line with
+    /// `comment`, [None] if the file can't be read.
+    pub fn excerpt(&self, comment: String) -> Option<AlertExample> {
+
+        let contents = std::fs::read_to_string(&self.file).ok()?;
+
+        if self.line == 0 {
+            return None
+        }
+
+        let column = if self.column > 0 {
+            self.column
+        } else {
+            contents
+                .lines()
+                .nth(self.line - 1)
+                .and_then(|line| line.find(|c: char| !c.is_whitespace()))
+                .map(|column| column + 1)
+                .unwrap_or(1)
+        };
+
+        Some(AlertExample::Code(AlertCode {
+            line: self.line,
+            file: self.file.clone(),
+            code: contents,
+            fix: vec![AlertCodeFix {
+                relative_line: self.line - 1,
+                column,
+                comment
+            }]
+        }))
+
+    }
+
+}
+
+impl SanitizerReport {
+
+    /// Renders the report with an excerpt of the top in project frame.
+    pub fn to_alert(&self, test: &str) -> Alert {
+
+        let description = format!("{} sanitizer reported `{}` in test `{test}`", self.sanitizer, self.kind);
+
+        let example = self.frame.as_ref().and_then(|frame| frame.excerpt(self.message.clone()));
+
+        let mut note = vec![];
+
+        if example.is_none() {
+            note.push(self.message.clone());
+        }
+
+        if let Some(frame) = self.frame.as_ref().filter(|frame| !frame.function.is_empty()) {
+            note.push(format!("inside of `{}`, see the test output for the full stack trace.", frame.function));
+        }
+
+        Alert::Error(AlertInfo {
+            description,
+            debug: debuginfo!(),
+            example,
+            note
+        })
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::arg_conf::Sanitizer;
+
+    use super::parse_reports;
+
+    // Frames have to point at files that exist relative to the pwd to
+    // count as inside of the project, tests are ran from the crate root.
+
+    #[test]
+    fn parses_address_sanitizer_report() {
+
+        let reports = parse_reports(concat!(
+            "=================================================================\n",
+            "==4182==ERROR: AddressSanitizer: heap-buffer-overflow on address 0x602000000014 at pc 0x55d5c8a1b2c3 bp 0x7ffc sp 0x7ffc\n",
+            "READ of size 4 at 0x602000000014 thread T0\n",
+            "    #0 0x55d5c8a1b2c2 in cesty_overflow src/main.rs:42:12\n",
+            "    #1 0x55d5c8a1b3f0 in main src/main.rs:90:5\n",
+            "    #2 0x7f1e2a229d8f in __libc_start_call_main ../sysdeps/nptl/libc_start_call_main.h:58:16\n",
+            "\n",
+            "0x602000000014 is located 0 bytes to the right of 4-byte region [0x602000000010,0x602000000014)\n",
+            "allocated by thread T0 here:\n",
+            "    #0 0x7f1e2a4b4887 in __interceptor_malloc (/lib/x86_64-linux-gnu/libasan.so.6+0xb4887)\n",
+            "SUMMARY: AddressSanitizer: heap-buffer-overflow src/main.rs:42:12 in cesty_overflow\n"
+        ));
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].sanitizer, Sanitizer::Address);
+        assert_eq!(reports[0].kind, "heap-buffer-overflow");
+        assert_eq!(reports[0].message, "heap-buffer-overflow, READ of size 4");
+
+        let frame = reports[0].frame.as_ref().unwrap();
+        assert_eq!(frame.function, "cesty_overflow");
+        assert_eq!(frame.file, "src/main.rs");
+        assert_eq!((frame.line, frame.column), (42, 12));
+
+    }
+
+    #[test]
+    fn parses_leak_sanitizer_report() {
+
+        let reports = parse_reports(concat!(
+            "==77==ERROR: LeakSanitizer: detected memory leaks\n",
+            "\n",
+            "Direct leak of 16 byte(s) in 1 object(s) allocated from:\n",
+            "    #0 0x7f3c in __interceptor_malloc (/lib/x86_64-linux-gnu/libasan.so.6+0xb4887)\n",
+            "    #1 0x55aa in cesty_leak src/main.rs:7:17\n",
+            "\n",
+            "SUMMARY: AddressSanitizer: 16 byte(s) leaked in 1 allocation(s).\n"
+        ));
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].sanitizer, Sanitizer::Leak);
+        assert_eq!(reports[0].kind, "detected memory leaks");
+        assert_eq!(reports[0].message, "detected memory leaks, Direct leak of 16 byte(s) in 1 object(s)");
+        assert_eq!(reports[0].frame.as_ref().unwrap().line, 7);
+
+    }
+
+    #[test]
+    fn parses_thread_sanitizer_report() {
+
+        let reports = parse_reports(concat!(
+            "==================\n",
+            "WARNING: ThreadSanitizer: data race (pid=9001)\n",
+            "  Write of size 4 at 0x55d0 by thread T2:\n",
+            "    #0 increment src/main.rs:12:13 (harness+0x1234)\n",
+            "\n",
+            "  Previous read of size 4 at 0x55d0 by thread T1:\n",
+            "    #0 increment src/main.rs:12:9 (harness+0x1200)\n",
+            "==================\n"
+        ));
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].sanitizer, Sanitizer::Thread);
+        assert_eq!(reports[0].kind, "data race");
+        assert_eq!(reports[0].message, "data race, Write of size 4");
+        let frame = reports[0].frame.as_ref().unwrap();
+        assert_eq!(frame.function, "increment");
+        assert_eq!((frame.file.as_str(), frame.line, frame.column), ("src/main.rs", 12, 13));
+
+    }
+
+    #[test]
+    fn parses_undefined_behavior_reports() {
+
+        let reports = parse_reports(concat!(
+            "src/main.rs:31:14: runtime error: signed integer overflow: 2147483647 + 1 cannot be represented in type 'int'\n",
+            "/usr/include/stdlib.h:5:1: runtime error: shift exponent 40 is too large for 32-bit type 'int'\n"
+        ));
+
+        assert_eq!(reports.len(), 2);
+
+        assert_eq!(reports[0].sanitizer, Sanitizer::Undefined);
+        assert_eq!(reports[0].kind, "signed integer overflow");
+        assert!(reports[0].message.starts_with("runtime error: signed integer overflow: 2147483647 + 1"));
+        let frame = reports[0].frame.as_ref().unwrap();
+        assert_eq!((frame.file.as_str(), frame.line, frame.column), ("src/main.rs", 31, 14));
+
+        // Outside of the project, reported without a location.
+        assert!(reports[1].frame.is_none());
+
+    }
+
+    #[test]
+    fn frames_outside_of_the_project_are_skipped() {
+
+        let reports = parse_reports(concat!(
+            "==5==ERROR: AddressSanitizer: SEGV on unknown address 0x000000000000 (pc 0x1 bp 0x2 sp 0x3 T0)\n",
+            "    #0 0x1 in strlen ../sysdeps/x86_64/multiarch/strlen-avx2.S:65\n",
+            "    #1 0x2 in helper .cesty/batch/harness.c:10:3\n",
+            "    #2 0x3 in cesty_segv src/main.rs:3:5\n"
+        ));
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].kind, "SEGV");
+        assert_eq!(reports[0].frame.as_ref().unwrap().function, "cesty_segv");
+
+    }
+
+    #[test]
+    fn ignores_other_output() {
+
+        assert!(parse_reports("running cesty_a\nok\n    #0 0x1 in main src/main.rs:1:1\n").is_empty());
+
+    }
+
+}
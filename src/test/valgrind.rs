@@ -0,0 +1,421 @@
+//! Running tests under valgrind's memcheck & parsing the
+//! XML report it writes.
+//!
+//! Every test is ran as:
+//! ```text
+//! valgrind --tool=memcheck --leak-check=full --error-exitcode=<code>
+//!          --xml=yes --xml-file=<test>.valgrind.xml [--suppressions=<file>...]
+//!          <binary> <test|fixture> [input]
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command
+};
+
+use crate::{
+    defaults::VALGRIND_ERROR_EXIT_CODE,
+    error::{
+        debuginfo, Alert, AlertCode,
+        AlertExample, AlertInfo
+    }
+};
+
+use super::sanitize::{in_project, Frame};
+
+/// A single error memcheck reported.
+#[derive(Clone, Debug)]
+pub struct ValgrindFinding {
+
+    /// Memcheck error kind, like `InvalidRead` or `Leak_DefinitelyLost`.
+    pub kind: String,
+
+    /// Description of the error, like `Invalid read of size 4`.
+    pub what: String,
+
+    /// Bytes lost for leaks, [None] for other errors.
+    pub leaked_bytes: Option<usize>,
+
+    /// Top frame of the first stack inside of the project.
+    pub frame: Option<Frame>
+
+}
+
+/// XML report file of the test with `artifacts`, see
+/// [super::compilable::CompilableTest::artifacts].
+pub fn report_path(artifacts: &Path) -> PathBuf {
+
+    artifacts.with_extension("valgrind.xml")
+
+}
+
+/// Command running `binary` under memcheck with `suppressions`,
+/// reporting into the [report_path] of `artifacts`.
+pub fn command(binary: &Path, artifacts: &Path, suppressions: &[String]) -> Command {
+
+    let report = report_path(artifacts);
+    _ = std::fs::remove_file(&report);
+
+    let mut command = Command::new("valgrind");
+
+    command
+        .arg("--tool=memcheck")
+        .arg("--leak-check=full")
+        .arg(format!("--error-exitcode={VALGRIND_ERROR_EXIT_CODE}"))
+        .arg("--xml=yes")
+        .arg(format!("--xml-file={}", report.to_string_lossy()))
+        .args(suppressions.iter().map(|file| format!("--suppressions={file}")))
+        .arg(binary);
+
+    command
+
+}
+
+fn child<'a, 'input>(node: roxmltree::Node<'a, 'input>, name: &str) -> Option<roxmltree::Node<'a, 'input>> {
+
+    node.children().find(|child| child.has_tag_name(name))
+
+}
+
+fn text_of(node: roxmltree::Node, name: &str) -> Option<String> {
+
+    child(node, name).map(|child| child.text().unwrap_or_default().trim().to_owned())
+
+}
+
+fn frame(node: roxmltree::Node) -> Option<Frame> {
+
+    let file = text_of(node, "file")?;
+    let file = match text_of(node, "dir") {
+        Some(dir) => Path::new(&dir).join(file).to_string_lossy().to_string(),
+        None      => file
+    };
+
+    Some(Frame {
+        function: text_of(node, "fn").unwrap_or_default(),
+        file,
+        line: text_of(node, "line")?.parse().ok()?,
+        column: 0
+    })
+
+}
+
+/// Parses the findings out of memcheck's XML output. Suppressed errors
+/// are only counted inside of `<suppcounts>` & never reported.
+///
+/// A test killed on a timeout leaves the report unfinished, in which
+/// case everything up to the last complete `<error>` is read.
+pub fn parse_report(contents: &str) -> Vec<ValgrindFinding> {
+
+    let truncated;
+    let document = match roxmltree::Document::parse(contents) {
+        Ok(document) => document,
+        Err(_) => {
+            let Some(end) = contents.rfind("</error>") else {
+                return vec![]
+            };
+            truncated = format!("{}</error></valgrindoutput>", &contents[..end]);
+            match roxmltree::Document::parse(&truncated) {
+                Ok(document) => document,
+                Err(_)       => return vec![]
+            }
+        }
+    };
+
+    let output = document.root_element();
+
+    if !output.has_tag_name("valgrindoutput") {
+        return vec![]
+    }
+
+    output
+        .children()
+        .filter(|node| node.has_tag_name("error"))
+        .map(|error| {
+
+            let xwhat = child(error, "xwhat");
+
+            ValgrindFinding {
+                kind: text_of(error, "kind").unwrap_or_default(),
+                what: text_of(error, "what")
+                    .or(xwhat.and_then(|xwhat| text_of(xwhat, "text")))
+                    .unwrap_or_default(),
+                leaked_bytes: xwhat
+                    .and_then(|xwhat| text_of(xwhat, "leakedbytes"))
+                    .and_then(|bytes| bytes.parse().ok()),
+                frame: child(error, "stack")
+                    .and_then(|stack| stack
+                        .children()
+                        .filter(|node| node.has_tag_name("frame"))
+                        .filter_map(frame)
+                        .find(|frame| in_project(&frame.file))
+                    )
+            }
+
+        })
+        .collect()
+
+}
+
+/// Reads the findings of the last run of `artifacts`, empty if
+/// it wasn't ran under valgrind or nothing was found.
+pub fn read_report(artifacts: &Path) -> Vec<ValgrindFinding> {
+
+    match std::fs::read_to_string(report_path(artifacts)) {
+        Ok(contents) => parse_report(&contents),
+        Err(_)       => vec![]
+    }
+
+}
+
+impl ValgrindFinding {
+
+    pub fn is_leak(&self) -> bool {
+        self.kind.starts_with("Leak_")
+    }
+
+}
+
+/// Summarises all the `findings` of a test into a single alert, every
+/// finding inside of the same file as the first located finding is
+/// pointed at inside of the excerpt, others are listed in the notes.
+pub fn summary_alert(findings: &[ValgrindFinding], test: &str) -> Alert {
+
+    let leaks: Vec<&ValgrindFinding> = findings.iter().filter(|finding| finding.is_leak()).collect();
+    let errors = findings.len() - leaks.len();
+    let leaked: usize = leaks.iter().filter_map(|leak| leak.leaked_bytes).sum();
+
+    let description = format!("valgrind found {errors} memory error(s) & {} leak(s) ({leaked} bytes) in test `{test}`",
+        leaks.len());
+
+    let mut example: Option<AlertExample> = None;
+    let mut note: Vec<String> = vec![];
+
+    for finding in findings.iter() {
+
+        let Some(frame) = finding.frame.as_ref() else {
+            note.push(format!("{} (no location inside of the project)", finding.what));
+            continue;
+        };
+
+        match example.as_mut() {
+            None => match frame.excerpt(finding.what.clone()) {
+                Some(excerpt) => example = Some(excerpt),
+                None => note.push(format!("`{}`:{} {}", frame.file, frame.line, finding.what))
+            },
+            Some(AlertExample::Code(AlertCode { file, fix, .. })) if *file == frame.file => {
+                match frame.excerpt(finding.what.clone()) {
+                    Some(AlertExample::Code(mut code)) => fix.append(&mut code.fix),
+                    _ => note.push(format!("`{}`:{} {}", frame.file, frame.line, finding.what))
+                }
+            }
+            Some(_) => note.push(format!("`{}`:{} {}", frame.file, frame.line, finding.what))
+        }
+
+    }
+
+    Alert::Error(AlertInfo {
+        description,
+        debug: debuginfo!(),
+        example,
+        note
+    })
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::parse_report;
+
+    /// Memcheck's XML output around `errors`, frames inside of `{dir}`
+    /// point at the sources of this crate & count as inside of the project.
+    fn report(errors: &str) -> String {
+
+        let body = format!(r#"<?xml version="1.0"?>
+
+<valgrindoutput>
+
+<protocolversion>4</protocolversion>
+<protocoltool>memcheck</protocoltool>
+
+<preamble>
+  <line>Memcheck, a memory error detector</line>
+  <line>Copyright (C) 2002-2022, and GNU GPL'd, by Julian Seward et al.</line>
+</preamble>
+
+<pid>31337</pid>
+<ppid>31336</ppid>
+<tool>memcheck</tool>
+
+<args>
+  <vargv>
+    <exe>/usr/bin/valgrind</exe>
+    <arg>--tool=memcheck</arg>
+    <arg>--leak-check=full</arg>
+  </vargv>
+  <argv>
+    <exe>./.cesty/batch/ds</exe>
+    <arg>cesty_a</arg>
+  </argv>
+</args>
+
+<status>
+  <state>RUNNING</state>
+  <time>00:00:00:00.041 </time>
+</status>
+
+{errors}
+
+<status>
+  <state>FINISHED</state>
+  <time>00:00:00:00.512 </time>
+</status>
+
+<errorcounts>
+</errorcounts>
+
+<suppcounts>
+</suppcounts>
+
+</valgrindoutput>
+"#);
+
+        body.replace("{dir}", concat!(env!("CARGO_MANIFEST_DIR"), "/src"))
+
+    }
+
+    const INVALID_READ: &str = r#"<error>
+  <unique>0x0</unique>
+  <tid>1</tid>
+  <kind>InvalidRead</kind>
+  <what>Invalid read of size 4</what>
+  <stack>
+    <frame>
+      <ip>0x109182</ip>
+      <obj>/tmp/.cesty/batch/ds</obj>
+      <fn>cesty_a</fn>
+      <dir>{dir}</dir>
+      <file>main.rs</file>
+      <line>12</line>
+    </frame>
+    <frame>
+      <ip>0x1091B4</ip>
+      <obj>/tmp/.cesty/batch/ds</obj>
+      <fn>main</fn>
+      <dir>{dir}</dir>
+      <file>lib.rs</file>
+      <line>40</line>
+    </frame>
+  </stack>
+  <auxwhat>Address 0x4a8d054 is 0 bytes after a block of size 20 alloc'd</auxwhat>
+</error>"#;
+
+    const DEFINITELY_LOST: &str = r#"<error>
+  <unique>0x1</unique>
+  <tid>1</tid>
+  <kind>Leak_DefinitelyLost</kind>
+  <xwhat>
+    <text>24 bytes in 1 blocks are definitely lost in loss record 1 of 1</text>
+    <leakedbytes>24</leakedbytes>
+    <leakedblocks>1</leakedblocks>
+  </xwhat>
+  <stack>
+    <frame>
+      <ip>0x4848899</ip>
+      <obj>/usr/libexec/valgrind/vgpreload_memcheck-amd64-linux.so</obj>
+      <fn>malloc</fn>
+      <dir>./coregrind/m_replacemalloc</dir>
+      <file>vg_replace_malloc.c</file>
+      <line>381</line>
+    </frame>
+    <frame>
+      <ip>0x1091A3</ip>
+      <obj>/tmp/.cesty/batch/ds</obj>
+      <fn>ds_new</fn>
+      <dir>{dir}</dir>
+      <file>main.rs</file>
+      <line>7</line>
+    </frame>
+  </stack>
+</error>"#;
+
+    #[test]
+    fn parses_invalid_read() {
+
+        let findings = parse_report(&report(INVALID_READ));
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, "InvalidRead");
+        assert_eq!(findings[0].what, "Invalid read of size 4");
+        assert_eq!(findings[0].leaked_bytes, None);
+        assert!(!findings[0].is_leak());
+
+        let frame = findings[0].frame.as_ref().unwrap();
+        assert_eq!(frame.function, "cesty_a");
+        assert_eq!(frame.file, concat!(env!("CARGO_MANIFEST_DIR"), "/src/main.rs"));
+        assert_eq!(frame.line, 12);
+
+    }
+
+    #[test]
+    fn parses_definitely_lost_leak() {
+
+        let findings = parse_report(&report(DEFINITELY_LOST));
+
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].is_leak());
+        assert_eq!(findings[0].what, "24 bytes in 1 blocks are definitely lost in loss record 1 of 1");
+        assert_eq!(findings[0].leaked_bytes, Some(24));
+
+        // `malloc` inside of valgrind is skipped for the first project frame.
+        let frame = findings[0].frame.as_ref().unwrap();
+        assert_eq!(frame.function, "ds_new");
+        assert_eq!(frame.line, 7);
+
+    }
+
+    #[test]
+    fn suppressed_errors_are_not_reported() {
+
+        // Suppressed errors only show up as counts.
+        let contents = report("").replace("<suppcounts>\n</suppcounts>", r#"<suppcounts>
+  <pair>
+    <count>3</count>
+    <name>ds_cache_reachable</name>
+  </pair>
+</suppcounts>"#);
+
+        assert!(parse_report(&contents).is_empty());
+
+    }
+
+    #[test]
+    fn reads_truncated_report_up_to_the_last_error() {
+
+        let contents = report(&format!("{INVALID_READ}\n{DEFINITELY_LOST}"));
+        let cut = contents.find("<kind>Leak_DefinitelyLost").unwrap();
+
+        let findings = parse_report(&contents[..cut]);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, "InvalidRead");
+
+    }
+
+    #[test]
+    fn unescapes_entities() {
+
+        let contents = report(&INVALID_READ.replace(
+            "<fn>cesty_a</fn>",
+            "<fn>operator delete(void*, unsigned long) &amp; friends&lt;int&gt;</fn>"
+        ));
+
+        let findings = parse_report(&contents);
+
+        assert_eq!(findings[0].frame.as_ref().unwrap().function,
+            "operator delete(void*, unsigned long) & friends<int>");
+
+    }
+
+}